@@ -1,9 +1,14 @@
 extern crate gutenberg_post_parser as parser;
 #[macro_use] extern crate criterion;
+extern crate nom;
 
-use criterion::Criterion;
+use criterion::{Benchmark, Criterion};
+use nom::FindSubstring;
 use std::{fs, path::Path};
 
+// Parse a fixture with `root`. `just bench-scanner` runs it on the
+// phrase-heavy fixtures with and without the `naive-scanner` feature,
+// to compare `root` before and after the scanner.
 macro_rules! criterion_for (
     ($name:ident, $input_path:expr) => (
         fn $name(c: &mut Criterion) {
@@ -20,6 +25,76 @@ macro_rules! criterion_for (
     );
 );
 
+// Compare the scanner against a naive search for `<!--`, i.e. what
+// `take_until_terminated!` used to do.
+macro_rules! criterion_scanner_for (
+    ($name:ident, $input_path:expr) => (
+        fn $name(c: &mut Criterion) {
+            let input_path = Path::new($input_path);
+            let content = fs::read(&input_path).unwrap();
+            let content_for_find_substring = content.clone();
+
+            c.bench(
+                stringify!($name),
+                Benchmark::new(
+                    "scanner",
+                    move |b| {
+                        b.iter(|| count_comment_openings(&content, |haystack| parser::scanner::find(haystack, parser::scanner::COMMENT_OPENING)))
+                    }
+                ).with_function(
+                    "find_substring",
+                    move |b| {
+                        b.iter(|| count_comment_openings(&content_for_find_substring, |haystack| haystack.find_substring(parser::scanner::COMMENT_OPENING)))
+                    }
+                )
+            );
+        }
+    );
+);
+
+// Compare the parallel parser against the single-threaded parser, on a
+// multi-megabyte document made of several copies of the input.
+#[cfg(feature = "parallel")]
+macro_rules! criterion_parallel_for (
+    ($name:ident, $input_path:expr, $copies:expr) => (
+        fn $name(c: &mut Criterion) {
+            let input_path = Path::new($input_path);
+            let content = fs::read(&input_path).unwrap().repeat($copies);
+            let content_for_parallel = content.clone();
+
+            c.bench(
+                stringify!($name),
+                Benchmark::new(
+                    "root",
+                    move |b| {
+                        b.iter(|| parser::root(&content).unwrap())
+                    }
+                ).with_function(
+                    "parallel",
+                    move |b| {
+                        b.iter(|| parser::parallel::root(&content_for_parallel).unwrap())
+                    }
+                ).sample_size(20)
+            );
+        }
+    );
+);
+
+fn count_comment_openings<F>(input: &[u8], find: F) -> usize
+where
+    F: Fn(&[u8]) -> Option<usize>
+{
+    let mut index = 0;
+    let mut count = 0;
+
+    while let Some(next_index) = find(&input[index..]) {
+        count += 1;
+        index += next_index + 1;
+    }
+
+    count
+}
+
 criterion_for!(autoclosing_block, "./tests/fixtures/autoclosing-block.html");
 criterion_for!(early_adopting_the_future, "./tests/fixtures/early-adopting-the-future.html");
 criterion_for!(gutenberg_demo, "./tests/fixtures/gutenberg-demo.html");
@@ -29,6 +104,14 @@ criterion_for!(redesigning_chrome_desktop, "./tests/fixtures/redesigning-chrome-
 criterion_for!(shortcode_shortcomings, "./tests/fixtures/shortcode-shortcomings.html");
 criterion_for!(web_at_maximum_fps, "./tests/fixtures/web-at-maximum-fps.html");
 
+criterion_scanner_for!(scanner_moby_dick_parsed, "./tests/fixtures/moby-dick-parsed.html");
+criterion_scanner_for!(scanner_pygmalian_raw_html, "./tests/fixtures/pygmalian-raw-html.html");
+
+#[cfg(feature = "parallel")]
+criterion_parallel_for!(parallel_moby_dick_parsed, "./tests/fixtures/moby-dick-parsed.html", 4);
+#[cfg(feature = "parallel")]
+criterion_parallel_for!(parallel_pygmalian_raw_html, "./tests/fixtures/pygmalian-raw-html.html", 16);
+
 criterion_group!(
     benches,
     autoclosing_block,
@@ -38,7 +121,20 @@ criterion_group!(
     pygmalian_raw_html,
     redesigning_chrome_desktop,
     shortcode_shortcomings,
-    web_at_maximum_fps
+    web_at_maximum_fps,
+    scanner_moby_dick_parsed,
+    scanner_pygmalian_raw_html
 );
 
+#[cfg(feature = "parallel")]
+criterion_group!(
+    parallel_benches,
+    parallel_moby_dick_parsed,
+    parallel_pygmalian_raw_html
+);
+
+#[cfg(not(feature = "parallel"))]
 criterion_main!(benches);
+
+#[cfg(feature = "parallel")]
+criterion_main!(benches, parallel_benches);
@@ -3,61 +3,48 @@
 #include <string.h>
 #include "gutenberg_post_parser.h"
 
-void print(const Vector_Node* nodes, int depth) {
-    const uintptr_t number_of_nodes = nodes->length;
-    
-    if (number_of_nodes == 0) {
-        return;
-    }
-
-    printf("%*.*snumber of nodes = %lu\n\n", depth, depth, " ", number_of_nodes);
-
-    for (uintptr_t nth = 0; nth < number_of_nodes; ++nth) {
-        const Node node = nodes->buffer[nth];
-
-        if (node.tag == Block) {
-            const Block_Body block = node.block;
-            const Slice_c_char namespace = block.namespace_;
-            const Slice_c_char name = block.name;
-
-            printf("%*.*sblock\n", depth, depth, " ");
+void print(const GutenbergNode* node, int depth) {
+    if (gutenberg_node_kind(node) == GutenbergNodeKind_Block) {
+        const Slice_c_char namespace = gutenberg_node_namespace(node);
+        const Slice_c_char name = gutenberg_node_name(node);
+        const Option_c_char attributes = gutenberg_node_attributes(node);
+        const uintptr_t number_of_children = gutenberg_node_child_count(node);
+
+        printf("%*.*sblock\n", depth, depth, " ");
+        printf(
+            "%*.*s    %.*s/%.*s\n",
+            depth, depth, " ",
+            (int) namespace.length, namespace.pointer,
+            (int) name.length, name.pointer
+        );
+
+        if (attributes.tag == Option_c_char_Some) {
             printf(
-                "%*.*s    %.*s/%.*s\n",
+                "%*.*s    %.*s\n",
                 depth, depth, " ",
-                (int) namespace.length, namespace.pointer,
-                (int) name.length, name.pointer
+                (int) attributes.some._0.length, attributes.some._0.pointer
             );
+        }
 
-            if (block.attributes.tag == Some) {
-                const Slice_c_char attributes = block.attributes.some._0;
+        if (number_of_children > 0) {
+            printf("%*.*s    number of nodes = %lu\n\n", depth, depth, " ", number_of_children);
 
-                printf(
-                    "%*.*s    %.*s\n",
-                    depth, depth, " ",
-                    (int) attributes.length, attributes.pointer
-                );
+            for (uintptr_t nth = 0; nth < number_of_children; ++nth) {
+                print(gutenberg_node_child(node, nth), depth + 4);
             }
-
-            const Vector_Node* children = (const Vector_Node*) (block.children);
-
-            print(children, depth + 4);
-
-            free((void*) children);
-        } else if (node.tag == Phrase) {
-            const Slice_c_char phrase = node.phrase._0;
-
-            printf("%*.*sphrase\n", depth, depth, " ");
-            printf(
-                "%*.*s    %.*s\n",
-                depth, depth, " ",
-                (int) phrase.length, phrase.pointer
-            );
         }
-
-        printf("\n");
+    } else if (gutenberg_node_kind(node) == GutenbergNodeKind_Phrase) {
+        const Slice_c_char phrase = gutenberg_node_phrase(node);
+
+        printf("%*.*sphrase\n", depth, depth, " ");
+        printf(
+            "%*.*s    %.*s\n",
+            depth, depth, " ",
+            (int) phrase.length, phrase.pointer
+        );
     }
 
-    free((void*) nodes->buffer);
+    printf("\n");
 }
 
 int main(int argc, char **argv) {
@@ -82,27 +69,51 @@ int main(int argc, char **argv) {
     char* file_content = (char*) malloc(file_size * sizeof(char));
     size_t result = fread(file_content, 1, file_size, file);
 
+    fclose(file);
+
     if (((long) result) != file_size) {
         printf("Error while reading file content.");
+        free(file_content);
 
         return 3;
     }
 
-    Result output = parse(file_content);
+    GutenbergDocument* document = gutenberg_parse(file_content, file_size);
+
+    // The document owns a copy of the input.
+    free(file_content);
 
-    if (output.tag == Err) {
+    if (document == NULL) {
         printf("Parse error\n");
 
         return 4;
     }
 
-    const Vector_Node nodes = output.ok._0;
-    print(&nodes, 0);
+    const uintptr_t number_of_nodes = gutenberg_document_node_count(document);
 
-    free(file_content);
-    fclose(file);
+    printf("number of nodes = %lu\n\n", number_of_nodes);
+
+    for (uintptr_t nth = 0; nth < number_of_nodes; ++nth) {
+        print(gutenberg_document_node(document, nth), 0);
+    }
+
+    const GutenbergError* error = gutenberg_document_error(document);
+    int exit_code = 0;
+
+    if (error != NULL) {
+        fprintf(
+            stderr,
+            "Parse error at byte %lu: %.*s\n",
+            error->offset,
+            (int) error->message.length, error->message.pointer
+        );
+
+        exit_code = 5;
+    }
+
+    gutenberg_document_free(document);
 
-    return 0;
+    return exit_code;
 }
 
 /*
@@ -13,14 +13,70 @@ Run `just build-c` to rebuild it.
 #include <stdint.h>
 #include <stdlib.h>
 
+#define GUTENBERG_ABI_VERSION_MAJOR 2
+
+#define GUTENBERG_ABI_VERSION_MINOR 0
+
+typedef enum {
+    GutenbergNodeKind_Block,
+    GutenbergNodeKind_Phrase,
+} GutenbergNodeKind;
+
+/*
+ * The reason why the input has not been parsed entirely, see
+ * `gutenberg_post_parser::error::ErrorKind`.
+ */
+typedef enum {
+    GutenbergErrorKind_InvalidBlockName,
+    GutenbergErrorKind_InvalidAttributes,
+    GutenbergErrorKind_InvalidDelimiter,
+    GutenbergErrorKind_UnterminatedDelimiter,
+    GutenbergErrorKind_UnclosedBlock,
+    GutenbergErrorKind_MismatchedClosingBlock,
+    GutenbergErrorKind_UnexpectedClosingBlock,
+    GutenbergErrorKind_Unknown,
+} GutenbergErrorKind;
+
+/*
+ * A parsed post. It owns a copy of the input, so that the caller can
+ * release its own buffer as soon as `gutenberg_parse` returns, and
+ * the nodes, which point into this copy.
+ */
+typedef struct GutenbergDocument GutenbergDocument;
+
+/*
+ * A node of a document, i.e. a block or a phrase. It lives as long
+ * as its document.
+ */
+typedef struct GutenbergNode GutenbergNode;
+
 typedef struct {
     const char *pointer;
     uintptr_t length;
 } Slice_c_char;
 
+/*
+ * Details about why the input has not been parsed entirely.
+ */
+typedef struct {
+    /*
+     * The reason.
+     */
+    GutenbergErrorKind kind;
+    /*
+     * The offset, in bytes, of the rejected data within the input.
+     */
+    uintptr_t offset;
+    /*
+     * A human readable message. It is static data, and it is not
+     * NUL-terminated.
+     */
+    Slice_c_char message;
+} GutenbergError;
+
 typedef enum {
-    Some,
-    None,
+    Option_c_char_Some,
+    Option_c_char_None,
 } Option_c_char_Tag;
 
 typedef struct {
@@ -34,49 +90,146 @@ typedef struct {
     };
 } Option_c_char;
 
-typedef enum {
-    Block,
-    Phrase,
-} Node_Tag;
-
+/*
+ * Function pointers called by `gutenberg_parse_with_callbacks` for
+ * each node, in the order of the input, with the user data pointer
+ * given to it. Each of them can be `NULL`.
+ */
 typedef struct {
-    Slice_c_char namespace_;
-    Slice_c_char name;
-    Option_c_char attributes;
-    const void *children;
-} Block_Body;
+    /*
+     * Called when a block starts, with its namespace, name, and
+     * attributes as written in the input, if any. When the namespace
+     * is implicit, it is the default namespace, which does not point
+     * into the input.
+     */
+    void (*on_block_start)(void *user_data, Slice_c_char namespace_, Slice_c_char name, Option_c_char attributes);
+    /*
+     * Called for each phrase.
+     */
+    void (*on_phrase)(void *user_data, Slice_c_char phrase);
+    /*
+     * Called when a block ends, after its children.
+     */
+    void (*on_block_end)(void *user_data, Slice_c_char namespace_, Slice_c_char name);
+} GutenbergCallbacks;
 
+/*
+ * Options of `gutenberg_parse_with_callbacks`, to adjust the grammar,
+ * see `gutenberg_post_parser::ParseOptions`. The slices are not
+ * NUL-terminated.
+ */
 typedef struct {
-    Slice_c_char _0;
-} Phrase_Body;
+    /*
+     * The namespace of a block name that has no explicit namespace,
+     * e.g. `core`.
+     */
+    Slice_c_char default_namespace;
+    /*
+     * Whether a block name part accepts uppercase letters, and can
+     * start with a digit.
+     */
+    bool lenient_names;
+    /*
+     * The prefix of the block delimiters, e.g. `wp`.
+     */
+    Slice_c_char delimiter_prefix;
+} GutenbergParseOptions;
 
-typedef struct {
-    Node_Tag tag;
-    union {
-        Block_Body block;
-        Phrase_Body phrase;
-    };
-} Node;
+/*
+ * The version of the ABI of the library, as `(major << 16) | minor`.
+ * A caller is compatible if the major version equals
+ * `GUTENBERG_ABI_VERSION_MAJOR`, and the minor version is greater
+ * than or equal to `GUTENBERG_ABI_VERSION_MINOR`, from the header it
+ * has been compiled with.
+ */
+uint32_t gutenberg_abi_version(void);
 
-typedef struct {
-    const Node *buffer;
-    uintptr_t length;
-} Vector_Node;
+/*
+ * Why the input has not been parsed entirely, or `NULL` if it has.
+ * The error lives as long as its document.
+ */
+const GutenbergError *gutenberg_document_error(const GutenbergDocument *document);
 
-typedef enum {
-    Ok,
-    Err,
-} Result_Tag;
+/*
+ * Release a document, and all its nodes. `document` can be `NULL`.
+ */
+void gutenberg_document_free(GutenbergDocument *document);
 
-typedef struct {
-    Vector_Node _0;
-} Ok_Body;
+/*
+ * The top-level node at `index`, or `NULL` if out of bounds.
+ */
+const GutenbergNode *gutenberg_document_node(const GutenbergDocument *document, uintptr_t index);
 
-typedef struct {
-    Result_Tag tag;
-    union {
-        Ok_Body ok;
-    };
-} Result;
+/*
+ * The number of top-level nodes of a document.
+ */
+uintptr_t gutenberg_document_node_count(const GutenbergDocument *document);
+
+/*
+ * The attributes of a block, as written in the input, if any.
+ */
+Option_c_char gutenberg_node_attributes(const GutenbergNode *node);
 
-Result parse(const char *pointer);
+/*
+ * The child of a block at `index`, or `NULL` if out of bounds.
+ */
+const GutenbergNode *gutenberg_node_child(const GutenbergNode *node, uintptr_t index);
+
+/*
+ * The number of children of a block, or 0 for a phrase.
+ */
+uintptr_t gutenberg_node_child_count(const GutenbergNode *node);
+
+/*
+ * The kind of a node.
+ */
+GutenbergNodeKind gutenberg_node_kind(const GutenbergNode *node);
+
+/*
+ * The name of a block, e.g. `paragraph`, or an empty slice for a
+ * phrase.
+ */
+Slice_c_char gutenberg_node_name(const GutenbergNode *node);
+
+/*
+ * The namespace of a block, e.g. `core`, or an empty slice for a
+ * phrase.
+ */
+Slice_c_char gutenberg_node_namespace(const GutenbergNode *node);
+
+/*
+ * The content of a phrase, or an empty slice for a block.
+ */
+Slice_c_char gutenberg_node_phrase(const GutenbergNode *node);
+
+/*
+ * Parse `length` bytes from `pointer`. The input does not need to be
+ * NUL-terminated, and may contain NUL bytes. Return `NULL` if
+ * `pointer` is `NULL` (and `length` is not zero); otherwise the
+ * document must be released with `gutenberg_document_free`. When the
+ * input is not parsed entirely, the document contains the nodes
+ * before the rejected data, see `gutenberg_document_error`.
+ */
+GutenbergDocument *gutenberg_parse(const char *pointer, uintptr_t length);
+
+/*
+ * Parse `length` bytes from `pointer`, and stream the nodes to
+ * `callbacks`, with `user_data`, with the grammar adjusted by
+ * `options`, or the default grammar if `options` is `NULL`. The
+ * events are emitted as soon as each delimiter or phrase is
+ * recognized: No subtree is built, only the names of the open
+ * blocks are kept to match their closing delimiters. The slices
+ * point into the input. Return `true` if the input has been parsed
+ * entirely; otherwise `error`, if not `NULL`, is filled to explain
+ * why, and the callbacks have been called for the input scanned so
+ * far, without ending the blocks that are still open. Return
+ * `false` without calling anything if `callbacks` is `NULL`, or if
+ * `pointer` or a slice of `options` is `NULL` (and its length is not
+ * zero).
+ */
+bool gutenberg_parse_with_callbacks(const char *pointer,
+                                    uintptr_t length,
+                                    const GutenbergParseOptions *options,
+                                    const GutenbergCallbacks *callbacks,
+                                    void *user_data,
+                                    GutenbergError *error);
@@ -1,11 +1,38 @@
 extern crate cbindgen;
 
+#[path = "src/abi.rs"]
+#[allow(dead_code)]
+mod abi;
+
 use std::env;
 
+const LIBRARY_NAME: &str = "gutenberg_post_parser_c";
+
 fn main() {
     let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
 
-    cbindgen::generate(crate_dir)
+    cbindgen::generate(&crate_dir)
         .expect("Unable to generate C bindings.")
         .write_to_file("bin/gutenberg_post_parser.h");
+
+    // Set the SONAME (or the install name) of the shared library, so
+    // that programs link against the major version of the ABI only.
+    match env::var("CARGO_CFG_TARGET_OS").unwrap().as_str() {
+        "macos" | "ios" => println!(
+            "cargo:rustc-cdylib-link-arg=-Wl,-install_name,@rpath/lib{}.{}.dylib",
+            LIBRARY_NAME,
+            abi::GUTENBERG_ABI_VERSION_MAJOR
+        ),
+
+        "windows" => (),
+
+        _ => println!(
+            "cargo:rustc-cdylib-link-arg=-Wl,-soname,lib{}.so.{}",
+            LIBRARY_NAME,
+            abi::GUTENBERG_ABI_VERSION_MAJOR
+        ),
+    }
+
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
 }
@@ -0,0 +1,12 @@
+//! Version of the ABI of the C binding. It is also read by `build.rs`
+//! to set the SONAME of the shared library.
+
+/// Bumped when the ABI changes in a backward-incompatible way, i.e.
+/// when a function, a structure or an enumeration is removed or
+/// modified. It is the version of the SONAME of the shared library.
+pub const GUTENBERG_ABI_VERSION_MAJOR: u32 = 2;
+
+/// Bumped when the ABI is extended in a backward-compatible way,
+/// e.g. when a function is added. Reset to 0 when the major version
+/// is bumped.
+pub const GUTENBERG_ABI_VERSION_MINOR: u32 = 0;
@@ -11,137 +11,563 @@ The workflow is basically the following:
      and links with the static library to generate a “C binary”.
 
 This module is responsible to map the AST into a C
-representation. Data are duplicated (most of them are stored on the
-stack).
+representation. `gutenberg_parse` returns an opaque
+`GutenbergDocument` handle, that owns a copy of the input and the
+AST. Nodes are read with the `gutenberg_document_*` and
+`gutenberg_node_*` accessors, and the document is released with
+`gutenberg_document_free`. If the input has not been parsed entirely,
+`gutenberg_document_error` explains why, like the `error` module of
+the parser does. All memory is allocated and freed by Rust:
+The caller must never `free()` anything returned by these functions.
+
+Embedders that do not need a tree can call
+`gutenberg_parse_with_callbacks` instead: It streams the nodes to a
+`GutenbergCallbacks` set of function pointers, with a user data
+pointer, and builds no tree at all. The input is scanned delimiter by
+delimiter, and an event is emitted for each of them, or for each
+phrase, while only the names of the open blocks are kept. The grammar
+can be adjusted with `GutenbergParseOptions`. The input is borrowed,
+not copied, so the slices given to the callbacks are valid as long as
+the caller's buffer is.
+
+The crate also builds a shared library, whose SONAME carries the major
+version of the ABI, `GUTENBERG_ABI_VERSION_MAJOR`. `gutenberg_abi_version`
+returns the version of the ABI of the loaded library, so that a caller
+can check it matches the header it has been compiled with.
 
 */
 
 extern crate gutenberg_post_parser;
 
-use gutenberg_post_parser::{ast, root};
-use std::ffi::CStr;
-use std::mem;
+mod abi;
+
+pub use abi::{GUTENBERG_ABI_VERSION_MAJOR, GUTENBERG_ABI_VERSION_MINOR};
+use gutenberg_post_parser::{ast, error, parser, root, ParseOptions};
 use std::os::raw::{c_char, c_void};
 use std::ptr;
+use std::slice;
 
 #[repr(C)]
+#[derive(Clone, Copy)]
 pub struct Slice_c_char {
-    pointer: *const c_char,
-    length: usize,
+    pub pointer: *const c_char,
+    pub length: usize,
 }
 
 #[repr(C)]
+#[derive(Clone, Copy)]
 pub enum Option_c_char {
     Some(Slice_c_char),
     None,
 }
 
 #[repr(C)]
-pub enum Node {
+#[derive(Clone, Copy, PartialEq)]
+pub enum GutenbergNodeKind {
+    Block,
+    Phrase,
+}
+
+/// The reason why the input has not been parsed entirely, see
+/// `gutenberg_post_parser::error::ErrorKind`.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GutenbergErrorKind {
+    InvalidBlockName,
+    InvalidAttributes,
+    InvalidDelimiter,
+    UnterminatedDelimiter,
+    UnclosedBlock,
+    MismatchedClosingBlock,
+    UnexpectedClosingBlock,
+    Unknown,
+}
+
+impl From<error::ErrorKind> for GutenbergErrorKind {
+    fn from(kind: error::ErrorKind) -> Self {
+        match kind {
+            error::ErrorKind::InvalidBlockName => GutenbergErrorKind::InvalidBlockName,
+            error::ErrorKind::InvalidAttributes => GutenbergErrorKind::InvalidAttributes,
+            error::ErrorKind::InvalidDelimiter => GutenbergErrorKind::InvalidDelimiter,
+            error::ErrorKind::UnterminatedDelimiter => GutenbergErrorKind::UnterminatedDelimiter,
+            error::ErrorKind::UnclosedBlock => GutenbergErrorKind::UnclosedBlock,
+            error::ErrorKind::MismatchedClosingBlock => GutenbergErrorKind::MismatchedClosingBlock,
+            error::ErrorKind::UnexpectedClosingBlock => GutenbergErrorKind::UnexpectedClosingBlock,
+            error::ErrorKind::Unknown => GutenbergErrorKind::Unknown,
+        }
+    }
+}
+
+/// Details about why the input has not been parsed entirely.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct GutenbergError {
+    /// The reason.
+    pub kind: GutenbergErrorKind,
+
+    /// The offset, in bytes, of the rejected data within the input.
+    pub offset: usize,
+
+    /// A human readable message. It is static data, and it is not
+    /// NUL-terminated.
+    pub message: Slice_c_char,
+}
+
+impl From<error::Error> for GutenbergError {
+    fn from(error: error::Error) -> Self {
+        GutenbergError {
+            kind: error.kind.into(),
+            offset: error.offset,
+            message: into_slice(error.kind.description().as_bytes()),
+        }
+    }
+}
+
+/// A parsed post. It owns a copy of the input, so that the caller can
+/// release its own buffer as soon as `gutenberg_parse` returns, and
+/// the nodes, which point into this copy.
+pub struct GutenbergDocument {
+    // Boxed, so that the slices of the nodes stay valid when the
+    // document moves.
+    _input: Box<[u8]>,
+    nodes: Vec<GutenbergNode>,
+    error: Option<GutenbergError>,
+}
+
+/// A node of a document, i.e. a block or a phrase. It lives as long
+/// as its document.
+pub enum GutenbergNode {
     Block {
         namespace: Slice_c_char,
         name: Slice_c_char,
         attributes: Option_c_char,
-        // Cannot type to `*const Vector_Node` here because of https://github.com/eqrion/cbindgen/issues/43.
-        children: *const c_void,
+        children: Vec<GutenbergNode>,
     },
     Phrase(Slice_c_char),
 }
 
+/// Function pointers called by `gutenberg_parse_with_callbacks` for
+/// each node, in the order of the input, with the user data pointer
+/// given to it. Each of them can be `NULL`.
 #[repr(C)]
-pub struct Vector_Node {
-    buffer: *const Node,
-    length: usize,
+pub struct GutenbergCallbacks {
+    /// Called when a block starts, with its namespace, name, and
+    /// attributes as written in the input, if any. When the namespace
+    /// is implicit, it is the default namespace, which does not point
+    /// into the input.
+    pub on_block_start: Option<extern "C" fn(user_data: *mut c_void, namespace_: Slice_c_char, name: Slice_c_char, attributes: Option_c_char)>,
+
+    /// Called for each phrase.
+    pub on_phrase: Option<extern "C" fn(user_data: *mut c_void, phrase: Slice_c_char)>,
+
+    /// Called when a block ends, after its children.
+    pub on_block_end: Option<extern "C" fn(user_data: *mut c_void, namespace_: Slice_c_char, name: Slice_c_char)>,
 }
 
+/// Options of `gutenberg_parse_with_callbacks`, to adjust the grammar,
+/// see `gutenberg_post_parser::ParseOptions`. The slices are not
+/// NUL-terminated.
 #[repr(C)]
-pub enum Result {
-    Ok(Vector_Node),
-    Err,
+pub struct GutenbergParseOptions {
+    /// The namespace of a block name that has no explicit namespace,
+    /// e.g. `core`.
+    pub default_namespace: Slice_c_char,
+
+    /// Whether a block name part accepts uppercase letters, and can
+    /// start with a digit.
+    pub lenient_names: bool,
+
+    /// The prefix of the block delimiters, e.g. `wp`.
+    pub delimiter_prefix: Slice_c_char,
 }
 
+/// An empty slice, returned by the accessors that do not apply to a
+/// node, e.g. the name of a phrase.
+const EMPTY_SLICE: Slice_c_char = Slice_c_char {
+    pointer: ptr::null(),
+    length: 0,
+};
+
+/// The version of the ABI of the library, as `(major << 16) | minor`.
+/// A caller is compatible if the major version equals
+/// `GUTENBERG_ABI_VERSION_MAJOR`, and the minor version is greater
+/// than or equal to `GUTENBERG_ABI_VERSION_MINOR`, from the header it
+/// has been compiled with.
 #[no_mangle]
-pub extern "C" fn parse(pointer: *const c_char) -> Result {
-    if pointer.is_null() {
-        return Result::Err;
-    }
+pub extern "C" fn gutenberg_abi_version() -> u32 {
+    (GUTENBERG_ABI_VERSION_MAJOR << 16) | GUTENBERG_ABI_VERSION_MINOR
+}
 
-    let input = unsafe { CStr::from_ptr(pointer).to_bytes() };
+/// Parse `length` bytes from `pointer`. The input does not need to be
+/// NUL-terminated, and may contain NUL bytes. Return `NULL` if
+/// `pointer` is `NULL` (and `length` is not zero); otherwise the
+/// document must be released with `gutenberg_document_free`. When the
+/// input is not parsed entirely, the document contains the nodes
+/// before the rejected data, see `gutenberg_document_error`.
+///
+/// # Safety
+///
+/// `pointer` must be valid for reads of `length` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn gutenberg_parse(pointer: *const c_char, length: usize) -> *mut GutenbergDocument {
+    let input: Box<[u8]> = if length == 0 {
+        Box::new([])
+    } else if pointer.is_null() {
+        return ptr::null_mut();
+    } else {
+        slice::from_raw_parts(pointer as *const u8, length).into()
+    };
+
+    let (nodes, error) = match root(&input) {
+        Ok((remaining, nodes)) => (
+            nodes.iter().map(into_c).collect(),
+            error::diagnose(&input, remaining).map(GutenbergError::from),
+        ),
+
+        Err(_) => (
+            vec![],
+            Some(GutenbergError::from(error::Error {
+                kind: error::ErrorKind::Unknown,
+                offset: 0,
+            })),
+        ),
+    };
+
+    Box::into_raw(Box::new(GutenbergDocument {
+        _input: input,
+        nodes,
+        error,
+    }))
+}
 
-    if let Ok((_remaining, nodes)) = root(input) {
-        let output: Vec<Node> =
-            nodes
-                .into_iter()
-                .map(
-                    |node| {
-                        into_c(&node)
+/// Parse `length` bytes from `pointer`, and stream the nodes to
+/// `callbacks`, with `user_data`, with the grammar adjusted by
+/// `options`, or the default grammar if `options` is `NULL`. The
+/// events are emitted as soon as each delimiter or phrase is
+/// recognized: No subtree is built, only the names of the open
+/// blocks are kept to match their closing delimiters. The slices
+/// point into the input. Return `true` if the input has been parsed
+/// entirely; otherwise `error`, if not `NULL`, is filled to explain
+/// why, and the callbacks have been called for the input scanned so
+/// far, without ending the blocks that are still open. Return
+/// `false` without calling anything if `callbacks` is `NULL`, or if
+/// `pointer` or a slice of `options` is `NULL` (and its length is not
+/// zero).
+///
+/// # Safety
+///
+/// `pointer` must be valid for reads of `length` bytes, `options`
+/// must be `NULL` or valid for reads, as well as its slices, and
+/// `error` must be `NULL` or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn gutenberg_parse_with_callbacks(
+    pointer: *const c_char,
+    length: usize,
+    options: *const GutenbergParseOptions,
+    callbacks: *const GutenbergCallbacks,
+    user_data: *mut c_void,
+    error: *mut GutenbergError,
+) -> bool {
+    let callbacks = match callbacks.as_ref() {
+        Some(callbacks) => callbacks,
+        None => return false,
+    };
+
+    let input = match from_raw_parts(pointer, length) {
+        Some(input) => input,
+        None => return false,
+    };
+
+    let options = match options.as_ref() {
+        Some(options) => match (
+            from_raw_parts(options.default_namespace.pointer, options.default_namespace.length),
+            from_raw_parts(options.delimiter_prefix.pointer, options.delimiter_prefix.length),
+        ) {
+            (Some(default_namespace), Some(delimiter_prefix)) => ParseOptions {
+                default_namespace,
+                lenient_names: options.lenient_names,
+                delimiter_prefix,
+            },
+
+            _ => return false,
+        },
+
+        None => ParseOptions::default(),
+    };
+
+    // The names and the offsets of the open blocks, so that a closing
+    // delimiter is matched against the innermost one, and an error is
+    // diagnosed from the outermost one, like `root` does.
+    let mut open_blocks: Vec<(ast::BlockName, usize)> = vec![];
+    let mut remaining = input;
+
+    while !remaining.is_empty() {
+        let offset = input.len() - remaining.len();
+
+        if let Ok((next_remaining, delimiter)) = parser::delimiter_with_options(remaining, &options) {
+            match delimiter {
+                parser::Delimiter::Opening { name, attributes } => {
+                    emit_block_start(callbacks, user_data, name, attributes);
+                    open_blocks.push((name, offset));
+                }
+
+                parser::Delimiter::Void { name, attributes } => {
+                    emit_block_start(callbacks, user_data, name, attributes);
+                    emit_block_end(callbacks, user_data, name);
+                }
+
+                parser::Delimiter::Closing { name } => match open_blocks.last() {
+                    Some(&(open_name, _)) if open_name.is_equivalent_to(&name) => {
+                        open_blocks.pop();
+                        emit_block_end(callbacks, user_data, open_name);
                     }
-                )
-                .collect();
 
-        let vector_node = Vector_Node {
-            buffer: output.as_slice().as_ptr(),
-            length: output.len()
+                    _ => break,
+                },
+            }
+
+            remaining = next_remaining;
+        } else if let Ok((next_remaining, ast::Node::Phrase(phrase))) = parser::phrase_with_options(remaining, &options) {
+            if let Some(on_phrase) = callbacks.on_phrase {
+                on_phrase(user_data, into_slice(phrase));
+            }
+
+            remaining = next_remaining;
+        } else {
+            break;
+        }
+    }
+
+    let rejected = match open_blocks.first() {
+        Some(&(_, offset)) => &input[offset..],
+        None => remaining,
+    };
+
+    match error::diagnose_with_options(input, rejected, &options) {
+        Some(diagnostic) => {
+            if let Some(error) = error.as_mut() {
+                *error = diagnostic.into();
+            }
+
+            false
+        }
+
+        None => true,
+    }
+}
+
+/// Release a document, and all its nodes. `document` can be `NULL`.
+///
+/// # Safety
+///
+/// `document` must be `NULL`, or returned by `gutenberg_parse`
+/// and not released yet. Its nodes must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn gutenberg_document_free(document: *mut GutenbergDocument) {
+    if !document.is_null() {
+        drop(Box::from_raw(document));
+    }
+}
+
+/// The number of top-level nodes of a document.
+///
+/// # Safety
+///
+/// `document` must be `NULL`, or returned by `gutenberg_parse` and
+/// not released yet.
+#[no_mangle]
+pub unsafe extern "C" fn gutenberg_document_node_count(document: *const GutenbergDocument) -> usize {
+    match document.as_ref() {
+        Some(document) => document.nodes.len(),
+        None => 0,
+    }
+}
+
+/// Why the input has not been parsed entirely, or `NULL` if it has.
+/// The error lives as long as its document.
+///
+/// # Safety
+///
+/// `document` must be `NULL`, or returned by `gutenberg_parse` and
+/// not released yet.
+#[no_mangle]
+pub unsafe extern "C" fn gutenberg_document_error(document: *const GutenbergDocument) -> *const GutenbergError {
+    match document.as_ref().and_then(|document| document.error.as_ref()) {
+        Some(error) => error,
+        None => ptr::null(),
+    }
+}
+
+/// The top-level node at `index`, or `NULL` if out of bounds.
+///
+/// # Safety
+///
+/// `document` must be `NULL`, or returned by `gutenberg_parse` and
+/// not released yet.
+#[no_mangle]
+pub unsafe extern "C" fn gutenberg_document_node(document: *const GutenbergDocument, index: usize) -> *const GutenbergNode {
+    match document.as_ref().and_then(|document| document.nodes.get(index)) {
+        Some(node) => node,
+        None => ptr::null(),
+    }
+}
+
+/// The kind of a node.
+///
+/// # Safety
+///
+/// `node` must be `NULL`, or a node of a document that has not
+/// been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn gutenberg_node_kind(node: *const GutenbergNode) -> GutenbergNodeKind {
+    match node.as_ref() {
+        Some(GutenbergNode::Block { .. }) => GutenbergNodeKind::Block,
+        _ => GutenbergNodeKind::Phrase,
+    }
+}
+
+/// The namespace of a block, e.g. `core`, or an empty slice for a
+/// phrase.
+///
+/// # Safety
+///
+/// `node` must be `NULL`, or a node of a document that has not
+/// been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn gutenberg_node_namespace(node: *const GutenbergNode) -> Slice_c_char {
+    match node.as_ref() {
+        Some(GutenbergNode::Block { namespace, .. }) => *namespace,
+        _ => EMPTY_SLICE,
+    }
+}
+
+/// The name of a block, e.g. `paragraph`, or an empty slice for a
+/// phrase.
+///
+/// # Safety
+///
+/// `node` must be `NULL`, or a node of a document that has not
+/// been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn gutenberg_node_name(node: *const GutenbergNode) -> Slice_c_char {
+    match node.as_ref() {
+        Some(GutenbergNode::Block { name, .. }) => *name,
+        _ => EMPTY_SLICE,
+    }
+}
+
+/// The attributes of a block, as written in the input, if any.
+///
+/// # Safety
+///
+/// `node` must be `NULL`, or a node of a document that has not
+/// been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn gutenberg_node_attributes(node: *const GutenbergNode) -> Option_c_char {
+    match node.as_ref() {
+        Some(GutenbergNode::Block { attributes, .. }) => *attributes,
+        _ => Option_c_char::None,
+    }
+}
+
+/// The content of a phrase, or an empty slice for a block.
+///
+/// # Safety
+///
+/// `node` must be `NULL`, or a node of a document that has not
+/// been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn gutenberg_node_phrase(node: *const GutenbergNode) -> Slice_c_char {
+    match node.as_ref() {
+        Some(GutenbergNode::Phrase(phrase)) => *phrase,
+        _ => EMPTY_SLICE,
+    }
+}
+
+/// The number of children of a block, or 0 for a phrase.
+///
+/// # Safety
+///
+/// `node` must be `NULL`, or a node of a document that has not
+/// been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn gutenberg_node_child_count(node: *const GutenbergNode) -> usize {
+    match node.as_ref() {
+        Some(GutenbergNode::Block { children, .. }) => children.len(),
+        _ => 0,
+    }
+}
+
+/// The child of a block at `index`, or `NULL` if out of bounds.
+///
+/// # Safety
+///
+/// `node` must be `NULL`, or a node of a document that has not
+/// been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn gutenberg_node_child(node: *const GutenbergNode, index: usize) -> *const GutenbergNode {
+    match node.as_ref() {
+        Some(GutenbergNode::Block { children, .. }) => match children.get(index) {
+            Some(child) => child,
+            None => ptr::null(),
+        },
+        _ => ptr::null(),
+    }
+}
+
+fn emit_block_start(callbacks: &GutenbergCallbacks, user_data: *mut c_void, name: ast::BlockName, attributes: Option<&[u8]>) {
+    if let Some(on_block_start) = callbacks.on_block_start {
+        let attributes = match attributes {
+            Some(attributes) => Option_c_char::Some(into_slice(attributes)),
+            None => Option_c_char::None,
         };
 
-        mem::forget(output);
+        on_block_start(user_data, into_slice(name.namespace()), into_slice(name.name()), attributes);
+    }
+}
+
+fn emit_block_end(callbacks: &GutenbergCallbacks, user_data: *mut c_void, name: ast::BlockName) {
+    if let Some(on_block_end) = callbacks.on_block_end {
+        on_block_end(user_data, into_slice(name.namespace()), into_slice(name.name()));
+    }
+}
 
-        Result::Ok(vector_node)
+/// Borrow `length` bytes from `pointer`, or return `None` if `pointer`
+/// is `NULL` and `length` is not zero.
+unsafe fn from_raw_parts<'a>(pointer: *const c_char, length: usize) -> Option<&'a [u8]> {
+    if length == 0 {
+        Some(&[])
+    } else if pointer.is_null() {
+        None
     } else {
-        Result::Err
+        Some(slice::from_raw_parts(pointer as *const u8, length))
     }
 }
 
-fn into_c<'a>(node: &ast::Node<'a>) -> Node {
+fn into_slice(input: &[u8]) -> Slice_c_char {
+    Slice_c_char {
+        pointer: input.as_ptr() as *const c_char,
+        length: input.len(),
+    }
+}
+
+fn into_c(node: &ast::Node) -> GutenbergNode {
     match *node {
         ast::Node::Block {
             name,
             attributes,
             ref children,
-        } => Node::Block {
-            namespace: Slice_c_char {
-                pointer: name.0.as_ptr() as *const c_char,
-                length: name.0.len(),
-            },
-            name: Slice_c_char {
-                pointer: name.1.as_ptr() as *const c_char,
-                length: name.1.len(),
-            },
+        } => GutenbergNode::Block {
+            namespace: into_slice(name.namespace()),
+            name: into_slice(name.name()),
             attributes: match attributes {
-                Some(attributes) => Option_c_char::Some(Slice_c_char {
-                    pointer: attributes.as_ptr() as *const c_char,
-                    length: attributes.len(),
-                }),
-
+                Some(attributes) => Option_c_char::Some(into_slice(attributes)),
                 None => Option_c_char::None,
             },
-            children: {
-                let output: Vec<Node> =
-                    children.into_iter().map(|node| into_c(&node)).collect();
-
-                let vector_node = if output.is_empty() {
-                    Box::new(Vector_Node {
-                        buffer: ptr::null(),
-                        length: 0,
-                    })
-                } else {
-                    Box::new(Vector_Node {
-                        buffer: output.as_slice().as_ptr(),
-                        length: output.len(),
-                    })
-                };
-                let vector_node_pointer = Box::into_raw(vector_node) as *const c_void;
-
-                mem::forget(output);
-
-                vector_node_pointer
-            },
+            children: children.iter().map(into_c).collect(),
         },
 
-        ast::Node::Phrase(input) => Node::Phrase(Slice_c_char {
-            pointer: input.as_ptr() as *const c_char,
-            length: input.len(),
-        }),
+        ast::Node::Phrase(input) => GutenbergNode::Phrase(into_slice(input)),
     }
 }
 
@@ -149,158 +575,304 @@ fn into_c<'a>(node: &ast::Node<'a>) -> Node {
 mod tests {
     use super::*;
 
-    macro_rules! str_to_c_char {
+    macro_rules! slice_c_char_to_str {
         ($input:expr) => {{
-            ::std::ffi::CString::new($input).unwrap()
+            let slice = $input;
+
+            ::std::str::from_utf8(::std::slice::from_raw_parts(
+                slice.pointer as *const u8,
+                slice.length,
+            ))
+            .unwrap()
         }};
     }
 
-    macro_rules! slice_c_char_to_str {
-        ($input:ident) => {
-            unsafe {
-                ::std::ffi::CStr::from_bytes_with_nul_unchecked(::std::slice::from_raw_parts(
-                    $input.pointer as *const u8,
-                    $input.length + 1,
-                ))
-                .to_str()
-                .unwrap()
-            }
+    macro_rules! parse {
+        ($input:expr) => {
+            unsafe { gutenberg_parse($input.as_ptr() as *const c_char, $input.len()) }
         };
     }
 
+    #[test]
+    fn test_abi_version() {
+        let version = gutenberg_abi_version();
+
+        assert_eq!(version >> 16, GUTENBERG_ABI_VERSION_MAJOR);
+        assert_eq!(version & 0xffff, GUTENBERG_ABI_VERSION_MINOR);
+    }
+
     #[test]
     fn test_root_with_a_phrase() {
-        let input = str_to_c_char!("foo");
-        let output = parse(input.as_ptr());
-
-        match output {
-            Result::Ok(result) => match result {
-                Vector_Node { buffer, length } if length == 1 => match unsafe { &*buffer } {
-                    Node::Phrase(phrase) => {
-                        assert_eq!(slice_c_char_to_str!(phrase), "foo");
-                    }
+        let document = parse!("foo");
 
-                    _ => assert!(false),
-                },
+        unsafe {
+            assert_eq!(gutenberg_document_node_count(document), 1);
 
-                _ => assert!(false),
-            },
+            let node = gutenberg_document_node(document, 0);
+
+            assert!(gutenberg_node_kind(node) == GutenbergNodeKind::Phrase);
+            assert_eq!(slice_c_char_to_str!(gutenberg_node_phrase(node)), "foo");
+            assert_eq!(gutenberg_node_name(node).length, 0);
+            assert!(gutenberg_document_node(document, 1).is_null());
 
-            _ => assert!(false),
+            gutenberg_document_free(document);
         }
     }
 
     #[test]
     fn test_root_with_a_block() {
-        let input = str_to_c_char!("<!-- wp:foo {bar} /-->");
-        let output = parse(input.as_ptr());
-
-        match output {
-            Result::Ok(result) => match result {
-                Vector_Node { buffer, length } if length == 1 => match unsafe { &*buffer } {
-                    Node::Block {
-                        namespace,
-                        name,
-                        attributes,
-                        children,
-                    } => {
-                        assert_eq!(slice_c_char_to_str!(namespace), "core");
-                        assert_eq!(slice_c_char_to_str!(name), "foo");
-
-                        match attributes {
-                            Option_c_char::Some(attributes) => {
-                                assert_eq!(slice_c_char_to_str!(attributes), "{bar}");
-                            }
-
-                            _ => assert!(false),
-                        }
-
-                        let children = unsafe { &*(children as *const _ as *const Vector_Node) };
-
-                        assert_eq!(children.length, 0);
-                    }
+        let document = parse!("<!-- wp:foo {bar} /-->");
 
-                    _ => assert!(false),
-                },
+        unsafe {
+            assert_eq!(gutenberg_document_node_count(document), 1);
 
-                _ => assert!(false),
-            },
+            let node = gutenberg_document_node(document, 0);
+
+            assert!(gutenberg_node_kind(node) == GutenbergNodeKind::Block);
+            assert_eq!(slice_c_char_to_str!(gutenberg_node_namespace(node)), "core");
+            assert_eq!(slice_c_char_to_str!(gutenberg_node_name(node)), "foo");
 
-            _ => assert!(false),
+            match gutenberg_node_attributes(node) {
+                Option_c_char::Some(attributes) => assert_eq!(slice_c_char_to_str!(attributes), "{bar}"),
+                Option_c_char::None => panic!("The block must have attributes."),
+            }
+
+            assert_eq!(gutenberg_node_child_count(node), 0);
+            assert!(gutenberg_node_child(node, 0).is_null());
+
+            gutenberg_document_free(document);
         }
     }
 
     #[test]
     fn test_root_with_a_block_with_no_attributes() {
-        let input = str_to_c_char!("<!-- wp:foo /-->");
-        let output = parse(input.as_ptr());
-
-        match output {
-            Result::Ok(result) => match result {
-                Vector_Node { buffer, length } if length == 1 => match unsafe { &*buffer } {
-                    Node::Block {
-                        namespace,
-                        name,
-                        attributes,
-                        children,
-                    } => {
-                        assert_eq!(slice_c_char_to_str!(namespace), "core");
-                        assert_eq!(slice_c_char_to_str!(name), "foo");
-
-                        match attributes {
-                            Option_c_char::None => assert!(true),
-                            _ => assert!(false),
-                        }
-
-                        let children = unsafe { &*(children as *const _ as *const Vector_Node) };
-
-                        assert_eq!(children.length, 0);
-                    }
+        let document = parse!("<!-- wp:foo /-->");
 
-                    _ => assert!(false),
-                },
+        unsafe {
+            let node = gutenberg_document_node(document, 0);
 
-                _ => assert!(false),
-            },
+            match gutenberg_node_attributes(node) {
+                Option_c_char::None => (),
+                Option_c_char::Some(_) => panic!("The block must not have attributes."),
+            }
 
-            _ => assert!(false),
+            gutenberg_document_free(document);
         }
     }
 
     #[test]
     fn test_root_with_a_block_with_specific_namespace() {
-        let input = str_to_c_char!("<!-- wp:foo/bar /-->");
-        let output = parse(input.as_ptr());
-
-        match output {
-            Result::Ok(result) => match result {
-                Vector_Node { buffer, length } if length == 1 => match unsafe { &*buffer } {
-                    Node::Block {
-                        namespace,
-                        name,
-                        attributes,
-                        children,
-                    } => {
-                        assert_eq!(slice_c_char_to_str!(namespace), "foo");
-                        assert_eq!(slice_c_char_to_str!(name), "bar");
-
-                        match attributes {
-                            Option_c_char::None => assert!(true),
-                            _ => assert!(false),
-                        }
-
-                        let children = unsafe { &*(children as *const _ as *const Vector_Node) };
-
-                        assert_eq!(children.length, 0);
-                    }
+        let document = parse!("<!-- wp:foo/bar /-->");
 
-                    _ => assert!(false),
-                },
+        unsafe {
+            let node = gutenberg_document_node(document, 0);
 
-                _ => assert!(false),
-            },
+            assert_eq!(slice_c_char_to_str!(gutenberg_node_namespace(node)), "foo");
+            assert_eq!(slice_c_char_to_str!(gutenberg_node_name(node)), "bar");
+
+            gutenberg_document_free(document);
+        }
+    }
+
+    #[test]
+    fn test_children() {
+        let document = parse!("<!-- wp:foo -->a<!-- wp:bar /-->b<!-- /wp:foo -->");
+
+        unsafe {
+            let node = gutenberg_document_node(document, 0);
+
+            assert_eq!(gutenberg_node_child_count(node), 3);
+            assert_eq!(slice_c_char_to_str!(gutenberg_node_phrase(gutenberg_node_child(node, 0))), "a");
+            assert_eq!(slice_c_char_to_str!(gutenberg_node_name(gutenberg_node_child(node, 1))), "bar");
+            assert_eq!(slice_c_char_to_str!(gutenberg_node_phrase(gutenberg_node_child(node, 2))), "b");
+            assert!(gutenberg_node_child(node, 3).is_null());
+
+            gutenberg_document_free(document);
+        }
+    }
+
+    #[test]
+    fn test_input_with_nul_bytes() {
+        let document = parse!("a\0b<!-- wp:foo /-->");
+
+        unsafe {
+            assert_eq!(gutenberg_document_node_count(document), 2);
+            assert_eq!(slice_c_char_to_str!(gutenberg_node_phrase(gutenberg_document_node(document, 0))), "a\0b");
+
+            gutenberg_document_free(document);
+        }
+    }
+
+    #[test]
+    fn test_the_document_owns_the_input() {
+        let input = String::from("<!-- wp:foo /-->");
+        let document = parse!(input);
+        drop(input);
+
+        unsafe {
+            assert_eq!(slice_c_char_to_str!(gutenberg_node_name(gutenberg_document_node(document, 0))), "foo");
+
+            gutenberg_document_free(document);
+        }
+    }
+
+    extern "C" fn record_block_start(user_data: *mut c_void, namespace_: Slice_c_char, name: Slice_c_char, attributes: Option_c_char) {
+        let events = unsafe { &mut *(user_data as *mut Vec<String>) };
+        let attributes = match attributes {
+            Option_c_char::Some(attributes) => unsafe { slice_c_char_to_str!(attributes) },
+            Option_c_char::None => "",
+        };
+
+        events.push(format!("start {}/{} {}", unsafe { slice_c_char_to_str!(namespace_) }, unsafe { slice_c_char_to_str!(name) }, attributes));
+    }
+
+    extern "C" fn record_phrase(user_data: *mut c_void, phrase: Slice_c_char) {
+        let events = unsafe { &mut *(user_data as *mut Vec<String>) };
+
+        events.push(format!("phrase {}", unsafe { slice_c_char_to_str!(phrase) }));
+    }
+
+    extern "C" fn record_block_end(user_data: *mut c_void, namespace_: Slice_c_char, name: Slice_c_char) {
+        let events = unsafe { &mut *(user_data as *mut Vec<String>) };
+
+        events.push(format!("end {}/{}", unsafe { slice_c_char_to_str!(namespace_) }, unsafe { slice_c_char_to_str!(name) }));
+    }
+
+    const RECORDER: GutenbergCallbacks = GutenbergCallbacks {
+        on_block_start: Some(record_block_start),
+        on_phrase: Some(record_phrase),
+        on_block_end: Some(record_block_end),
+    };
+
+    macro_rules! parse_with_callbacks {
+        ($input:expr, $events:expr, $error:expr) => {
+            unsafe {
+                gutenberg_parse_with_callbacks(
+                    $input.as_ptr() as *const c_char,
+                    $input.len(),
+                    ptr::null(),
+                    &RECORDER,
+                    $events as *mut Vec<String> as *mut c_void,
+                    $error,
+                )
+            }
+        };
+    }
+
+    #[test]
+    fn test_callbacks() {
+        let mut events = vec![];
+
+        assert!(parse_with_callbacks!(
+            "a<!-- wp:foo {\"b\":1} -->c<!-- wp:ns/bar /--><!-- /wp:foo -->d",
+            &mut events,
+            ptr::null_mut()
+        ));
+        assert_eq!(
+            events,
+            vec![
+                "phrase a",
+                "start core/foo {\"b\":1}",
+                "phrase c",
+                "start ns/bar ",
+                "end ns/bar",
+                "end core/foo",
+                "phrase d",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_callbacks_with_an_error() {
+        let mut events = vec![];
+        let mut error = GutenbergError::from(error::Error {
+            kind: error::ErrorKind::Unknown,
+            offset: 0,
+        });
+
+        assert!(!parse_with_callbacks!("a<!-- wp:foo /-->b<!-- wp:bar -->", &mut events, &mut error));
+        assert_eq!(error.kind, GutenbergErrorKind::UnclosedBlock);
+        assert_eq!(error.offset, 18);
+        assert_eq!(events, vec!["phrase a", "start core/foo ", "end core/foo", "phrase b", "start core/bar "]);
+    }
+
+    #[test]
+    fn test_callbacks_with_a_mismatched_closing_block() {
+        let mut events = vec![];
+        let mut error = GutenbergError::from(error::Error {
+            kind: error::ErrorKind::Unknown,
+            offset: 0,
+        });
+
+        assert!(!parse_with_callbacks!("<!-- wp:foo -->a<!-- wp:bar -->b<!-- /wp:foo -->", &mut events, &mut error));
+        assert_eq!(error.kind, GutenbergErrorKind::MismatchedClosingBlock);
+        assert_eq!(error.offset, 32);
+        assert_eq!(events, vec!["start core/foo ", "phrase a", "start core/bar ", "phrase b"]);
+    }
+
+    #[test]
+    fn test_callbacks_with_options() {
+        let mut events = vec![];
+        let options = GutenbergParseOptions {
+            default_namespace: into_slice(b"acme"),
+            lenient_names: true,
+            delimiter_prefix: into_slice(b"x"),
+        };
+        let input = "<!-- x:Foo --><!-- wp:bar /--><!-- /x:Foo -->";
+
+        unsafe {
+            assert!(gutenberg_parse_with_callbacks(
+                input.as_ptr() as *const c_char,
+                input.len(),
+                &options,
+                &RECORDER,
+                &mut events as *mut Vec<String> as *mut c_void,
+                ptr::null_mut(),
+            ));
+        }
+
+        assert_eq!(events, vec!["start acme/Foo ", "phrase <!-- wp:bar /-->", "end acme/Foo"]);
+    }
+
+    #[test]
+    fn test_missing_callbacks() {
+        let callbacks = GutenbergCallbacks {
+            on_block_start: None,
+            on_phrase: None,
+            on_block_end: None,
+        };
+        let input = "<!-- wp:foo /-->";
+
+        unsafe {
+            assert!(gutenberg_parse_with_callbacks(input.as_ptr() as *const c_char, input.len(), ptr::null(), &callbacks, ptr::null_mut(), ptr::null_mut()));
+            assert!(!gutenberg_parse_with_callbacks(input.as_ptr() as *const c_char, input.len(), ptr::null(), ptr::null(), ptr::null_mut(), ptr::null_mut()));
+            assert!(!gutenberg_parse_with_callbacks(ptr::null(), 1, ptr::null(), &callbacks, ptr::null_mut(), ptr::null_mut()));
+
+            let options = GutenbergParseOptions {
+                default_namespace: Slice_c_char { pointer: ptr::null(), length: 1 },
+                lenient_names: false,
+                delimiter_prefix: into_slice(b"wp"),
+            };
+
+            assert!(!gutenberg_parse_with_callbacks(input.as_ptr() as *const c_char, input.len(), &options, &callbacks, ptr::null_mut(), ptr::null_mut()));
+        }
+    }
+
+    #[test]
+    fn test_null_pointers() {
+        unsafe {
+            assert!(gutenberg_parse(ptr::null(), 1).is_null());
+            assert_eq!(gutenberg_document_node_count(ptr::null()), 0);
+            assert!(gutenberg_document_node(ptr::null(), 0).is_null());
+            assert_eq!(gutenberg_node_child_count(ptr::null()), 0);
+
+            gutenberg_document_free(ptr::null_mut());
+
+            let document = gutenberg_parse(ptr::null(), 0);
+
+            assert_eq!(gutenberg_document_node_count(document), 0);
 
-            _ => assert!(false),
+            gutenberg_document_free(document);
         }
     }
 }
@@ -1,11 +1,36 @@
+extern crate gutenberg_post_parser;
+extern crate gutenberg_post_parser_c;
 extern crate rayon;
 
+use gutenberg_post_parser::{error, root};
+use gutenberg_post_parser_c::*;
 use rayon::prelude::*;
 use std::fs;
 use std::env;
 use std::path::PathBuf;
 use std::ffi::OsStr;
+use std::os::raw::{c_char, c_void};
 use std::process::Command;
+use std::ptr;
+use std::slice;
+use std::str;
+
+fn parse(input: &[u8]) -> *mut GutenbergDocument {
+    unsafe { gutenberg_parse(input.as_ptr() as *const c_char, input.len()) }
+}
+
+/// The exit code of `bin/gutenberg-post-parser` for a fixture: 5 when
+/// the fixture is malformed on purpose, and not parsed entirely.
+fn expected_exit_code(input_path: &PathBuf) -> i32 {
+    let input = fs::read(input_path).unwrap();
+    let (remaining, _nodes) = root(&input).unwrap();
+
+    if remaining.is_empty() { 0 } else { 5 }
+}
+
+fn slice_to_str<'a>(slice: Slice_c_char) -> &'a str {
+    unsafe { str::from_utf8(slice::from_raw_parts(slice.pointer as *const u8, slice.length)).unwrap() }
+}
 
 #[test]
 fn run_all_fixtures() {
@@ -20,6 +45,8 @@ fn run_all_fixtures() {
 
         if let Some(extension) = input_path.extension() {
             if extension == html {
+                let exit_code = expected_exit_code(&input_path);
+
                 (0..255).into_par_iter().for_each(
                     |_| {
                         let output =
@@ -29,7 +56,7 @@ fn run_all_fixtures() {
                                 .expect("Failed to execute `gutenberg-post-parser`.");
 
                         assert!(
-                            output.status.success(),
+                            output.status.code() == Some(exit_code),
                             format!(
                                 "Failed to parse {:?}\nStatus: {}\nOutput: {}",
                                 input_path,
@@ -43,3 +70,251 @@ fn run_all_fixtures() {
         }
     }
 }
+
+#[test]
+fn run_all_fixtures_with_valgrind() {
+    if Command::new("valgrind").arg("--version").output().is_err() {
+        eprintln!("`valgrind` is not installed, skipping.");
+
+        return;
+    }
+
+    let mut fixtures_directory = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    fixtures_directory.push("../../tests/fixtures");
+
+    let html = OsStr::new("html");
+
+    for entry in fs::read_dir(fixtures_directory).unwrap() {
+        let entry = entry.unwrap();
+        let input_path = entry.path();
+
+        if let Some(extension) = input_path.extension() {
+            if extension == html {
+                let output =
+                    Command::new("valgrind")
+                        .arg("--quiet")
+                        .arg("--leak-check=full")
+                        .arg("--errors-for-leak-kinds=definite,indirect")
+                        .arg("--error-exitcode=42")
+                        .arg("bin/gutenberg-post-parser")
+                        .arg(&input_path)
+                        .output()
+                        .expect("Failed to execute `valgrind`.");
+
+                assert!(
+                    output.status.code() == Some(expected_exit_code(&input_path)),
+                    "Memory errors while parsing {:?}\nStatus: {}\nValgrind: {}",
+                    input_path,
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr)
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn fixtures_are_diagnosed_like_the_parser() {
+    let mut fixtures_directory = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    fixtures_directory.push("../../tests/fixtures");
+
+    let html = OsStr::new("html");
+
+    for entry in fs::read_dir(fixtures_directory).unwrap() {
+        let input_path = entry.unwrap().path();
+
+        if input_path.extension() == Some(html) {
+            let input = fs::read(&input_path).unwrap();
+            let (remaining, _nodes) = root(&input).unwrap();
+            let document = parse(&input);
+
+            unsafe {
+                let error = gutenberg_document_error(document).as_ref().map(|error| (error.kind, error.offset));
+                let expected_error = error::diagnose(&input, remaining).map(|error| (GutenbergErrorKind::from(error.kind), error.offset));
+
+                assert_eq!(error, expected_error, "{:?} is not diagnosed like the parser does.", input_path);
+                assert!(gutenberg_document_node_count(document) > 0);
+
+                gutenberg_document_free(document);
+            }
+        }
+    }
+}
+
+extern "C" fn record_block_start(user_data: *mut c_void, namespace_: Slice_c_char, name: Slice_c_char, attributes: Option_c_char) {
+    let events = unsafe { &mut *(user_data as *mut Vec<String>) };
+
+    events.push(format!("start {}/{} {}", slice_to_str(namespace_), slice_to_str(name), attributes_to_str(attributes)));
+}
+
+extern "C" fn record_phrase(user_data: *mut c_void, phrase: Slice_c_char) {
+    let events = unsafe { &mut *(user_data as *mut Vec<String>) };
+
+    events.push(format!("phrase {}", slice_to_str(phrase)));
+}
+
+extern "C" fn record_block_end(user_data: *mut c_void, namespace_: Slice_c_char, name: Slice_c_char) {
+    let events = unsafe { &mut *(user_data as *mut Vec<String>) };
+
+    events.push(format!("end {}/{}", slice_to_str(namespace_), slice_to_str(name)));
+}
+
+fn attributes_to_str<'a>(attributes: Option_c_char) -> &'a str {
+    match attributes {
+        Option_c_char::Some(attributes) => slice_to_str(attributes),
+        Option_c_char::None => "",
+    }
+}
+
+fn record_node(node: *const GutenbergNode, events: &mut Vec<String>) {
+    unsafe {
+        if gutenberg_node_kind(node) == GutenbergNodeKind::Phrase {
+            events.push(format!("phrase {}", slice_to_str(gutenberg_node_phrase(node))));
+
+            return;
+        }
+
+        let namespace = slice_to_str(gutenberg_node_namespace(node));
+        let name = slice_to_str(gutenberg_node_name(node));
+
+        events.push(format!("start {}/{} {}", namespace, name, attributes_to_str(gutenberg_node_attributes(node))));
+
+        for index in 0..gutenberg_node_child_count(node) {
+            record_node(gutenberg_node_child(node, index), events);
+        }
+
+        events.push(format!("end {}/{}", namespace, name));
+    }
+}
+
+#[test]
+fn callbacks_stream_the_same_nodes_as_the_document() {
+    let mut fixtures_directory = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    fixtures_directory.push("../../tests/fixtures");
+
+    let html = OsStr::new("html");
+    let callbacks = GutenbergCallbacks {
+        on_block_start: Some(record_block_start),
+        on_phrase: Some(record_phrase),
+        on_block_end: Some(record_block_end),
+    };
+
+    for entry in fs::read_dir(fixtures_directory).unwrap() {
+        let input_path = entry.unwrap().path();
+
+        if input_path.extension() == Some(html) {
+            let input = fs::read(&input_path).unwrap();
+            let mut streamed_events = vec![];
+            let mut document_events = vec![];
+            let mut streamed_error = GutenbergError {
+                kind: GutenbergErrorKind::Unknown,
+                offset: 0,
+                message: Slice_c_char { pointer: ptr::null(), length: 0 },
+            };
+
+            unsafe {
+                let parsed_entirely = gutenberg_parse_with_callbacks(
+                    input.as_ptr() as *const c_char,
+                    input.len(),
+                    ptr::null(),
+                    &callbacks,
+                    &mut streamed_events as *mut Vec<String> as *mut c_void,
+                    &mut streamed_error,
+                );
+
+                let document = parse(&input);
+
+                for index in 0..gutenberg_document_node_count(document) {
+                    record_node(gutenberg_document_node(document, index), &mut document_events);
+                }
+
+                match gutenberg_document_error(document).as_ref() {
+                    Some(error) => {
+                        assert!(!parsed_entirely, "{:?} is parsed entirely.", input_path);
+                        assert_eq!((streamed_error.kind, streamed_error.offset), (error.kind, error.offset), "Errors differ for {:?}.", input_path);
+                    }
+
+                    None => assert!(parsed_entirely, "{:?} is not parsed entirely.", input_path),
+                }
+
+                gutenberg_document_free(document);
+
+                if parsed_entirely {
+                    assert_eq!(streamed_events, document_events, "Events differ for {:?}.", input_path);
+                } else {
+                    // The events of a malformed input continue into
+                    // the blocks that are never closed.
+                    assert!(streamed_events.starts_with(&document_events), "Events differ for {:?}.", input_path);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn input_with_nul_bytes_is_not_truncated() {
+    let document = parse(b"a\0b<!-- wp:foo /-->\0c");
+
+    unsafe {
+        assert!(gutenberg_document_error(document).is_null());
+        assert_eq!(gutenberg_document_node_count(document), 3);
+        assert_eq!(slice_to_str(gutenberg_node_phrase(gutenberg_document_node(document, 2))), "\0c");
+
+        gutenberg_document_free(document);
+    }
+}
+
+#[test]
+fn error_of_an_unclosed_block() {
+    let document = parse(b"abc <!-- wp:foo -->def");
+
+    unsafe {
+        let error = gutenberg_document_error(document).as_ref().unwrap();
+
+        assert_eq!(error.kind, GutenbergErrorKind::UnclosedBlock);
+        assert_eq!(error.offset, 4);
+        assert_eq!(slice_to_str(error.message), "The block is opened but never closed.");
+
+        // The nodes before the rejected data are kept.
+        assert_eq!(gutenberg_document_node_count(document), 1);
+        assert_eq!(slice_to_str(gutenberg_node_phrase(gutenberg_document_node(document, 0))), "abc ");
+
+        gutenberg_document_free(document);
+    }
+}
+
+#[test]
+fn error_of_a_mismatched_closing_block() {
+    let document = parse(b"<!-- wp:foo -->a<!-- /wp:bar -->");
+
+    unsafe {
+        let error = gutenberg_document_error(document).as_ref().unwrap();
+
+        assert_eq!(error.kind, GutenbergErrorKind::MismatchedClosingBlock);
+        assert_eq!(error.offset, 16);
+
+        gutenberg_document_free(document);
+    }
+}
+
+#[test]
+fn error_after_a_nul_byte() {
+    let document = parse(b"\0<!-- wp:foo {\"a\": -->");
+
+    unsafe {
+        let error = gutenberg_document_error(document).as_ref().unwrap();
+
+        assert_eq!(error.kind, GutenbergErrorKind::InvalidAttributes);
+        assert_eq!(error.offset, 13);
+
+        gutenberg_document_free(document);
+    }
+}
+
+#[test]
+fn null_input() {
+    unsafe {
+        assert!(gutenberg_parse(ptr::null(), 42).is_null());
+        assert!(gutenberg_document_error(ptr::null()).is_null());
+    }
+}
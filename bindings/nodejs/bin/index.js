@@ -1,5 +1,5 @@
 #!/usr/bin/env node
-const gutenberg_post_parser = require('../native');
+const gutenberg_post_parser = require('../lib');
 const fs = require('fs');
 const process = require('process');
 
@@ -13,6 +13,9 @@ function usage() {
         'FLAGS:' + "\n" +
         '    -d, --emit-debug    Compile the AST into JS debug format (default).' + "\n" +
         '    -j, --emit-json     Compile the AST into JSON format.' + "\n" +
+        '    -a, --async         Parse on the thread pool.' + "\n" +
+        '    -r, --raw-attributes' + "\n" +
+        '                        Keep the attributes as strings.' + "\n" +
         '    -h, --help          Prints help information.' + "\n\n" +
         'ARGS:' + "\n" +
         '    <INPUT>    File containing the input.' + "\n";
@@ -26,6 +29,8 @@ if (2 >= process.argv.length) {
 
 let input = '';
 let emit = 'debug';
+let async = false;
+const options = { rawAttributes: false };
 
 process.argv.slice(2).forEach(
     (argument_value) => {
@@ -48,6 +53,18 @@ process.argv.slice(2).forEach(
 
                 break;
 
+            case '-a':
+            case '--async':
+                async = true;
+
+                break;
+
+            case '-r':
+            case '--raw-attributes':
+                options.rawAttributes = true;
+
+                break;
+
             default:
                 if (argument_value && '-' === argument_value[0]) {
                     stderr.write('Argument `' + argument_value + '` is invalid.' + "\n\n");
@@ -76,17 +93,39 @@ try {
     process.exit(5);
 }
 
-const content = fs.readFileSync(input, { encoding: 'utf-8' });
-const output = gutenberg_post_parser.root(content);
+// The content is a `Buffer` of UTF-8 bytes, given as is to the parser.
+const content = fs.readFileSync(input);
 
-switch (emit) {
-    case 'debug':
-        console.log(output);
+function emitOutput(output) {
+    switch (emit) {
+        case 'debug':
+            console.log(output);
 
-        break;
+            break;
 
-    case 'json':
-        stdout.write(JSON.stringify(output, null, 4));
+        case 'json':
+            stdout.write(JSON.stringify(output, null, 4));
 
-        break;
+            break;
+    }
+}
+
+function emitError(error) {
+    if (!(error instanceof SyntaxError)) {
+        throw error;
+    }
+
+    stderr.write(error.message + "\n");
+
+    process.exit(6);
+}
+
+if (async) {
+    gutenberg_post_parser.parseAsync(content, options).then(emitOutput, emitError);
+} else {
+    try {
+        emitOutput(gutenberg_post_parser.root(content, options));
+    } catch (error) {
+        emitError(error);
+    }
 }
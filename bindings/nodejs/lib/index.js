@@ -0,0 +1,54 @@
+const native = require('../native');
+
+// A `Uint8Array` is viewed as a `Buffer`, without copying it.
+function toInput(input) {
+    if ('string' === typeof input || Buffer.isBuffer(input)) {
+        return input;
+    }
+
+    if (input instanceof Uint8Array) {
+        return Buffer.from(input.buffer, input.byteOffset, input.byteLength);
+    }
+
+    throw new TypeError('The input must be a string, a `Buffer`, or a `Uint8Array`.');
+}
+
+/**
+ * Parse `input`, a string, or a `Buffer` or a `Uint8Array` of UTF-8
+ * bytes, on the main thread.
+ *
+ * Attributes that are not valid JSON throw a `SyntaxError`, naming the
+ * block, the byte offset of the error, and the JSON error, unless
+ * `options.rawAttributes` is `true`: Attributes are then kept as
+ * strings, as written in the input.
+ */
+function root(input, options) {
+    return native.root(toInput(input), options || {});
+}
+
+/**
+ * Parse `input` like `root`, with the same options, but on the libuv thread pool, so that the
+ * event loop is not blocked. Return a `Promise` of the output.
+ */
+function parseAsync(input, options) {
+    return new Promise(
+        (resolve, reject) => {
+            native.parse_async(
+                toInput(input),
+                options || {},
+                (error, output) => {
+                    if (error) {
+                        reject(error);
+                    } else {
+                        resolve(output);
+                    }
+                }
+            );
+        }
+    );
+}
+
+module.exports = {
+    root: root,
+    parseAsync: parseAsync
+};
@@ -2,18 +2,41 @@
 
 NodeJS bindings.
 
+The input is either a string or a `Buffer` (or any `Uint8Array`)
+containing UTF-8 bytes, which avoids a UTF-16 to UTF-8 round trip.
+
+`root` parses on the main thread. `parse_async` parses on the libuv
+thread pool, and calls a NodeJS-style callback with the output; the
+`parseAsync` function of `lib/index.js` wraps it in a `Promise`. The
+nodes borrow the input, so they are sent back to the main thread with
+the `gutenberg_post_parser::wire` format, which is cheap to decode, with
+the attributes already decoded on the thread pool.
+
+The output is the canonical output model of the
+`gutenberg_post_parser::output` module, i.e. the blocks returned by
+Gutenberg `parse`. Like with Gutenberg, parsing never fails: The input
+that the parser does not recognize is recovered by
+`gutenberg_post_parser::output::parse`.
+
+Both accept an optional object of options. Attributes are parsed as
+JSON; when they are invalid, a `SyntaxError` names the block, the byte
+offset of the error in the input, and the JSON error. With the
+`rawAttributes` option, the attributes are kept as written in the
+input, as a string.
+
 */
 
 extern crate gutenberg_post_parser;
 #[macro_use] extern crate neon;
-extern crate serde_json;
-extern crate neon_serde;
 
-use gutenberg_post_parser::ast::Node;
-use neon::js::{JsArray, JsNull, JsString, JsObject, Object};
+use gutenberg_post_parser::{ast::{BlockName, Node}, json, output, wire};
+use neon::js::{JsArray, JsBoolean, JsFunction, JsNull, JsNumber, JsString, JsObject, JsUndefined, JsValue, Object};
+use neon::js::binary::JsBuffer;
+use neon::js::error::{JsError, Kind};
 use neon::mem::Handle;
 use neon::scope::Scope;
-use neon::vm::{Call, JsResult, Throw};
+use neon::task::Task;
+use neon::vm::{Arguments, Call, JsResult, Lock, VmResult};
 use std::ops::DerefMut;
 use std::str;
 
@@ -23,129 +46,363 @@ macro_rules! to_str (
     )
 );
 
+const INVALID_UTF8: &str = "The input is not valid UTF-8.";
+
+/// Options of `root` and `parse_async`.
+#[derive(Clone, Copy, Default)]
+struct Options {
+    /// Keep the attributes as written in the input, instead of parsing
+    /// them.
+    raw_attributes: bool
+}
+
 fn root(call: Call) -> JsResult<JsArray> {
     let scope = call.scope;
     let arguments = call.arguments;
 
-    let input = arguments.require(scope, 0)?.check::<JsString>()?.value();
-    let mut output: Handle<JsArray>;
+    let input = read_input(scope, &arguments)?;
+    let options = read_options(scope, &arguments, 1)?;
+
+    if str::from_utf8(&input).is_err() {
+        return JsError::throw(Kind::TypeError, INVALID_UTF8);
+    }
+
+    into_js_array(&output::parse(&input), &input, options, scope)
+}
+
+fn parse_async(call: Call) -> JsResult<JsUndefined> {
+    let scope = call.scope;
+    let arguments = call.arguments;
+
+    let input = read_input(scope, &arguments)?;
+    let options = read_options(scope, &arguments, 1)?;
+    let callback = arguments.require(scope, 2)?.check::<JsFunction>()?;
+
+    ParseTask { input, options }.schedule(callback);
+
+    Ok(JsUndefined::new())
+}
+
+register_module!(
+    module,
+    {
+        module.export("root", root)?;
+        module.export("parse_async", parse_async)
+    }
+);
+
+/// Copy the input, a string or a `Buffer`, as bytes.
+fn read_input<'a, S: Scope<'a>>(scope: &mut S, arguments: &Arguments<'a>) -> VmResult<Vec<u8>> {
+    let argument = arguments.require(scope, 0)?;
+
+    if let Some(string) = argument.downcast::<JsString>() {
+        return Ok(string.value().into_bytes());
+    }
+
+    let mut buffer = argument.check::<JsBuffer>()?;
+
+    Ok(buffer.grab(|contents| contents.as_slice().to_vec()))
+}
+
+/// Read the options, an optional object, at `index`.
+fn read_options<'a, S: Scope<'a>>(scope: &mut S, arguments: &Arguments<'a>, index: i32) -> VmResult<Options> {
+    let mut options = Options::default();
+
+    if let Some(object) = arguments.get(scope, index).and_then(|argument| argument.downcast::<JsObject>()) {
+        if let Some(raw_attributes) = object.get(scope, "rawAttributes")?.downcast::<JsBoolean>() {
+            options.raw_attributes = raw_attributes.value();
+        }
+    }
+
+    Ok(options)
+}
+
+/// Parse an owned input on the libuv thread pool.
+struct ParseTask {
+    input: Vec<u8>,
+    options: Options
+}
+
+impl Task for ParseTask {
+    type Output = Vec<u8>;
+    type Error = &'static str;
+    type JsEvent = JsArray;
+
+    fn perform(&self) -> Result<Self::Output, Self::Error> {
+        let input = self.input.as_slice();
+
+        str::from_utf8(input).map_err(|_| INVALID_UTF8)?;
+
+        // Attributes are decoded here, on the thread pool, unless they
+        // are kept as written in the input.
+        let encode_options = wire::EncodeOptions { attribute_values: !self.options.raw_attributes };
+        let mut encoding = vec![];
+
+        wire::encode_with_options(input, &output::parse(input), None, &encode_options, &mut encoding);
+
+        Ok(encoding)
+    }
+
+    fn complete<'a, T: Scope<'a>>(self, scope: &'a mut T, result: Result<Self::Output, Self::Error>) -> JsResult<Self::JsEvent> {
+        let encoding = match result {
+            Ok(encoding) => encoding,
+            Err(message) => return JsError::throw(Kind::TypeError, message)
+        };
+
+        match wire::decode(&self.input, &encoding) {
+            Ok((nodes, _error)) => wire_into_js_array(&nodes, &self.input, self.options, scope),
+            Err(error) => JsError::throw(Kind::Error, error.kind.description())
+        }
+    }
+}
+
+fn into_js_array<'a, 'b, S: Scope<'b>>(nodes: &[Node<'a>], input: &'a [u8], options: Options, scope: &mut S) -> JsResult<'b, JsArray> {
+    blocks_into_js_array(&output::blocks(nodes), input, options, scope)
+}
 
-    if let Ok((_remaining, nodes)) = gutenberg_post_parser::root(input.as_bytes()) {
-        output = JsArray::new(scope, nodes.len() as u32);
+fn blocks_into_js_array<'a, 'b, S: Scope<'b>>(blocks: &[output::Block<'a>], input: &'a [u8], options: Options, scope: &mut S) -> JsResult<'b, JsArray> {
+    let mut output = JsArray::new(scope, blocks.len() as u32);
 
+    {
         let raw_output = output.deref_mut();
 
-        for (index, node) in nodes.iter().enumerate() {
+        for (index, block) in blocks.iter().enumerate() {
             raw_output.set(
                 index as u32,
-                into_js_object(&node, scope)?
+                into_js_object(block, input, options, scope)?
             )?;
         }
-    } else {
-        output = JsArray::new(scope, 0u32);
     }
 
     Ok(output)
 }
 
-register_module!(
-    module,
+fn into_js_object<'a, 'b, S: Scope<'b>>(block: &output::Block<'a>, input: &'a [u8], options: Options, scope: &mut S) -> JsResult<'b, JsObject> {
+    let attributes: Handle<JsValue> = match block.attributes {
+        output::Attributes::Empty => JsObject::new(scope).upcast(),
+
+        output::Attributes::Value(raw, _) | output::Attributes::Invalid(raw, _) if options.raw_attributes => {
+            JsString::new_or_throw(scope, to_str!(raw))?.upcast()
+        },
+
+        output::Attributes::Value(_, ref value) => into_js_value(value, scope)?,
+        output::Attributes::Invalid(raw, ref error) => return throw_invalid_attributes(block.name, raw, error, input)
+    };
+
+    let inner_blocks = blocks_into_js_array(&block.inner_blocks, input, options, scope)?;
+
+    new_js_block(block.name, attributes, inner_blocks, &block.inner_content, scope)
+}
+
+/// Map nodes decoded from the wire format to blocks, like
+/// `into_js_array` does with the nodes of the AST.
+fn wire_into_js_array<'a, 'b, S: Scope<'b>>(nodes: &[wire::Node<'a>], input: &'a [u8], options: Options, scope: &mut S) -> JsResult<'b, JsArray> {
+    let mut output = JsArray::new(scope, nodes.len() as u32);
+
     {
-        module.export("root", root)
+        let raw_output = output.deref_mut();
+
+        for (index, node) in nodes.iter().enumerate() {
+            raw_output.set(
+                index as u32,
+                wire_into_js_object(node, input, options, scope)?
+            )?;
+        }
     }
-);
 
-fn into_js_object<'a, 'b, S: Scope<'b>>(node: &Node<'a>, scope: &mut S) -> JsResult<'b, JsObject> {
-    let output = JsObject::new(scope);
+    Ok(output)
+}
 
-    match *node {
-        Node::Block { name, attributes, ref children } => {
-            // Name.
-            output.set(
-                "blockName",
-                JsString::new_or_throw(
-                    scope,
-                    &format!(
-                        "{}/{}",
-                        to_str!(name.0),
-                        to_str!(name.1)
-                    )
-                )?
-            )?;
+fn wire_into_js_object<'a, 'b, S: Scope<'b>>(node: &wire::Node<'a>, input: &'a [u8], options: Options, scope: &mut S) -> JsResult<'b, JsObject> {
+    match node {
+        wire::Node::Block { name, attributes, children } => {
+            // Without the `rawAttributes` option, attributes are
+            // encoded as written in the input only when they are
+            // invalid: They are parsed again to report the error.
+            let attributes: Handle<JsValue> = match attributes {
+                None => JsObject::new(scope).upcast(),
+                Some(wire::Attributes::Raw(raw)) if options.raw_attributes => JsString::new_or_throw(scope, to_str!(raw))?.upcast(),
+                Some(wire::Attributes::Value(value)) => into_js_value(value, scope)?,
 
-            // Attributes.
-            output.set(
-                "attrs",
-                if let Some(attributes) = attributes {
-                    let json =
-                        serde_json::from_slice::<serde_json::Value>(attributes)
-                        .map_err(|_| Throw)?;
-
-                    neon_serde::to_value(scope, &json)?
-                } else {
-                    JsNull::new().upcast()
+                Some(wire::Attributes::Raw(raw)) => match json::parse(raw) {
+                    Ok(value) => into_js_value(&value, scope)?,
+                    Err(error) => return throw_invalid_attributes(Some(*name), raw, &error, input)
                 }
-            )?;
+            };
 
-            // Inner blocks.
-            let number_of_blocks =
-                children
-                .iter()
-                .fold(
-                    0u32,
-                    |accumulator, node| {
-                        accumulator +
-                            match node {
-                                Node::Block { .. } => 1,
-                                _                  => 0
-                            }
-                    }
-                );
-            let mut blocks = JsArray::new(scope, number_of_blocks);
-            let mut phrases = String::new();
+            let number_of_inner_blocks = children.iter().filter(|child| match child {
+                wire::Node::Block { .. } => true,
+                wire::Node::Phrase(_) => false
+            }).count();
+
+            let mut inner_blocks = JsArray::new(scope, number_of_inner_blocks as u32);
+            let mut inner_content = Vec::with_capacity(children.len());
 
             {
-                let raw_blocks = blocks.deref_mut();
+                let raw_inner_blocks = inner_blocks.deref_mut();
                 let mut index = 0u32;
 
-                for node in children {
-                    match node {
-                        Node::Block { .. } => {
-                            raw_blocks.set(
-                                index,
-                                into_js_object(&node, scope)?
-                            )?;
-
+                for child in children {
+                    match child {
+                        wire::Node::Block { .. } => {
+                            raw_inner_blocks.set(index, wire_into_js_object(child, input, options, scope)?)?;
                             index += 1;
+                            inner_content.push(None);
                         },
 
-                        Node::Phrase(phrase) => {
-                            phrases.push_str(to_str!(phrase));
-                        }
+                        wire::Node::Phrase(phrase) => inner_content.push(Some(*phrase))
                     }
                 }
             }
 
-            let phrases = JsString::new_or_throw(scope, phrases.as_str())?;
-
-            output.set("innerBlocks", blocks)?;
-            output.set("innerHTML", phrases)?;
+            new_js_block(Some(*name), attributes, inner_blocks, &inner_content, scope)
         },
 
-        Node::Phrase(phrase) => {
-            output.set(
-                "attrs",
-                JsObject::new(scope)
-            )?;
-            output.set(
-                "innerHTML",
-                JsString::new_or_throw(
-                    scope,
-                    to_str!(phrase)
-                )?
-            )?;
+        // A top-level phrase is a freeform block.
+        wire::Node::Phrase(phrase) => {
+            let attributes = JsObject::new(scope).upcast();
+            let inner_blocks = JsArray::new(scope, 0u32);
+
+            new_js_block(None, attributes, inner_blocks, &[Some(*phrase)], scope)
+        }
+    }
+}
+
+/// Build a block object, with `innerHTML` computed from
+/// `inner_content`.
+fn new_js_block<'a, 'b, S: Scope<'b>>(
+    name: Option<BlockName<'a>>,
+    attributes: Handle<'b, JsValue>,
+    inner_blocks: Handle<'b, JsArray>,
+    inner_content: &[Option<&'a [u8]>],
+    scope: &mut S
+) -> JsResult<'b, JsObject> {
+    let output = JsObject::new(scope);
+
+    // Name.
+    let name: Handle<JsValue> = match name {
+        Some(name) => JsString::new_or_throw(scope, &name.fully_qualified())?.upcast(),
+        None => JsNull::new().upcast()
+    };
+
+    output.set("blockName", name)?;
+
+    // Attributes.
+    output.set("attrs", attributes)?;
+
+    // Inner blocks.
+    output.set("innerBlocks", inner_blocks)?;
+
+    // Phrases.
+    let inner_html: Vec<u8> = inner_content.iter().filter_map(|item| *item).flat_map(|phrase| phrase.iter().cloned()).collect();
+    let inner_html = JsString::new_or_throw(scope, to_str!(&inner_html))?;
+
+    output.set("innerHTML", inner_html)?;
+
+    let mut js_inner_content = JsArray::new(scope, inner_content.len() as u32);
+
+    {
+        let raw_inner_content = js_inner_content.deref_mut();
+
+        for (index, item) in inner_content.iter().enumerate() {
+            let item: Handle<JsValue> = match item {
+                Some(phrase) => JsString::new_or_throw(scope, to_str!(phrase))?.upcast(),
+                None => JsNull::new().upcast()
+            };
+
+            raw_inner_content.set(index as u32, item)?;
         }
     }
 
+    output.set("innerContent", js_inner_content)?;
+
     Ok(output)
 }
+
+/// Throw a `SyntaxError` for invalid attributes, with the offset of
+/// the error in the input.
+fn throw_invalid_attributes<T>(name: Option<BlockName>, raw: &[u8], error: &json::Error, input: &[u8]) -> VmResult<T> {
+    JsError::throw(
+        Kind::SyntaxError,
+        &format!(
+            "Invalid attributes of the block `{}` at offset {}: {}",
+            name.map(|name| name.fully_qualified()).unwrap_or_default(),
+            raw.as_ptr() as usize - input.as_ptr() as usize + error.offset,
+            error.kind.description()
+        )
+    )
+}
+
+fn into_js_value<'a, 'b, S: Scope<'b>>(value: &json::Value<'a>, scope: &mut S) -> JsResult<'b, JsValue> {
+    Ok(
+        match value {
+            json::Value::Null => JsNull::new().upcast(),
+            json::Value::Boolean(boolean) => JsBoolean::new(scope, *boolean).upcast(),
+            json::Value::Number(number) => JsNumber::new(scope, to_str!(number).parse().unwrap_or(f64::NAN)).upcast(),
+            json::Value::String(string) => JsString::new_or_throw(scope, string)?.upcast(),
+
+            json::Value::Array(items) => {
+                let mut array = JsArray::new(scope, items.len() as u32);
+
+                {
+                    let raw_array = array.deref_mut();
+
+                    for (index, item) in items.iter().enumerate() {
+                        raw_array.set(index as u32, into_js_value(item, scope)?)?;
+                    }
+                }
+
+                array.upcast()
+            },
+
+            // The last duplicated member wins, like with `JSON.parse`.
+            json::Value::Object(members) => {
+                let object = JsObject::new(scope);
+
+                for (key, value) in members {
+                    let value = into_js_value(value, scope)?;
+
+                    // Like `JSON.parse`, a `__proto__` key is an own
+                    // property, not the prototype, which `set` would
+                    // assign.
+                    if key == "__proto__" {
+                        define_own_property(object, key, value, scope)?;
+                    } else {
+                        object.set(key.as_str(), value)?;
+                    }
+                }
+
+                object.upcast()
+            }
+        }
+    )
+}
+
+/// Define an own data property, like `Object.defineProperty` does.
+fn define_own_property<'b, S: Scope<'b>>(object: Handle<'b, JsObject>, key: &str, value: Handle<'b, JsValue>, scope: &mut S) -> VmResult<()> {
+    // `Object` is read from a new object, since `object` may have an
+    // own `constructor` member.
+    let define_property =
+        JsObject::new(scope)
+            .get(scope, "constructor")?
+            .check::<JsFunction>()?
+            .get(scope, "defineProperty")?
+            .check::<JsFunction>()?;
+
+    let descriptor = JsObject::new(scope);
+
+    descriptor.set("value", value)?;
+    descriptor.set("writable", JsBoolean::new(scope, true))?;
+    descriptor.set("enumerable", JsBoolean::new(scope, true))?;
+    descriptor.set("configurable", JsBoolean::new(scope, true))?;
+
+    let arguments: Vec<Handle<JsValue>> = vec![
+        object.upcast(),
+        JsString::new_or_throw(scope, key)?.upcast(),
+        descriptor.upcast()
+    ];
+
+    define_property.call(scope, JsNull::new(), arguments)?;
+
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use std::fs;
+use std::env;
+use std::path::PathBuf;
+use std::ffi::OsStr;
+use std::process::Command;
+
+#[test]
+fn run_all_fixtures() {
+    let mut fixtures_directory = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    fixtures_directory.push("../../../tests/fixtures");
+
+    let html = OsStr::new("html");
+
+    for entry in fs::read_dir(fixtures_directory).unwrap() {
+        let entry = entry.unwrap();
+        let input_path = entry.path();
+
+        if let Some(extension) = input_path.extension() {
+            if extension == html {
+                let expected_path = input_path.as_path().with_extension("json");
+                let expected_content = fs::read_to_string(&expected_path).unwrap();
+
+                for flags in &[&["--emit-json"][..], &["--emit-json", "--async"][..]] {
+                    let output =
+                        Command::new("../bin/gutenberg-post-parser")
+                        .args(*flags)
+                        .arg(&input_path)
+                        .output()
+                        .expect("Failed to execute `gutenberg-post-parser`.");
+
+                    assert!(
+                        output.status.success(),
+                        format!(
+                            "Failed to parse {:?}\nStatus: {}\nOutput: {}",
+                            input_path,
+                            output.status,
+                            String::from_utf8_lossy(&output.stdout.as_slice())
+                        )
+                    );
+
+                    let output_content = String::from_utf8(output.stdout).unwrap();
+
+                    assert!(expected_content == output_content, format!("file = {:?}, flags = {:?}", input_path, flags));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_proto_attribute_is_an_own_property() {
+    let mut input_path = env::temp_dir();
+    input_path.push("gutenberg-post-parser-nodejs-proto.html");
+
+    fs::write(&input_path, "<!-- wp:foo {\"__proto__\":{\"a\":1}} /-->").unwrap();
+
+    // With `Object::set`, `__proto__` would be the prototype, and
+    // `attrs` would serialize as `{}`.
+    let expected_content = r#"[
+    {
+        "blockName": "core/foo",
+        "attrs": {
+            "__proto__": {
+                "a": 1
+            }
+        },
+        "innerBlocks": [],
+        "innerHTML": "",
+        "innerContent": []
+    }
+]"#;
+
+    for flags in &[&["--emit-json"][..], &["--emit-json", "--async"][..]] {
+        let output =
+            Command::new("../bin/gutenberg-post-parser")
+            .args(*flags)
+            .arg(&input_path)
+            .output()
+            .expect("Failed to execute `gutenberg-post-parser`.");
+
+        assert!(output.status.success(), format!("flags = {:?}, status = {}", flags, output.status));
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), expected_content, "flags = {:?}", flags);
+    }
+}
+
+fn run_on_content(name: &str, content: &str, flags: &[&str]) -> std::process::Output {
+    let mut input_path = env::temp_dir();
+    input_path.push(format!("gutenberg-post-parser-nodejs-{}.html", name));
+
+    fs::write(&input_path, content).unwrap();
+
+    let output =
+        Command::new("../bin/gutenberg-post-parser")
+        .args(flags)
+        .arg(&input_path)
+        .output()
+        .expect("Failed to execute `gutenberg-post-parser`.");
+
+    fs::remove_file(&input_path).unwrap();
+
+    output
+}
+
+#[test]
+fn test_invalid_attributes_are_a_syntax_error() {
+    for flags in &[&["--emit-json"][..], &["--emit-json", "--async"][..]] {
+        let output = run_on_content("invalid-attributes", "a<!-- wp:foo {\"a\":} /-->", flags);
+
+        assert_eq!(output.status.code(), Some(6), "flags = {:?}", flags);
+        assert_eq!(
+            String::from_utf8(output.stderr).unwrap(),
+            "Invalid attributes of the block `core/foo` at offset 18: Unexpected character in JSON.\n",
+            "flags = {:?}",
+            flags
+        );
+    }
+}
+
+#[test]
+fn test_raw_attributes_are_strings() {
+    let expected_content = r#"[
+    {
+        "blockName": "core/foo",
+        "attrs": "{\"a\":}",
+        "innerBlocks": [],
+        "innerHTML": "",
+        "innerContent": []
+    }
+]"#;
+
+    for flags in &[&["--emit-json", "--raw-attributes"][..], &["--emit-json", "--raw-attributes", "--async"][..]] {
+        let output = run_on_content("raw-attributes", "<!-- wp:foo {\"a\":} /-->", flags);
+
+        assert!(output.status.success(), format!("flags = {:?}, status = {}", flags, output.status));
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), expected_content, "flags = {:?}", flags);
+    }
+}
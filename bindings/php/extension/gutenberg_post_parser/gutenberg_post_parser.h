@@ -1 +0,0 @@
-../../gutenberg_post_parser.h
\ No newline at end of file
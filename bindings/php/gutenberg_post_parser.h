@@ -1 +0,0 @@
-../c/bin/gutenberg_post_parser.h
\ No newline at end of file
@@ -0,0 +1,342 @@
+/*!
+
+PHP bindings.
+
+The extension is written in Rust with [`ext-php-rs`][ext-php-rs]. The
+PHP values are built directly from the AST: They are allocated and
+owned by PHP, and the AST is dropped once they are built, so that no
+memory is released by the other side of the boundary.
+
+`gutenberg_post_parse` returns `Gutenberg_Parser_Block` and
+`Gutenberg_Parser_Phrase` objects. `gutenberg_post_parse_blocks`
+returns the associative arrays of `WP_Block_Parser::parse`, i.e. the
+canonical output model of the `gutenberg_post_parser::output` module,
+with the attributes decoded like `json_decode($attributes, true)`
+does.
+
+Both read the input with its length, so a NUL byte does not truncate
+it. `gutenberg_post_parse` throws a `GutenbergParserException`,
+carrying the kind and the offset of the error, when the input cannot
+be parsed entirely. `gutenberg_post_parse_blocks` never does: Like
+`WP_Block_Parser::parse`, it recovers the rest of the input, see
+`output::parse`.
+
+[ext-php-rs]: https://github.com/davidcole1340/ext-php-rs
+
+*/
+
+#[macro_use] extern crate ext_php_rs;
+extern crate gutenberg_parser;
+
+use ext_php_rs::builders::ClassBuilder;
+use ext_php_rs::class::RegisteredClass;
+use ext_php_rs::convert::IntoZval;
+use ext_php_rs::exception::PhpException;
+use ext_php_rs::flags::{ClassFlags, PropertyFlags};
+use ext_php_rs::prelude::*;
+use ext_php_rs::types::{ZendClassObject, ZendHashTable, ZendObject, ZendStr, Zval};
+use ext_php_rs::zend::{ce, ClassEntry, ExecutorGlobals, ModuleEntry};
+use gutenberg_parser::{ast::Node, error, json, output};
+use std::str;
+
+const BLOCK_CLASS: &str = "Gutenberg_Parser_Block";
+const PHRASE_CLASS: &str = "Gutenberg_Parser_Phrase";
+
+/// Thrown when the input cannot be parsed entirely.
+#[php_class(name = "GutenbergParserException")]
+#[extends(ce::exception)]
+#[derive(Default)]
+pub struct GutenbergParserException {
+    kind: &'static str,
+    offset: i64
+}
+
+#[php_impl]
+impl GutenbergParserException {
+    /// A short identifier of the reason, e.g. `unclosed-block`.
+    pub fn get_kind(&self) -> &'static str {
+        self.kind
+    }
+
+    /// The position of the rejected block in the input, in bytes.
+    pub fn get_offset(&self) -> i64 {
+        self.offset
+    }
+}
+
+/// Declare `Gutenberg_Parser_Block` and `Gutenberg_Parser_Phrase`.
+#[php_startup]
+pub fn startup() {
+    ClassBuilder::new(BLOCK_CLASS)
+        .flags(ClassFlags::Final)
+        .property("namespace", "", PropertyFlags::Public)
+        .property("name", "", PropertyFlags::Public)
+        .property("attributes", (), PropertyFlags::Public)
+        .property("children", (), PropertyFlags::Public)
+        .build()
+        .expect("Failed to declare `Gutenberg_Parser_Block`.");
+
+    ClassBuilder::new(PHRASE_CLASS)
+        .flags(ClassFlags::Final)
+        .property("content", "", PropertyFlags::Public)
+        .build()
+        .expect("Failed to declare `Gutenberg_Parser_Phrase`.");
+}
+
+/// Parse a Gutenberg post, and return a collection of
+/// `Gutenberg_Parser_Block` and `Gutenberg_Parser_Phrase` objects.
+#[php_function]
+pub fn gutenberg_post_parse(gutenberg_post_as_string: &Zval) -> PhpResult<Vec<Zval>> {
+    let input = read_input(gutenberg_post_as_string)?;
+    let classes = Classes::find();
+
+    parse(input)?
+        .iter()
+        .map(|node| into_php_object(node, &classes))
+        .collect()
+}
+
+/// Parse a Gutenberg post, and return the same arrays as
+/// `WP_Block_Parser::parse`, including for a malformed post.
+#[php_function]
+pub fn gutenberg_post_parse_blocks(gutenberg_post_as_string: &Zval) -> PhpResult<Vec<Zval>> {
+    let input = read_input(gutenberg_post_as_string)?;
+
+    output::blocks(&output::parse(input))
+        .iter()
+        .map(into_php_block_array)
+        .collect()
+}
+
+/// Provide information about the module.
+pub extern "C" fn module_info(_module: *mut ModuleEntry) {
+    info_table_start!();
+    info_table_row!("gutenberg_post_parser support", "enabled");
+    info_table_end!();
+}
+
+#[php_module]
+pub fn get_module(module: ModuleBuilder) -> ModuleBuilder {
+    module.info_function(module_info)
+}
+
+/// Read the input as bytes, with its length.
+fn read_input(input: &Zval) -> PhpResult<&[u8]> {
+    input
+        .zend_str()
+        .map(ZendStr::as_bytes)
+        .ok_or_else(|| PhpException::new("The input must be a string.".to_string(), 0, ce::type_error()))
+}
+
+/// Parse the input entirely, or fail with a
+/// `GutenbergParserException`.
+fn parse(input: &[u8]) -> PhpResult<Vec<Node>> {
+    match gutenberg_parser::root(input) {
+        Ok((remaining, nodes)) => match error::diagnose(input, remaining) {
+            Some(error) => Err(parser_exception(error)),
+            None => Ok(nodes)
+        },
+
+        Err(_) => Err(parser_exception(error::Error { kind: error::ErrorKind::Unknown, offset: 0 }))
+    }
+}
+
+/// Map an error to a `GutenbergParserException`, whose message
+/// describes the error.
+fn parser_exception(error: error::Error) -> PhpException {
+    let message = error.kind.description();
+    let class = GutenbergParserException::get_metadata().ce();
+    let mut php_exception = PhpException::new(message.to_string(), 0, class);
+
+    let mut exception = ZendClassObject::new(
+        GutenbergParserException {
+            kind: error.kind.as_str(),
+            offset: error.offset as i64
+        }
+    );
+
+    // `message` is a protected attribute of `Exception`, so it is
+    // written from the scope of `Exception`, like
+    // `zend_update_property` does.
+    let globals = ExecutorGlobals::get_mut();
+    let scope = globals.fake_scope;
+
+    globals.fake_scope = ce::exception() as *const ClassEntry as *mut ClassEntry;
+
+    let written = exception.std.set_property("message", message);
+
+    globals.fake_scope = scope;
+
+    if written.is_ok() {
+        php_exception.set_object(exception.into_zval(false).ok());
+    }
+
+    php_exception
+}
+
+/// The classes of the nodes.
+struct Classes {
+    block: &'static ClassEntry,
+    phrase: &'static ClassEntry
+}
+
+impl Classes {
+    fn find() -> Self {
+        Classes {
+            block: ClassEntry::try_find(BLOCK_CLASS).expect("`Gutenberg_Parser_Block` is not declared."),
+            phrase: ClassEntry::try_find(PHRASE_CLASS).expect("`Gutenberg_Parser_Phrase` is not declared.")
+        }
+    }
+}
+
+/// Map a node to a `Gutenberg_Parser_Block` or a
+/// `Gutenberg_Parser_Phrase` object.
+fn into_php_object(node: &Node, classes: &Classes) -> PhpResult<Zval> {
+    match *node {
+        Node::Block { name, attributes, ref children } => {
+            let mut block = ZendObject::new(classes.block);
+
+            block.set_property("namespace", bytes_into_zval(name.namespace()))?;
+            block.set_property("name", bytes_into_zval(name.name()))?;
+
+            // The default value of `attributes` is `NULL`.
+            if let Some(attributes) = attributes {
+                block.set_property("attributes", bytes_into_zval(attributes))?;
+            }
+
+            // The default value of `children` is `NULL`.
+            if !children.is_empty() {
+                let children =
+                    children
+                        .iter()
+                        .map(|child| into_php_object(child, classes))
+                        .collect::<PhpResult<Vec<Zval>>>()?;
+
+                block.set_property("children", children)?;
+            }
+
+            Ok(block.into_zval(false)?)
+        },
+
+        Node::Phrase(phrase) => {
+            let mut phrase_object = ZendObject::new(classes.phrase);
+
+            phrase_object.set_property("content", bytes_into_zval(phrase))?;
+
+            Ok(phrase_object.into_zval(false)?)
+        }
+    }
+}
+
+/// Map a block to an array, as built by `WP_Block_Parser`, with the
+/// keys in the same order.
+fn into_php_block_array(block: &output::Block) -> PhpResult<Zval> {
+    let mut array = ZendHashTable::with_capacity(5);
+
+    array.insert(
+        "blockName",
+        match block.name {
+            Some(name) => name.fully_qualified().into_zval(false)?,
+            None => Zval::new()
+        }
+    )?;
+
+    // Invalid attributes are `NULL`, like with `json_decode`.
+    array.insert(
+        "attrs",
+        match block.attributes {
+            output::Attributes::Empty => ZendHashTable::new().into_zval(false)?,
+            output::Attributes::Value(_, ref value) => json_into_zval(value)?,
+            output::Attributes::Invalid(..) => Zval::new()
+        }
+    )?;
+
+    array.insert(
+        "innerBlocks",
+        block.inner_blocks
+            .iter()
+            .map(into_php_block_array)
+            .collect::<PhpResult<Vec<Zval>>>()?
+    )?;
+
+    array.insert("innerHTML", bytes_into_zval(&block.inner_html()))?;
+
+    array.insert(
+        "innerContent",
+        block.inner_content
+            .iter()
+            .map(
+                |item| match item {
+                    Some(phrase) => bytes_into_zval(phrase),
+                    None => Zval::new()
+                }
+            )
+            .collect::<Vec<Zval>>()
+    )?;
+
+    Ok(array.into_zval(false)?)
+}
+
+/// Map a JSON value to a PHP value, like `json_decode($json, true)`
+/// does: Objects are associative arrays, and a number is an integer
+/// if it is written as such and fits, a float otherwise.
+fn json_into_zval(value: &json::Value) -> PhpResult<Zval> {
+    Ok(
+        match *value {
+            json::Value::Null => Zval::new(),
+            json::Value::Boolean(boolean) => boolean.into_zval(false)?,
+
+            json::Value::Number(number) => {
+                // A number only contains ASCII characters.
+                let number = str::from_utf8(number).unwrap_or_default();
+
+                match number.parse::<i64>() {
+                    Ok(integer) => integer.into_zval(false)?,
+                    Err(_) => number.parse::<f64>().unwrap_or_default().into_zval(false)?
+                }
+            },
+
+            json::Value::String(ref string) => string.as_str().into_zval(false)?,
+
+            json::Value::Array(ref items) => {
+                items
+                    .iter()
+                    .map(json_into_zval)
+                    .collect::<PhpResult<Vec<Zval>>>()?
+                    .into_zval(false)?
+            },
+
+            // The last duplicated key wins, at the position of the
+            // first one.
+            json::Value::Object(ref members) => {
+                let mut array = ZendHashTable::with_capacity(members.len() as u32);
+
+                for (key, value) in members {
+                    let value = json_into_zval(value)?;
+
+                    match integer_key(key) {
+                        Some(index) => array.insert_at_index(index as u64, value)?,
+                        None => array.insert(key.as_str(), value)?
+                    }
+                }
+
+                array.into_zval(false)?
+            }
+        }
+    )
+}
+
+/// PHP turns a key that is a decimal integer, e.g. `"42"` but not
+/// `"042"`, into an integer key.
+fn integer_key(key: &str) -> Option<i64> {
+    key.parse::<i64>().ok().filter(|index| index.to_string() == key)
+}
+
+/// Map bytes to a PHP string. PHP strings are binary-safe.
+fn bytes_into_zval(bytes: &[u8]) -> Zval {
+    let mut zval = Zval::new();
+
+    zval.set_zend_string(ZendStr::new(bytes, false));
+
+    zval
+}
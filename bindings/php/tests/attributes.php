@@ -0,0 +1,47 @@
+#!/usr/bin/env php -dextension=gutenberg_post_parser
+<?php
+
+/**
+ * Check that `gutenberg_post_parse_blocks` decodes the attributes like
+ * `WP_Block_Parser::parse` does, i.e. with `json_decode($json, true)`:
+ * Floats, big integers, duplicated keys, integer-like keys, and
+ * invalid attributes, which are `NULL`.
+ */
+
+$cases = [
+    // No attributes.
+    ''
+        => [],
+    '{}'
+        => [],
+    '{"a":1.5,"b":1e2,"c":-0,"d":-0.0,"e":1E-2,"f":12345678901234567890}'
+        => ['a' => 1.5, 'b' => 100.0, 'c' => 0, 'd' => -0.0, 'e' => 0.01, 'f' => 12345678901234567890.0],
+    '{"a":1,"b":2,"a":3}'
+        => ['a' => 3, 'b' => 2],
+    '{"1":"a","01":"b","-1":"c","-0":"d","9223372036854775807":"e","9223372036854775808":"f"}'
+        => [1 => 'a', '01' => 'b', -1 => 'c', '-0' => 'd', PHP_INT_MAX => 'e', '9223372036854775808' => 'f'],
+    '{"a":null,"b":[null,true,false],"c":{"d":{}}}'
+        => ['a' => null, 'b' => [null, true, false], 'c' => ['d' => []]],
+    '{"a":"é😀\n"}'
+        => ['a' => "é😀\n"],
+    '{"a":}'
+        => null,
+    '{"a":1,}'
+        => null
+];
+
+$exit_code = 0;
+
+foreach ($cases as $attributes => $expected) {
+    $input = '<!-- wp:foo ' . ('' === $attributes ? '' : $attributes . ' ') . '/-->';
+    $output = gutenberg_post_parse_blocks($input)[0]['attrs'];
+    $decoded = '' === $attributes ? [] : json_decode($attributes, true);
+
+    if ($expected !== $output || $decoded !== $output) {
+        fwrite(STDERR, 'The attributes of `' . $input . '` are `' . var_export($output, true) . '`.' . "\n");
+
+        $exit_code = 2;
+    }
+}
+
+exit($exit_code);
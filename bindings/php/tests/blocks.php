@@ -0,0 +1,26 @@
+#!/usr/bin/env php -dextension=gutenberg_post_parser
+<?php
+
+/**
+ * Check that `gutenberg_post_parse_blocks` returns the blocks of the
+ * JSON fixture of an input, decoded like `json_decode($json, true)`,
+ * i.e. the arrays built by `WP_Block_Parser::parse`.
+ */
+
+if (2 !== $_SERVER['argc']) {
+    fwrite(STDERR, 'USAGE: ' . $_SERVER['argv'][0] . ' <INPUT>' . "\n");
+
+    exit(1);
+}
+
+$input = $_SERVER['argv'][1];
+$fixture = preg_replace('/\.html$/', '.json', $input);
+
+$expected = json_decode(file_get_contents($fixture), true);
+$output = gutenberg_post_parse_blocks(file_get_contents($input));
+
+if ($expected !== $output) {
+    fwrite(STDERR, 'The blocks of `' . $input . '` are not the ones of `' . $fixture . '`.' . "\n");
+
+    exit(2);
+}
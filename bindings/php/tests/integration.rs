@@ -1,5 +1,7 @@
+extern crate gutenberg_parser;
 extern crate rayon;
 
+use gutenberg_parser::root;
 use rayon::prelude::*;
 use std::fs;
 use std::env;
@@ -7,6 +9,15 @@ use std::path::PathBuf;
 use std::ffi::OsStr;
 use std::process::Command;
 
+/// The exit code of `bin/gutenberg-post-parser` for a fixture: 6 when
+/// the fixture is malformed on purpose, and not parsed entirely.
+fn expected_exit_code(input_path: &PathBuf) -> i32 {
+    let input = fs::read(input_path).unwrap();
+    let (remaining, _nodes) = root(&input).unwrap();
+
+    if remaining.is_empty() { 0 } else { 6 }
+}
+
 #[test]
 fn run_all_fixtures() {
     let mut fixtures_directory = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
@@ -20,6 +31,8 @@ fn run_all_fixtures() {
 
         if let Some(extension) = input_path.extension() {
             if extension == html {
+                let exit_code = expected_exit_code(&input_path);
+
                 (0..255).into_par_iter().for_each(
                     |_| {
                         let output =
@@ -29,7 +42,7 @@ fn run_all_fixtures() {
                             .expect("Failed to execute `gutenberg-post-parser`.");
 
                         assert!(
-                            output.status.success(),
+                            output.status.code() == Some(exit_code),
                             format!(
                                 "Failed to parse {:?}\nStatus: {}\nOutput: {}",
                                 input_path,
@@ -43,3 +56,119 @@ fn run_all_fixtures() {
         }
     }
 }
+
+/// Check with `blocks.php` that the blocks of each HTML fixture of
+/// `directory` are the ones of its JSON fixture.
+fn assert_blocks_are_the_json_fixtures(directory: &str) {
+    let mut fixtures_directory = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    fixtures_directory.push(directory);
+
+    let html = OsStr::new("html");
+
+    for entry in fs::read_dir(fixtures_directory).unwrap() {
+        let entry = entry.unwrap();
+        let input_path = entry.path();
+
+        if let Some(extension) = input_path.extension() {
+            if extension == html {
+                let output =
+                    Command::new("./tests/blocks.php")
+                    .arg(&input_path)
+                    .output()
+                    .expect("Failed to execute `blocks.php`.");
+
+                assert!(
+                    output.status.success(),
+                    format!(
+                        "Failed to compare the blocks of {:?}\nStatus: {}\nOutput: {}",
+                        input_path,
+                        output.status,
+                        String::from_utf8_lossy(&output.stderr.as_slice())
+                    )
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn blocks_are_the_json_fixtures() {
+    assert_blocks_are_the_json_fixtures("../../tests/fixtures");
+}
+
+/// The fixtures of `bindings/php/tests/fixtures` are the outputs of
+/// `WP_Block_Parser::parse`, including for malformed posts.
+#[test]
+fn blocks_are_the_block_parser_fixtures() {
+    assert_blocks_are_the_json_fixtures("tests/fixtures");
+}
+
+#[test]
+fn attributes_are_decoded_like_json_decode() {
+    let output =
+        Command::new("./tests/attributes.php")
+        .output()
+        .expect("Failed to execute `attributes.php`.");
+
+    assert!(
+        output.status.success(),
+        format!(
+            "Failed to compare the attributes\nStatus: {}\nOutput: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr.as_slice())
+        )
+    );
+}
+
+fn run_on_content(name: &str, content: &[u8], flags: &[&str]) -> std::process::Output {
+    let mut input_path = env::temp_dir();
+    input_path.push(format!("gutenberg-post-parser-php-{}.html", name));
+
+    fs::write(&input_path, content).unwrap();
+
+    let output =
+        Command::new("./bin/gutenberg-post-parser")
+        .arg("--emit-json")
+        .args(flags)
+        .arg(&input_path)
+        .output()
+        .expect("Failed to execute `gutenberg-post-parser`.");
+
+    fs::remove_file(&input_path).unwrap();
+
+    output
+}
+
+#[test]
+fn parse_errors_are_thrown() {
+    let output = run_on_content("unclosed-block", "é<!-- wp:foo -->bar".as_bytes(), &[]);
+
+    assert_eq!(output.status.code(), Some(6));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Parse error `unclosed-block` at offset 2: The block is opened but never closed.\n"
+    );
+}
+
+#[test]
+fn input_is_not_truncated_at_nul_bytes() {
+    let output = run_on_content("nul-bytes", b"a\0b<!-- wp:foo -->c\0d", &[]);
+
+    assert_eq!(output.status.code(), Some(6));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Parse error `unclosed-block` at offset 3: The block is opened but never closed.\n"
+    );
+}
+
+#[test]
+fn blocks_recover_an_unclosed_block() {
+    let output = run_on_content("unclosed-block-blocks", b"a<!-- wp:foo -->bar", &["--blocks"]);
+
+    assert!(output.status.success(), format!("Status: {}", output.status));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "[{\"blockName\":null,\"attrs\":[],\"innerBlocks\":[],\"innerHTML\":\"a\",\"innerContent\":[\"a\"]},\
+          {\"blockName\":\"core\\/foo\",\"attrs\":[],\"innerBlocks\":[],\"innerHTML\":\"bar\",\"innerContent\":[\"bar\"]}]"
+    );
+}
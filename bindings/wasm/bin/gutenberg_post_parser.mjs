@@ -1,100 +1,314 @@
-function writeString(module, string_buffer, string_buffer_capacity) {
-    const pointer = module.alloc(string_buffer_capacity);
-    const buffer = new Uint8ClampedArray(module.memory.buffer, pointer);
+// Write the encoded input in the memory owned by the parser handle.
+function writeString(module, string_buffer) {
+    const pointer = module.parser_input(module.parser, string_buffer.length);
 
-    for (let i = 0; i < string_buffer_capacity; i++) {
-        buffer[i] = string_buffer[i]
-    }
-
-    return pointer;
+    new Uint8Array(module.memory.buffer, pointer, string_buffer.length).set(string_buffer);
 }
 
+// The `gutenberg_post_parser::wire` format, see its documentation.
+const WIRE_VERSION = 4;
+const FLAG_ATTRIBUTE_VALUES = 1;
+const STATUS_PARSED = 0;
+const STATUS_FAILED = 1;
+const TAG_PHRASE = 0;
+const TAG_BLOCK = 1;
+const TAG_BLOCK_WITH_ATTRIBUTES = 2;
+const VALUE_NULL = 0;
+const VALUE_FALSE = 1;
+const VALUE_TRUE = 2;
+const VALUE_NUMBER = 3;
+const VALUE_STRING = 4;
+const VALUE_ARRAY = 5;
+const VALUE_OBJECT = 6;
+const VALUE_RAW = 7;
+
+// The output is owned by the parser handle, and stays valid until the
+// next parse, so it is decoded in place.
 function read(module, start_pointer) {
-    const buffer_properties = new Uint32Array(module.memory.buffer, start_pointer, 2);
-    const buffer_capacity = buffer_properties[0] / 4;
-    const buffer_length = buffer_properties[1] / 4;
-    const payload_pointer = start_pointer + 8;
+    const buffer_length = new Uint32Array(module.memory.buffer, start_pointer, 2)[1];
+    const buffer = new Uint8Array(module.memory.buffer, start_pointer + 8, buffer_length - 8);
+
+    if (WIRE_VERSION !== buffer[0]) {
+        throw new Error('Unsupported version of the wire format: ' + buffer[0] + '.');
+    }
 
-    const buffer = new Uint32Array(module.memory.buffer, payload_pointer, buffer_length);
-    const number_of_nodes = buffer[0];
+    const reader = {
+        buffer: buffer,
+        offset: 3,
+        cursor: 0,
+        attributeValues: 0 !== (buffer[1] & FLAG_ATTRIBUTE_VALUES),
+        names: []
+    };
 
-    if (0 >= number_of_nodes) {
-        return null;
+    switch (buffer[2]) {
+        case STATUS_PARSED:
+            break;
+
+        case STATUS_FAILED:
+            const kind = readString(reader);
+            const offset = readVarint(reader);
+            const message = readString(reader);
+
+            throw new GutenbergParseError(kind, offset, message);
+
+        default:
+            throw new Error('Unknown status in the wire format: ' + buffer[2] + '.');
+    }
+
+    const number_of_names = readVarint(reader);
+
+    for (let i = 0; i < number_of_names; ++i) {
+        reader.names.push(readString(reader));
     }
 
+    const number_of_nodes = readVarint(reader);
     const nodes = [];
 
-    readNodes(number_of_nodes, module, buffer, 1, nodes);
-    module.dealloc(start_pointer, buffer_capacity);
+    readNodes(number_of_nodes, module, reader, nodes);
 
     return nodes;
 }
 
-const name_cache = {};
+// Read an unsigned LEB128 varint. Numbers are not combined with
+// bitwise operators, which are limited to 32 bits.
+function readVarint(reader) {
+    let value = 0;
+    let factor = 1;
+    let byte;
 
-function readNodes(number_of_nodes, module, buffer, offset, nodes) {
-    for (let i = 0; i < number_of_nodes; ++i) {
-        const node_type = buffer[offset];
-        offset += 1;
+    do {
+        byte = reader.buffer[reader.offset];
+        reader.offset += 1;
 
-        switch (node_type) {
-            case 1: // Block.
-                const name_length = buffer[offset];
-                offset += 1;
+        value += (byte & 0x7f) * factor;
+        factor *= 128;
+    } while (byte & 0x80);
 
-                const name_buffer = buffer.subarray(offset, offset + name_length);
-                const name_cache_key = name_buffer.join(',');
+    return value;
+}
 
-                let name = name_cache[name_cache_key];
+// Read a length, and an ASCII string.
+function readString(reader) {
+    const length = readVarint(reader);
+    const string = String.fromCharCode.apply(null, reader.buffer.subarray(reader.offset, reader.offset + length));
 
-                if (!name) {
-                    name =
-                        name_cache[name_cache_key] =
-                        name_buffer.reduce((accumulator, value) => accumulator + String.fromCharCode(value), '');
-                }
+    reader.offset += length;
 
-                offset += name_length;
+    return string;
+}
 
-                const attributes_offset = buffer[offset    ];
-                const attributes_length = buffer[offset + 1];
+// Read a length, and a UTF-8 string.
+function readUTF8String(module, reader) {
+    const length = readVarint(reader);
+    const string = module.decoder.decode(reader.buffer.subarray(reader.offset, reader.offset + length));
 
-                const attributes =
-                    0 === attributes_length
-                        ? null
-                        : JSON.parse(module.input.substr(attributes_offset, attributes_length));
+    reader.offset += length;
 
-                const number_of_children = buffer[offset + 2];
-                const children = [];
+    return string;
+}
 
-                offset += 3;
-                offset = readNodes(number_of_children, module, buffer, offset, children);
+// Read the attributes of a block, either referenced in the input, or
+// already parsed.
+function readAttributes(module, reader) {
+    if (!reader.attributeValues) {
+        return module.attributes(readSpan(module, reader));
+    }
 
-                nodes.push(new module.Block(name, attributes, children));
+    if (VALUE_RAW === reader.buffer[reader.offset]) {
+        reader.offset += 1;
 
-                break;
+        return module.attributes(readSpan(module, reader));
+    }
+
+    return readValue(module, reader);
+}
+
+function readValue(module, reader) {
+    const value_type = reader.buffer[reader.offset];
+    reader.offset += 1;
+
+    switch (value_type) {
+        case VALUE_NULL:
+            return null;
+
+        case VALUE_FALSE:
+            return false;
+
+        case VALUE_TRUE:
+            return true;
+
+        case VALUE_NUMBER:
+            return Number(readString(reader));
+
+        case VALUE_STRING:
+            return readUTF8String(module, reader);
+
+        case VALUE_ARRAY:
+            const number_of_items = readVarint(reader);
+            const items = [];
+
+            for (let i = 0; i < number_of_items; ++i) {
+                items.push(readValue(module, reader));
+            }
+
+            return items;
+
+        case VALUE_OBJECT:
+            const number_of_members = readVarint(reader);
+            const object = {};
+
+            for (let i = 0; i < number_of_members; ++i) {
+                const key = readUTF8String(module, reader);
+
+                // Like `JSON.parse`, a `__proto__` key is an own
+                // property, not the prototype.
+                Object.defineProperty(
+                    object,
+                    key,
+                    {
+                        value: readValue(module, reader),
+                        writable: true,
+                        enumerable: true,
+                        configurable: true
+                    }
+                );
+            }
+
+            return object;
+
+        default:
+            throw new Error('Unknown value type in the wire format: ' + value_type + '.');
+    }
+}
+
+// Read a gap and a length, and return the corresponding substring of
+// the input. The gap is zigzag-encoded: Even gaps move forward, odd
+// gaps move backward, over the spans recovered by `output::recover`.
+function readSpan(module, reader) {
+    const gap = readVarint(reader);
+
+    reader.cursor += 0 === gap % 2 ? gap / 2 : -(gap + 1) / 2;
+
+    const offset = reader.cursor;
+    const length = readVarint(reader);
+
+    reader.cursor += length;
+
+    return module.input.substr(offset, length);
+}
+
+function readNodes(number_of_nodes, module, reader, nodes) {
+    for (let i = 0; i < number_of_nodes; ++i) {
+        const node_type = reader.buffer[reader.offset];
+        reader.offset += 1;
 
-            case 2: // Phrase.
-                const phrase_offset = buffer[offset    ];
-                const phrase_length = buffer[offset + 1];
+        switch (node_type) {
+            case TAG_BLOCK:
+            case TAG_BLOCK_WITH_ATTRIBUTES:
+                const name = reader.names[readVarint(reader)];
+                const attributes =
+                    TAG_BLOCK_WITH_ATTRIBUTES === node_type
+                        ? readAttributes(module, reader)
+                        : module.noAttributes();
+
+                const number_of_children = readVarint(reader);
+                const children = [];
+
+                readNodes(number_of_children, module, reader, children);
 
-                const phrase = module.input.substr(phrase_offset, phrase_length);
+                nodes.push(module.block(name, attributes, children));
 
-                nodes.push(new module.Phrase(phrase));
+                break;
 
-                offset += 2;
+            case TAG_PHRASE:
+                nodes.push(module.phrase(readSpan(module, reader)));
 
                 break;
 
             default:
-                console.error('unknown node type', node_type);
+                throw new Error('Unknown node type in the wire format: ' + node_type + '.');
         }
     }
+}
+
+// Map a block to the shape of the blocks returned by `parse` from the
+// `@wordpress/block-serialization-default-parser` package. Children are
+// already mapped: Phrases are strings, blocks are objects.
+function gutenbergBlock(name, attributes, children) {
+    const innerBlocks = [];
+    const innerContent = [];
+    let innerHTML = '';
+
+    for (const child of children) {
+        if ('string' === typeof child) {
+            innerHTML += child;
+            innerContent.push(child);
+        } else {
+            innerBlocks.push(child);
+            innerContent.push(null);
+        }
+    }
+
+    return {
+        blockName: name,
+        attrs: attributes,
+        innerBlocks: innerBlocks,
+        innerHTML: innerHTML,
+        innerContent: innerContent
+    };
+}
+
+function gutenbergPhrase(phrase) {
+    return phrase;
+}
+
+// Like Gutenberg, attributes that are not valid JSON are `null`.
+function gutenbergAttributes(attributes) {
+    try {
+        return JSON.parse(attributes);
+    } catch (e) {
+        return null;
+    }
+}
+
+function gutenbergNoAttributes() {
+    return {};
+}
+
+// A top-level phrase is a freeform block, with no name.
+function gutenbergFreeformBlock(node) {
+    if ('string' !== typeof node) {
+        return node;
+    }
 
-    return offset;
+    return {
+        blockName: null,
+        attrs: {},
+        innerBlocks: [],
+        innerHTML: node,
+        innerContent: [node]
+    };
+}
+
+/**
+ * Thrown when the input is not parsed entirely:
+ *     * `kind` is a short identifier of the reason, e.g. `unclosed-block`,
+ *     * `offset` is the position of the rejected block in the input
+ *       string, in UTF-16 code units, i.e. a string index,
+ *     * `message` is a human-readable description of the reason.
+ */
+export class GutenbergParseError extends Error {
+    constructor(kind, offset, message) {
+        super(message);
+
+        this.name = 'GutenbergParseError';
+        this.kind = kind;
+        this.offset = offset;
+    }
 }
 
 export class Gutenberg_Post_Parser {
-    constructor(Block, Phrase, wasmURL, textEncoder) {
+    constructor(Block, Phrase, wasmURL, textEncoder, textDecoder) {
         this.Block = Block;
         this.Phrase = Phrase;
 
@@ -103,44 +317,98 @@ export class Gutenberg_Post_Parser {
         }
 
         this._encoder = textEncoder || new TextEncoder();
+        this._decoder = textDecoder || new TextDecoder();
     }
 
-    _parse(module) {
-        const buffer = this._encoder.encode(module.input);
-        const buffer_length = buffer.length;
-        const buffer_pointer = writeString(module, buffer, buffer_length);
-
-        const output_pointer = module.root(buffer_pointer, buffer_length);
-        const result = read(module, output_pointer);
-
-        module.input = null;
+    _parse(module, parse, options) {
+        writeString(module, this._encoder.encode(module.input));
 
-        module.dealloc(buffer_pointer, buffer_length);
+        const output_pointer = parse(module.parser, options);
 
-        return result;
+        return read(module, output_pointer);
     }
 
     instantiateWASM(url, importObject) {
         return this._wasm = WebAssembly.instantiateStreaming(fetch(url), importObject).then(obj => obj.instance);
     }
 
+    _module(module, block, phrase, attributes, noAttributes) {
+        // The parser handle is created once, and reused by every parse.
+        if (undefined === this._parser) {
+            this._parser = module.exports.parser_new();
+        }
+
+        return {
+            parser: this._parser,
+            parser_input: module.exports.parser_input,
+            parser_root: module.exports.parser_root,
+            parser_parse: module.exports.parser_parse,
+            memory: module.exports.memory,
+            decoder: this._decoder,
+            block: block,
+            phrase: phrase,
+            attributes: attributes,
+            noAttributes: noAttributes,
+            input: null
+        };
+    }
+
     root(input) {
-        const _module = {};
+        return this._wasm.then(
+            (module) => {
+                const _module = this._module(
+                    module,
+                    (name, attributes, children) => new this.Block(name, attributes, children),
+                    (phrase) => new this.Phrase(phrase),
+                    JSON.parse,
+                    () => null
+                );
 
+                _module.input = input;
+
+                return this._parse(_module, _module.parser_root, 0);
+            }
+        );
+    }
+
+    /**
+     * Parse like `parse` from the `@wordpress/block-serialization-default-parser`
+     * package: Blocks are objects with the `blockName`, `attrs`,
+     * `innerBlocks`, `innerHTML`, and `innerContent` properties. The
+     * attributes are parsed by the WASM binary, so that the output is
+     * decoded in a single pass. It never throws a `GutenbergParseError`:
+     * The rest of an input that is not parsed entirely is recovered
+     * like `WP_Block_Parser` does.
+     */
+    parse(input) {
         return this._wasm.then(
             (module) => {
-                if (undefined === _module.alloc) {
-                    _module.alloc = module.exports.alloc;
-                    _module.dealloc = module.exports.dealloc;
-                    _module.root = module.exports.root;
-                    _module.memory = module.exports.memory;
-                    _module.Block = this.Block;
-                    _module.Phrase = this.Phrase;
-                }
+                const _module = this._module(
+                    module,
+                    gutenbergBlock,
+                    gutenbergPhrase,
+                    gutenbergAttributes,
+                    gutenbergNoAttributes
+                );
 
                 _module.input = input;
 
-                return this._parse(_module);
+                return this._parse(_module, _module.parser_parse, FLAG_ATTRIBUTE_VALUES).map(gutenbergFreeformBlock);
+            }
+        );
+    }
+
+    /**
+     * Release the memory owned by the parser handle. The next parse
+     * creates a new handle.
+     */
+    free() {
+        return this._wasm.then(
+            (module) => {
+                if (undefined !== this._parser) {
+                    module.exports.parser_free(this._parser);
+                    this._parser = undefined;
+                }
             }
         );
     }
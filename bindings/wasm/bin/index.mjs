@@ -1,5 +1,5 @@
 #!/usr/bin/env node --experimental-modules
-import { Gutenberg_Post_Parser } from './gutenberg_post_parser.mjs';
+import { Gutenberg_Post_Parser, GutenbergParseError } from './gutenberg_post_parser.mjs';
 import fs from 'fs';
 import path from 'path';
 import util from 'util';
@@ -108,7 +108,10 @@ try {
 
 const content = fs.readFileSync(input, { encoding: 'utf-8' });
 
-parser.root(content).then(
+// JSON is the canonical output model, see `gutenberg_post_parser::output`.
+const parsed = 'json' === emit ? parser.parse(content) : parser.root(content);
+
+parsed.then(
     (output) => {
         switch (emit) {
             case 'debug':
@@ -121,5 +124,14 @@ parser.root(content).then(
 
                 break;
         }
+    },
+    (error) => {
+        if (!(error instanceof GutenbergParseError)) {
+            throw error;
+        }
+
+        stderr.write('Parse error `' + error.kind + '` at offset ' + error.offset + ': ' + error.message + "\n");
+
+        process.exit(6);
     }
 );
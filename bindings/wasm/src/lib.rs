@@ -11,6 +11,31 @@ The workflow is basically the following:
      in the Javascript lands with specific objects. The respective AST
      are not necessarily the same.
 
+The sequence of bytes starts with its capacity and its length, as
+`u32` in little-endian, so that Javascript can deallocate it,
+followed by the AST encoded with the `gutenberg_post_parser::wire`
+format. When the input is not parsed entirely, the encoding carries
+the error, and Javascript throws it. `parser_parse` never fails: the
+rest of an input that is not parsed entirely is recovered like
+`WP_Block_Parser`, see `gutenberg_post_parser::output`. With
+`root_with_options`,
+Javascript can ask for attributes to be encoded as parsed values,
+see `wire::FLAG_ATTRIBUTE_VALUES`.
+
+To parse many posts, Javascript creates a parser handle with
+`parser_new`. The handle owns two buffers, for the input and for the
+output, whose capacity is reused from one parse to another:
+`parser_input` clears them and returns where to write the next input,
+and `parser_root` or `parser_parse` returns the output, which stays
+valid until the next parse. Javascript never deallocates per parse, so
+nothing leaks if decoding throws. The handle is released with
+`parser_free`. The AST itself is still allocated per parse, and
+dropped once encoded.
+
+`root` and `root_with_options` are deprecated: They return an output
+that Javascript must deallocate with `dealloc`, and that leaks when
+decoding throws.
+
 This module is responsible of managing WebAssembly memory allocations
 and deallocations, to panic, and to manage an out-of-memory situation.
 
@@ -26,11 +51,12 @@ and deallocations, to panic, and to manage an out-of-memory situation.
 
 extern crate gutenberg_post_parser;
 extern crate wee_alloc;
-#[macro_use] extern crate alloc;
+extern crate alloc;
 
-use gutenberg_post_parser::ast::Node;
+use gutenberg_post_parser::{error, output, wire};
+use alloc::boxed::Box;
 use alloc::vec::Vec;
-use core::{mem, slice, str::from_utf8_unchecked};
+use core::{mem, slice};
 
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
@@ -76,52 +102,21 @@ pub extern "C" fn dealloc(pointer: *mut u8, capacity: usize) {
     }
 }
 
-// Push a `u32` into `u8`s in little-endian (the WASM byte order).
-macro_rules! push_u32_as_u8s {
-    ($u32:ident in $output:ident) => (
-        $output.push((($u32      ) & 0xff) as u8);
-        $output.push((($u32 >>  8) & 0xff) as u8);
-        $output.push((($u32 >> 16) & 0xff) as u8);
-        $output.push((($u32 >> 24) & 0xff) as u8);
-    );
-
-    ($u32s:ident all in $output:ident) => (
-        for c in $u32s {
-            push_u32_as_u8s!(c in $output);
-        }
-    )
+#[deprecated(note = "Use a parser handle, see `parser_new`.")]
+#[no_mangle]
+#[allow(deprecated)]
+pub extern "C" fn root(pointer: *mut u8, length: usize) -> *const u8 {
+    root_with_options(pointer, length, 0)
 }
 
+/// `options` is a combination of `wire::FLAG_*`.
+#[deprecated(note = "Use a parser handle, see `parser_new`.")]
 #[no_mangle]
-pub extern "C" fn root(pointer: *mut u8, length: usize) -> *const u8 {
+pub extern "C" fn root_with_options(pointer: *mut u8, length: usize, options: u32) -> *const u8 {
     let input = unsafe { slice::from_raw_parts(pointer, length) };
-    let mut output: Vec<u8> = vec![0; 8];
+    let mut output = Vec::new();
 
-    if let Ok((_remaining, nodes)) = gutenberg_post_parser::root(input) {
-        let nodes_length = nodes.len() as u32;
-        let mut remaining_input = input;
-        let mut utf16_offset = 0;
-
-        output.reserve(nodes_length as usize * 14);
-        push_u32_as_u8s!(nodes_length in output);
-
-        for node in nodes {
-            remaining_input = into_bytes(&node, &remaining_input, &mut utf16_offset, &mut output);
-        }
-    }
-
-    let output_capacity = output.capacity() as u32;
-    let output_length = output.len() as u32;
-
-    output[0] = ((output_capacity      ) & 0xff) as u8;
-    output[1] = ((output_capacity >>  8) & 0xff) as u8;
-    output[2] = ((output_capacity >> 16) & 0xff) as u8;
-    output[3] = ((output_capacity >> 24) & 0xff) as u8;
-
-    output[4] = ((output_length      ) & 0xff) as u8;
-    output[5] = ((output_length >>  8) & 0xff) as u8;
-    output[6] = ((output_length >> 16) & 0xff) as u8;
-    output[7] = ((output_length >> 24) & 0xff) as u8;
+    encode(input, options, &mut output);
 
     let pointer = output.as_ptr();
 
@@ -130,107 +125,99 @@ pub extern "C" fn root(pointer: *mut u8, length: usize) -> *const u8 {
     pointer
 }
 
-fn into_bytes<'a>(node: &Node<'a>, mut remaining_input: &'a [u8], utf16_offset: &mut u32, output: &mut Vec<u8>) -> &'a [u8] {
-    match *node {
-        Node::Block { name, attributes, ref children } => {
-            let node_type = 1u32;
-            
-            push_u32_as_u8s!(node_type in output);
-
-            let name_length = name.0.len() + name.1.len() + 1;
-            let name_0 = name.0.iter().map(|c: &u8| *c as u32);
-            let name_1 = name.1.iter().map(|c: &u8| *c as u32);
-            let name_separator = b'/' as u32;
-
-            push_u32_as_u8s!(name_length in output);
-            push_u32_as_u8s!(name_0 all in output);
-            push_u32_as_u8s!(name_separator in output);
-            push_u32_as_u8s!(name_1 all in output);
-
-            let input_offset: usize = remaining_input.offset(&name.1) + name.1.len() + 1;
-            remaining_input = &remaining_input[input_offset..];
-            *utf16_offset += input_offset as u32;
-
-            let attributes_offset;
-            let attributes_length;
-
-            match attributes {
-                Some(attributes) => {
-                    let mut input_offset: usize = remaining_input.offset(&attributes);
+/// A parser handle, owning the buffers of the input and of the output,
+/// whose capacity is reused from one parse to another.
+pub struct Parser {
+    input: Vec<u8>,
+    output: Vec<u8>
+}
 
-                    *utf16_offset += input_offset as u32;
-                    attributes_offset = *utf16_offset;
-                    attributes_length = count_utf16(unsafe { from_utf8_unchecked(attributes) });
-                    *utf16_offset += attributes_length;
+#[no_mangle]
+pub extern "C" fn parser_new() -> *mut Parser {
+    Box::into_raw(Box::new(Parser { input: Vec::new(), output: Vec::new() }))
+}
 
-                    input_offset += attributes.len();
-                    remaining_input = &remaining_input[input_offset..];
-                },
+#[no_mangle]
+pub extern "C" fn parser_free(parser: *mut Parser) {
+    if !parser.is_null() {
+        unsafe {
+            let _ = Box::from_raw(parser);
+        }
+    }
+}
 
-                None => {
-                    attributes_offset = 0;
-                    attributes_length = 0;
-                }
-            };
+/// Reset the parser, and return where to write the next input of
+/// `length` bytes.
+#[no_mangle]
+pub extern "C" fn parser_input(parser: *mut Parser, length: usize) -> *mut u8 {
+    let parser = unsafe { &mut *parser };
 
-            push_u32_as_u8s!(attributes_offset in output);
-            push_u32_as_u8s!(attributes_length in output);
+    parser.output.clear();
+    parser.input.clear();
+    parser.input.resize(length, 0);
 
-            let number_of_children = children.len() as u32;
+    parser.input.as_mut_ptr()
+}
 
-            push_u32_as_u8s!(number_of_children in output);
+/// Parse the input written by Javascript. The output is owned by the
+/// parser. `options` is a combination of `wire::FLAG_*`.
+#[no_mangle]
+pub extern "C" fn parser_root(parser: *mut Parser, options: u32) -> *const u8 {
+    let parser = unsafe { &mut *parser };
 
-            for child in children {
-                remaining_input = into_bytes(&child, remaining_input, utf16_offset, output);
-            }
+    parser.output.clear();
+    encode(&parser.input, options, &mut parser.output);
 
-            remaining_input
-        },
+    parser.output.as_ptr()
+}
 
-        Node::Phrase(phrase) => {
-            let node_type = 2u32;
+/// Parse the input written by Javascript like `parser_root`, but
+/// recover the rest of the input instead of failing, see
+/// `gutenberg_post_parser::output::parse`.
+#[no_mangle]
+pub extern "C" fn parser_parse(parser: *mut Parser, options: u32) -> *const u8 {
+    let parser = unsafe { &mut *parser };
+    let nodes = output::parse(&parser.input);
 
-            push_u32_as_u8s!(node_type in output);
+    parser.output.clear();
+    parser.output.extend_from_slice(&[0; 8]);
+    wire::encode_with_options(&parser.input, &nodes, None, &encode_options(options), &mut parser.output);
+    write_header(&mut parser.output);
 
-            let mut input_offset: usize = remaining_input.offset(&phrase);
+    parser.output.as_ptr()
+}
 
-            *utf16_offset += input_offset as u32;
-            let phrase_offset = *utf16_offset;
-            let phrase_length = count_utf16(unsafe { from_utf8_unchecked(phrase) });
-            *utf16_offset += phrase_length;
+fn encode_options(options: u32) -> wire::EncodeOptions {
+    wire::EncodeOptions {
+        attribute_values: options & u32::from(wire::FLAG_ATTRIBUTE_VALUES) != 0
+    }
+}
 
-            input_offset += phrase.len();
-            remaining_input = &remaining_input[input_offset..];
+fn encode(input: &[u8], options: u32, output: &mut Vec<u8>) {
+    let options = encode_options(options);
 
-            push_u32_as_u8s!(phrase_offset in output);
-            push_u32_as_u8s!(phrase_length in output);
+    output.extend_from_slice(&[0; 8]);
 
-            remaining_input
-        }
+    match gutenberg_post_parser::root(input) {
+        Ok((remaining, nodes)) => wire::encode_with_options(input, &nodes, error::diagnose(input, remaining), &options, output),
+        Err(_) => wire::encode_with_options(input, &[], Some(error::Error { kind: error::ErrorKind::Unknown, offset: 0 }), &options, output)
     }
-}
 
-trait Offset {
-    fn offset(&self, second: &Self) -> usize;
+    write_header(output);
 }
 
-impl<'a> Offset for &'a [u8] {
-    fn offset(&self, second: &Self) -> usize {
-        second.as_ptr() as usize - self.as_ptr() as usize
-    }
-}
+/// Write the capacity and the length of `output` in its first 8 bytes.
+fn write_header(output: &mut Vec<u8>) {
+    let output_capacity = output.capacity() as u32;
+    let output_length = output.len() as u32;
 
-fn count_utf16(input: &str) -> u32 {
-    input.chars().fold(
-        0,
-        |acc, ch| {
-            let code = ch as u32;
+    output[0] = ((output_capacity      ) & 0xff) as u8;
+    output[1] = ((output_capacity >>  8) & 0xff) as u8;
+    output[2] = ((output_capacity >> 16) & 0xff) as u8;
+    output[3] = ((output_capacity >> 24) & 0xff) as u8;
 
-            if (code & 0xffff) == code {
-                acc + 1
-            } else {
-                acc + 2
-            }
-        }
-    )
+    output[4] = ((output_length      ) & 0xff) as u8;
+    output[5] = ((output_length >>  8) & 0xff) as u8;
+    output[6] = ((output_length >> 16) & 0xff) as u8;
+    output[7] = ((output_length >> 24) & 0xff) as u8;
 }
@@ -44,3 +44,130 @@ fn run_all_fixtures() {
         }
     }
 }
+
+fn run_on_content(name: &str, content: &str, emit: &str) -> std::process::Output {
+    let mut input_path = env::temp_dir();
+    input_path.push(format!("gutenberg-post-parser-wasm-{}.html", name));
+
+    fs::write(&input_path, content).unwrap();
+
+    let output =
+        Command::new("./bin/gutenberg-post-parser")
+        .arg(emit)
+        .arg(&input_path)
+        .output()
+        .expect("Failed to execute `gutenberg-post-parser`.");
+
+    fs::remove_file(&input_path).unwrap();
+
+    output
+}
+
+#[test]
+fn empty_post_is_an_empty_list() {
+    let output = run_on_content("empty", "", "--emit-json");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "[]");
+}
+
+#[test]
+fn parse_errors_are_thrown_by_root() {
+    let output = run_on_content("unclosed-block", "é<!-- wp:foo -->bar", "--emit-debug");
+
+    assert_eq!(output.status.code(), Some(6));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Parse error `unclosed-block` at offset 1: The block is opened but never closed.\n"
+    );
+}
+
+#[test]
+fn parse_errors_are_located_in_utf16_code_units() {
+    let output = run_on_content("mismatched-closing-block", "😀<!-- wp:foo -->bar<!-- /wp:baz -->", "--emit-debug");
+
+    assert_eq!(output.status.code(), Some(6));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Parse error `mismatched-closing-block` at offset 20: The closing delimiter does not match the name of the opened block.\n"
+    );
+}
+
+#[test]
+fn json_output_has_parsed_attributes_and_inner_content() {
+    let output = run_on_content(
+        "inner-content",
+        "a<!-- wp:foo {\"b\":[1,\"\\u00e9\"]} -->c<!-- wp:bar /-->d<!-- /wp:foo -->",
+        "--emit-json"
+    );
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        r#"[
+    {
+        "blockName": null,
+        "attrs": {},
+        "innerBlocks": [],
+        "innerHTML": "a",
+        "innerContent": [
+            "a"
+        ]
+    },
+    {
+        "blockName": "core/foo",
+        "attrs": {
+            "b": [
+                1,
+                "é"
+            ]
+        },
+        "innerBlocks": [
+            {
+                "blockName": "core/bar",
+                "attrs": {},
+                "innerBlocks": [],
+                "innerHTML": "",
+                "innerContent": []
+            }
+        ],
+        "innerHTML": "cd",
+        "innerContent": [
+            "c",
+            null,
+            "d"
+        ]
+    }
+]"#
+    );
+}
+
+#[test]
+fn json_output_recovers_unclosed_blocks() {
+    let output = run_on_content("recovered-unclosed-block", "é<!-- wp:foo -->bar", "--emit-json");
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        r#"[
+    {
+        "blockName": null,
+        "attrs": {},
+        "innerBlocks": [],
+        "innerHTML": "é",
+        "innerContent": [
+            "é"
+        ]
+    },
+    {
+        "blockName": "core/foo",
+        "attrs": {},
+        "innerBlocks": [],
+        "innerHTML": "bar",
+        "innerContent": [
+            "bar"
+        ]
+    }
+]"#
+    );
+}
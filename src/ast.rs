@@ -5,6 +5,9 @@ The Abstract Syntax Tree (AST), i.e. the output of the parser.
 */
 
 use super::Input;
+use super::parser::{COMMENT_AUTO_CLOSING, COMMENT_CLOSING, COMMENT_OPENING};
+use super::scanner;
+use std::string::String;
 use std::vec::Vec;
 
 /// Represent a node in an AST.
@@ -13,10 +16,9 @@ use std::vec::Vec;
 pub enum Node<'a> {
     /// A block is the elementary component of the post format.
     Block {
-        /// The fully-qualified block name, where the left part of the
-        /// pair represents the namespace, and the right part of the pair
-        /// represents the block name.
-        name: (Input<'a>, Input<'a>),
+        /// The fully-qualified block name, i.e. a namespace and a
+        /// name.
+        name: BlockName<'a>,
 
         /// A block can have attributes, just like an HTML element can
         /// have attributes. Attributes are encoded as a JSON string.
@@ -29,3 +31,216 @@ pub enum Node<'a> {
     /// Anything that is not a block.
     Phrase(Input<'a>)
 }
+
+impl<'a> Node<'a> {
+    /// The span of the node within `input`, which must be the input
+    /// given to the parser. The span of a block includes its
+    /// delimiters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate gutenberg_post_parser;
+    ///
+    /// use gutenberg_post_parser::{root, ast::Span};
+    ///
+    /// let input = &b"abc <!-- wp:foo -->def<!-- /wp:foo -->"[..];
+    /// let (_remaining, nodes) = root(input).unwrap();
+    ///
+    /// assert_eq!(nodes[0].span(input), Span { start: 0, end: 4 });
+    /// assert_eq!(nodes[1].span(input), Span { start: 4, end: 38 });
+    /// ```
+    pub fn span(&self, input: Input) -> Span {
+        match self {
+            Node::Phrase(phrase) => Span::of(phrase, input),
+
+            Node::Block { name, attributes, children } => {
+                let name_span = name.span(input);
+                let start =
+                    input[..name_span.start]
+                        .windows(COMMENT_OPENING.len())
+                        .rposition(|window| window == COMMENT_OPENING)
+                        .unwrap_or(0);
+
+                let head_end = match attributes {
+                    Some(attributes) => Span::of(attributes, input).end,
+                    None => name_span.end
+                };
+                let opening_end = delimiter_end(input, head_end);
+
+                // A void block ends with its opening delimiter.
+                if input[..opening_end].ends_with(COMMENT_AUTO_CLOSING) {
+                    return Span { start, end: opening_end };
+                }
+
+                let children_end = match children.last() {
+                    Some(child) => child.span(input).end,
+                    None => opening_end
+                };
+
+                Span {
+                    start,
+                    end: delimiter_end(input, children_end)
+                }
+            }
+        }
+    }
+}
+
+/// Offset after the first `-->` found from `offset` in `input`.
+fn delimiter_end(input: Input, offset: usize) -> usize {
+    match scanner::find(&input[offset..], COMMENT_CLOSING) {
+        Some(index) => offset + index + COMMENT_CLOSING.len(),
+        None => input.len()
+    }
+}
+
+/// Represent a block name, composed of a namespace and a name.
+///
+/// The namespace is optional in a post, e.g. `wp:paragraph` and
+/// `wp:core/paragraph` both have the `core` namespace. A block name
+/// records whether its namespace is implicit, so that both forms can
+/// be told apart. The name, and the namespace when explicit, are slices
+/// of the parser input, so that their spans can be computed. An
+/// implicit namespace is not: It is the default namespace, e.g. a
+/// static `core`, so positions must be computed with
+/// [`namespace_span`](#method.namespace_span), which is `None` then,
+/// never from [`namespace`](#method.namespace).
+///
+/// # Examples
+///
+/// ```
+/// extern crate gutenberg_post_parser;
+///
+/// use gutenberg_post_parser::ast::{BlockName, Span};
+///
+/// let input = &b"foo/bar"[..];
+/// let name = BlockName::new(&input[0..3], &input[4..7]);
+///
+/// assert_eq!(name.namespace(), &b"foo"[..]);
+/// assert_eq!(name.name(), &b"bar"[..]);
+/// assert_eq!(name.fully_qualified(), "foo/bar");
+/// assert_eq!(name.span(input), Span { start: 0, end: 7 });
+/// ```
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(not(feature = "no_std"), derive(Debug))]
+pub struct BlockName<'a> {
+    namespace: Input<'a>,
+    name: Input<'a>,
+    is_namespace_implicit: bool
+}
+
+impl<'a> BlockName<'a> {
+    /// Create a block name with an explicit namespace.
+    pub fn new(namespace: Input<'a>, name: Input<'a>) -> Self {
+        BlockName {
+            namespace,
+            name,
+            is_namespace_implicit: false
+        }
+    }
+
+    /// Create a block name with an implicit namespace, i.e. the
+    /// namespace is not part of the parser input, like the default
+    /// `core` namespace.
+    pub fn implicit(namespace: Input<'a>, name: Input<'a>) -> Self {
+        BlockName {
+            namespace,
+            name,
+            is_namespace_implicit: true
+        }
+    }
+
+    /// The namespace, either explicit or implicit. An implicit
+    /// namespace is not a slice of the parser input, see
+    /// [`namespace_span`](#method.namespace_span).
+    pub fn namespace(&self) -> Input<'a> {
+        self.namespace
+    }
+
+    /// The name, without the namespace.
+    pub fn name(&self) -> Input<'a> {
+        self.name
+    }
+
+    /// Whether the namespace is implicit, e.g. `true` for
+    /// `wp:paragraph`, and `false` for `wp:core/paragraph`.
+    pub fn is_namespace_implicit(&self) -> bool {
+        self.is_namespace_implicit
+    }
+
+    /// Whether two block names have the same namespace and the same
+    /// name, regardless of the namespaces being implicit or not.
+    pub fn is_equivalent_to(&self, other: &BlockName) -> bool {
+        self.namespace == other.namespace && self.name == other.name
+    }
+
+    /// The fully-qualified block name, i.e. `namespace/name`. Invalid
+    /// UTF-8 sequences are replaced by `U+FFFD`.
+    pub fn fully_qualified(&self) -> String {
+        let mut bytes = Vec::with_capacity(self.namespace.len() + 1 + self.name.len());
+        bytes.extend_from_slice(self.namespace);
+        bytes.push(b'/');
+        bytes.extend_from_slice(self.name);
+
+        String::from_utf8(bytes)
+            .unwrap_or_else(|error| String::from_utf8_lossy(error.as_bytes()).into_owned())
+    }
+
+    /// The span of the namespace within `input`, which must be the
+    /// input given to the parser. It is `None` if the namespace is
+    /// implicit.
+    pub fn namespace_span(&self, input: Input) -> Option<Span> {
+        if self.is_namespace_implicit {
+            None
+        } else {
+            Some(Span::of(self.namespace, input))
+        }
+    }
+
+    /// The span of the name within `input`, which must be the input
+    /// given to the parser.
+    pub fn name_span(&self, input: Input) -> Span {
+        Span::of(self.name, input)
+    }
+
+    /// The span of the whole block name, as written in `input`, which
+    /// must be the input given to the parser.
+    pub fn span(&self, input: Input) -> Span {
+        let name_span = self.name_span(input);
+
+        match self.namespace_span(input) {
+            Some(namespace_span) => Span { start: namespace_span.start, end: name_span.end },
+            None => name_span
+        }
+    }
+}
+
+/// Represent a range of bytes in the parser input.
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(not(feature = "no_std"), derive(Debug))]
+pub struct Span {
+    /// Offset of the first byte.
+    pub start: usize,
+
+    /// Offset after the last byte.
+    pub end: usize
+}
+
+impl Span {
+    /// Compute the span of `slice` within `input`. `slice` must be a
+    /// sub-slice of `input`.
+    pub fn of(slice: Input, input: Input) -> Self {
+        debug_assert!(
+            slice.as_ptr() >= input.as_ptr() && slice.as_ptr() as usize + slice.len() <= input.as_ptr() as usize + input.len(),
+            "The slice is not part of the input."
+        );
+
+        let start = slice.as_ptr() as usize - input.as_ptr() as usize;
+
+        Span {
+            start,
+            end: start + slice.len()
+        }
+    }
+}
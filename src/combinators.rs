@@ -15,13 +15,14 @@ use std::vec::Vec;
 /// lookahead combinator `C`. It's not similar to
 /// `terminated!(take_until(S, peek!(C)))` because it loops over the
 /// input until `C` is true.
+///
+/// `S` is searched with the [`scanner`](../scanner/index.html).
 #[macro_export]
 macro_rules! take_until_terminated (
     (_ $input:expr, $substr:expr, $consume:expr, $submac:ident!( $($args:tt)* )) => (
         {
             use ::nom::{
                 ErrorKind,
-                FindSubstring,
                 IResult,
                 Needed,
                 Slice,
@@ -29,11 +30,12 @@ macro_rules! take_until_terminated (
             };
 
             let input = $input;
-            let substr_length = $substr.len();
+            let substr = ::nom::AsBytes::as_bytes(&$substr);
+            let substr_length = substr.len();
             let mut index = 0;
             let mut result: Option<IResult<_, _>> = None;
 
-            while let Some(next_index) = input.slice(index..).find_substring($substr) {
+            while let Some(next_index) = $crate::scanner::find(input.slice(index..), substr) {
                 match $submac!(input.slice(index + next_index + substr_length..), $($args)*) {
                     Ok(_) => {
                         let separator = if $consume {
@@ -154,6 +156,18 @@ pub(crate) fn is_alphanumeric_extended(chr: u8) -> bool {
     (chr >= 0x61 && chr <= 0x7a) || (chr >= 0x30 && chr <= 0x39) || chr == b'_' || chr == b'-'
 }
 
+/// Check whether a character is in the set of lenient alphanumeric
+/// characters, i.e. `[a-zA-Z0-9]`.
+pub(crate) fn is_lenient_alphanumeric(chr: u8) -> bool {
+    chr.is_ascii_alphanumeric()
+}
+
+/// Check whether a character is in the set of lenient alphanumeric
+/// extended characters, i.e. `[a-zA-Z0-9_-]`.
+pub(crate) fn is_lenient_alphanumeric_extended(chr: u8) -> bool {
+    is_lenient_alphanumeric(chr) || chr == b'_' || chr == b'-'
+}
+
 /// Check whether a character is a whitespace, i.e. `[ \n\r\t]`.
 pub(crate) fn is_whitespace(chr: u8) -> bool {
     chr == b' ' || chr == b'\n' || chr == b'\r' || chr == b'\t'
@@ -0,0 +1,363 @@
+/*!
+
+Diagnose why a post has not been parsed entirely.
+
+The parser never fails on a malformed block: It stops, and returns the
+remaining data, i.e. the data that have not been parsed. The remaining
+data always start at the delimiter of the first block that could not
+be recognized. The [`diagnose`](./fn.diagnose.html) function analyses
+this block, and explains why it has been rejected.
+
+# Examples
+
+```
+extern crate gutenberg_post_parser;
+
+use gutenberg_post_parser::{root, error::{diagnose, Error, ErrorKind}};
+
+let input = &b"abc <!-- wp:foo -->def<!-- /wp:bar -->"[..];
+let (remaining, _nodes) = root(input).unwrap();
+
+assert_eq!(
+    diagnose(input, remaining),
+    Some(Error { kind: ErrorKind::MismatchedClosingBlock, offset: 22 })
+);
+```
+
+*/
+
+use super::{Input, ParseOptions};
+use super::ast::{BlockName, Span};
+use super::combinators;
+use super::parser;
+use super::scanner;
+use std::fmt;
+
+/// Represent the reason why a block has been rejected.
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(not(feature = "no_std"), derive(Debug))]
+pub enum ErrorKind {
+    /// The block name is invalid, e.g. `<!-- wp:Foo -->`.
+    InvalidBlockName,
+
+    /// The block attributes are not a JSON object, or the object is
+    /// not followed by the end of the delimiter, e.g. `<!-- wp:foo
+    /// {"bar": 42 -->`.
+    InvalidAttributes,
+
+    /// The delimiter contains unexpected data, e.g. `<!-- wp:foo bar
+    /// -->`.
+    InvalidDelimiter,
+
+    /// The delimiter is never terminated by `-->` or `/-->`.
+    UnterminatedDelimiter,
+
+    /// A balanced block is opened, but never closed.
+    UnclosedBlock,
+
+    /// A balanced block is closed by a delimiter with another block
+    /// name, e.g. `<!-- wp:foo --><!-- /wp:bar -->`.
+    MismatchedClosingBlock,
+
+    /// A closing delimiter has no opening delimiter.
+    UnexpectedClosingBlock,
+
+    /// The block has been rejected for an unknown reason.
+    Unknown
+}
+
+impl ErrorKind {
+    /// Return a short identifier of the error kind, e.g.
+    /// `unclosed-block`, that is stable and suitable for tooling.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::InvalidBlockName => "invalid-block-name",
+            ErrorKind::InvalidAttributes => "invalid-attributes",
+            ErrorKind::InvalidDelimiter => "invalid-delimiter",
+            ErrorKind::UnterminatedDelimiter => "unterminated-delimiter",
+            ErrorKind::UnclosedBlock => "unclosed-block",
+            ErrorKind::MismatchedClosingBlock => "mismatched-closing-block",
+            ErrorKind::UnexpectedClosingBlock => "unexpected-closing-block",
+            ErrorKind::Unknown => "unknown"
+        }
+    }
+
+    /// Find an error kind by its short identifier, see
+    /// [`as_str`](#method.as_str).
+    pub fn from_name(name: &str) -> Option<ErrorKind> {
+        [
+            ErrorKind::InvalidBlockName,
+            ErrorKind::InvalidAttributes,
+            ErrorKind::InvalidDelimiter,
+            ErrorKind::UnterminatedDelimiter,
+            ErrorKind::UnclosedBlock,
+            ErrorKind::MismatchedClosingBlock,
+            ErrorKind::UnexpectedClosingBlock,
+            ErrorKind::Unknown
+        ].iter().find(|kind| kind.as_str() == name).cloned()
+    }
+
+    /// Return a human-readable description of the error kind.
+    pub fn description(&self) -> &'static str {
+        match self {
+            ErrorKind::InvalidBlockName => "The block name is invalid.",
+            ErrorKind::InvalidAttributes => "The block attributes must be a JSON object, followed by the end of the delimiter.",
+            ErrorKind::InvalidDelimiter => "The block delimiter contains unexpected data.",
+            ErrorKind::UnterminatedDelimiter => "The block delimiter is never terminated by `-->` or `/-->`.",
+            ErrorKind::UnclosedBlock => "The block is opened but never closed.",
+            ErrorKind::MismatchedClosingBlock => "The closing delimiter does not match the name of the opened block.",
+            ErrorKind::UnexpectedClosingBlock => "The closing delimiter has no opening delimiter.",
+            ErrorKind::Unknown => "The block has been rejected for an unknown reason."
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(self.description())
+    }
+}
+
+/// Represent a located error, i.e. the reason why a block has been
+/// rejected, and where.
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(not(feature = "no_std"), derive(Debug))]
+pub struct Error {
+    /// The reason why the block has been rejected.
+    pub kind: ErrorKind,
+
+    /// The offset, in bytes from the beginning of the input, where
+    /// the error has been detected.
+    pub offset: usize
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{} (at byte {})", self.kind, self.offset)
+    }
+}
+
+/// Diagnose why the parser has stopped, given the `input` of the
+/// parser, and the `remaining` data it has returned. Return `None` if
+/// the input has been parsed entirely.
+pub fn diagnose<'a>(input: Input<'a>, remaining: Input<'a>) -> Option<Error> {
+    diagnose_with_options(input, remaining, &ParseOptions::default())
+}
+
+/// Diagnose why the parser has stopped, with the options that have
+/// been used to parse. See [`diagnose`](./fn.diagnose.html).
+pub fn diagnose_with_options<'a>(input: Input<'a>, remaining: Input<'a>, options: &ParseOptions<'a>) -> Option<Error> {
+    if remaining.is_empty() {
+        None
+    } else {
+        Some(diagnose_block(input, remaining, options))
+    }
+}
+
+/// Diagnose the block starting at `block`, which is a slice of `input`.
+fn diagnose_block<'a>(input: Input<'a>, block: Input<'a>, options: &ParseOptions<'a>) -> Error {
+    let error = |kind, slice: Input<'a>| {
+        Error {
+            kind,
+            offset: Span::of(slice, input).start
+        }
+    };
+
+    if !block.starts_with(parser::COMMENT_OPENING) {
+        return error(ErrorKind::Unknown, block);
+    }
+
+    let delimiter = skip_whitespaces(&block[parser::COMMENT_OPENING.len()..]);
+
+    if parser::delimiter_closing(delimiter, options).is_ok() {
+        return error(ErrorKind::UnexpectedClosingBlock, block);
+    }
+
+    let name_input = match parser::delimiter_opening(delimiter, options) {
+        Ok((name_input, _)) => name_input,
+        Err(_) => return error(ErrorKind::Unknown, block)
+    };
+
+    if scanner::find(name_input, parser::COMMENT_CLOSING).is_none() {
+        return error(ErrorKind::UnterminatedDelimiter, block);
+    }
+
+    let (after_name, name) = match parser::block_name_with_options(name_input, options) {
+        Ok(result) => result,
+        Err(_) => return error(ErrorKind::InvalidBlockName, name_input)
+    };
+
+    if !is_valid_name(&name, after_name) {
+        return error(ErrorKind::InvalidBlockName, name_input);
+    }
+
+    let after_whitespaces = skip_whitespaces(after_name);
+
+    let after_attributes = if after_whitespaces.starts_with(b"{") {
+        match parser::block_attributes(after_whitespaces) {
+            Ok((after_attributes, _)) => after_attributes,
+            Err(_) => return error(ErrorKind::InvalidAttributes, after_whitespaces)
+        }
+    } else {
+        after_whitespaces
+    };
+
+    let delimiter_end = skip_whitespaces(after_attributes);
+
+    if !is_delimiter_end(delimiter_end) {
+        return error(ErrorKind::InvalidDelimiter, delimiter_end);
+    }
+
+    if delimiter_end.starts_with(parser::COMMENT_AUTO_CLOSING) {
+        // A void block cannot be rejected once its delimiter is valid.
+        return error(ErrorKind::Unknown, block);
+    }
+
+    let children = &delimiter_end[parser::COMMENT_CLOSING.len()..];
+
+    let after_children = match parser::block_list_with_options(children, options) {
+        Ok((after_children, _)) => after_children,
+        Err(_) => return error(ErrorKind::Unknown, children)
+    };
+
+    if after_children.is_empty() {
+        return error(ErrorKind::UnclosedBlock, block);
+    }
+
+    let closing_delimiter = skip_whitespaces(&after_children[parser::COMMENT_OPENING.len()..]);
+
+    match parser::delimiter_closing(closing_delimiter, options) {
+        Ok((closing_name_input, _)) => {
+            match parser::block_name_with_options(closing_name_input, options) {
+                Ok((after_closing_name, closing_name)) if !is_valid_name(&closing_name, after_closing_name) => {
+                    error(ErrorKind::InvalidBlockName, closing_name_input)
+                },
+
+                Ok((_, closing_name)) if !name.is_equivalent_to(&closing_name) => {
+                    error(ErrorKind::MismatchedClosingBlock, after_children)
+                },
+
+                Ok((after_closing_name, _)) => {
+                    error(ErrorKind::InvalidDelimiter, skip_whitespaces(after_closing_name))
+                },
+
+                Err(_) => error(ErrorKind::InvalidBlockName, closing_name_input)
+            }
+        },
+
+        // A child block has been rejected.
+        Err(_) => diagnose_block(input, after_children, options)
+    }
+}
+
+/// Check that a block name is not empty, and is not directly followed
+/// by unexpected data, e.g. `wp:fooBar` where only `foo` is recognized.
+fn is_valid_name(name: &BlockName, after_name: Input) -> bool {
+    if name.name().is_empty() {
+        return false;
+    }
+
+    match after_name.first() {
+        None => true,
+        Some(&byte) => combinators::is_whitespace(byte) || byte == b'{' || is_delimiter_end(after_name)
+    }
+}
+
+fn skip_whitespaces(input: Input) -> Input {
+    match parser::whitespaces(input) {
+        Ok((remaining, _)) => remaining,
+        Err(_) => input
+    }
+}
+
+fn is_delimiter_end(input: Input) -> bool {
+    input.starts_with(parser::COMMENT_CLOSING) || input.starts_with(parser::COMMENT_AUTO_CLOSING)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::root;
+
+    macro_rules! assert_diagnostic (
+        ($input:expr, $kind:expr, $offset:expr) => (
+            let input = &$input[..];
+            let (remaining, _) = root(input).unwrap();
+
+            assert_eq!(
+                diagnose(input, remaining),
+                Some(Error { kind: $kind, offset: $offset })
+            );
+        )
+    );
+
+    #[test]
+    fn test_nothing_to_diagnose() {
+        let input = &b"abc <!-- wp:foo --><!-- wp:bar /--><!-- /wp:foo -->"[..];
+        let (remaining, _) = root(input).unwrap();
+
+        assert_eq!(diagnose(input, remaining), None);
+    }
+
+    #[test]
+    fn test_invalid_block_name() {
+        assert_diagnostic!(b"abc <!-- wp:Foo /-->", ErrorKind::InvalidBlockName, 12);
+        assert_diagnostic!(b"abc <!-- wp:fooBar /-->", ErrorKind::InvalidBlockName, 12);
+        assert_diagnostic!(b"<!-- wp:foo --><!-- /wp:Foo -->", ErrorKind::InvalidBlockName, 24);
+    }
+
+    #[test]
+    fn test_invalid_attributes() {
+        assert_diagnostic!(b"<!-- wp:foo {\"a\": 1 /-->", ErrorKind::InvalidAttributes, 12);
+    }
+
+    #[test]
+    fn test_invalid_delimiter() {
+        assert_diagnostic!(b"<!-- wp:foo bar /-->", ErrorKind::InvalidDelimiter, 12);
+        assert_diagnostic!(b"<!-- wp:foo --><!-- /wp:foo bar -->", ErrorKind::InvalidDelimiter, 28);
+    }
+
+    #[test]
+    fn test_unterminated_delimiter() {
+        assert_diagnostic!(b"abc <!-- wp:foo", ErrorKind::UnterminatedDelimiter, 4);
+        assert_diagnostic!(b"abc <!-- wp:foo {\"a\": 1}", ErrorKind::UnterminatedDelimiter, 4);
+    }
+
+    #[test]
+    fn test_unclosed_block() {
+        assert_diagnostic!(b"abc <!-- wp:foo -->def", ErrorKind::UnclosedBlock, 4);
+    }
+
+    #[test]
+    fn test_mismatched_closing_block() {
+        assert_diagnostic!(b"<!-- wp:foo -->def<!-- /wp:bar -->", ErrorKind::MismatchedClosingBlock, 18);
+        assert_diagnostic!(b"<!-- wp:foo -->def<!-- /wp:ns/foo -->", ErrorKind::MismatchedClosingBlock, 18);
+    }
+
+    #[test]
+    fn test_unexpected_closing_block() {
+        assert_diagnostic!(b"abc <!-- /wp:foo -->", ErrorKind::UnexpectedClosingBlock, 4);
+    }
+
+    #[test]
+    fn test_rejected_child_block() {
+        assert_diagnostic!(b"<!-- wp:foo --><!-- wp:bar -->x<!-- /wp:foo -->", ErrorKind::MismatchedClosingBlock, 31);
+        assert_diagnostic!(b"<!-- wp:foo -->x<!-- wp:Bar /--><!-- /wp:foo -->", ErrorKind::InvalidBlockName, 24);
+    }
+
+    #[test]
+    fn test_diagnose_with_options() {
+        let options = ParseOptions {
+            delimiter_prefix: &b"acme"[..],
+            .. ParseOptions::default()
+        };
+        let input = &b"<!-- acme:foo -->"[..];
+        let (remaining, _) = super::super::parse_with_options(input, &options).unwrap();
+
+        assert_eq!(
+            diagnose_with_options(input, remaining, &options),
+            Some(Error { kind: ErrorKind::UnclosedBlock, offset: 0 })
+        );
+    }
+}
@@ -0,0 +1,242 @@
+/*!
+
+Format a post into its canonical form.
+
+The canonical form of a post is:
+
+  * Delimiters have a single whitespace between their parts, e.g.
+    `<!-- wp:name {attributes} -->`, `<!-- wp:name /-->` and
+    `<!-- /wp:name -->`,
+  * The implicit `core` namespace is dropped, e.g. `wp:core/paragraph`
+    becomes `wp:paragraph`,
+  * Attributes are serialized like Gutenberg does, see
+    [`json::Value::serialize`](../json/enum.Value.html#method.serialize),
+    and empty attributes are dropped,
+  * Optionally, a block without children is void, e.g. `<!-- wp:foo
+    --><!-- /wp:foo -->` becomes `<!-- wp:foo /-->`, see
+    `FormatOptions::void_empty_blocks`,
+  * Optionally, the delimiters of nested blocks starting a line are
+    indented.
+
+Phrases are kept as is, except the indentation preceding a delimiter.
+
+# Examples
+
+```
+extern crate gutenberg_post_parser;
+
+use gutenberg_post_parser::formatter::{format, FormatOptions};
+
+let input = &b"<!--wp:core/foo   {\"b\": 1, \"a\": \"<\"}-->bar<!--  /wp:foo\t-->"[..];
+
+assert_eq!(
+    format(input, &FormatOptions::default()).unwrap(),
+    &b"<!-- wp:foo {\"a\":\"\\u003c\",\"b\":1} -->bar<!-- /wp:foo -->"[..]
+);
+```
+
+*/
+
+use super::{Input, root};
+use super::ast::{BlockName, Node, Span};
+use super::error::{self, Error, ErrorKind};
+use super::json;
+use std::vec::Vec;
+
+/// The namespace that is dropped from the block names.
+const IMPLICIT_NAMESPACE: &[u8] = b"core";
+
+/// Options of the formatter.
+#[derive(Clone, Copy, PartialEq, Default)]
+#[cfg_attr(not(feature = "no_std"), derive(Debug))]
+pub struct FormatOptions {
+    /// Number of spaces per level of nesting to indent the delimiters
+    /// starting a line with. If `None`, the indentation is kept as is.
+    pub indentation: Option<usize>,
+
+    /// Whether a block without children is written void. It is off by
+    /// default: Gutenberg does not handle both forms the same way, e.g.
+    /// a balanced block has an empty `innerContent` phrase when nested.
+    pub void_empty_blocks: bool
+}
+
+/// Format a post into its canonical form. An error is returned if the
+/// post is not parsed entirely, see [`error::diagnose`](../error/fn.diagnose.html),
+/// or if block attributes are not valid JSON.
+pub fn format(input: Input, options: &FormatOptions) -> Result<Vec<u8>, Error> {
+    let (remaining, nodes) = root(input).map_err(|_| Error { kind: ErrorKind::Unknown, offset: 0 })?;
+
+    if let Some(error) = error::diagnose(input, remaining) {
+        return Err(error);
+    }
+
+    let mut output = Vec::with_capacity(input.len());
+    format_nodes(input, &nodes, options, 0, &mut output)?;
+
+    Ok(output)
+}
+
+fn format_nodes(input: Input, nodes: &[Node], options: &FormatOptions, depth: usize, output: &mut Vec<u8>) -> Result<(), Error> {
+    for node in nodes {
+        match node {
+            Node::Phrase(phrase) => output.extend_from_slice(phrase),
+
+            Node::Block { name, attributes, children } => {
+                indent(options, depth, output);
+                output.extend_from_slice(b"<!-- wp:");
+                format_name(name, output);
+
+                if let Some(attributes) = attributes {
+                    let attributes_offset = Span::of(attributes, input).start;
+                    let value = json::parse(attributes).map_err(|json_error| {
+                        Error {
+                            kind: ErrorKind::InvalidAttributes,
+                            offset: attributes_offset + json_error.offset
+                        }
+                    })?;
+
+                    if value != json::Value::Object(vec![]) {
+                        output.push(b' ');
+                        output.extend_from_slice(value.serialize().as_bytes());
+                    }
+                }
+
+                if children.is_empty() && (options.void_empty_blocks || is_void(input, name, attributes)) {
+                    output.extend_from_slice(b" /-->");
+                } else {
+                    output.extend_from_slice(b" -->");
+                    format_nodes(input, children, options, depth + 1, output)?;
+                    indent(options, depth, output);
+                    output.extend_from_slice(b"<!-- /wp:");
+                    format_name(name, output);
+                    output.extend_from_slice(b" -->");
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether the block is void in the input, i.e. whether its
+/// delimiter ends with `/-->`.
+fn is_void(input: Input, name: &BlockName, attributes: &Option<Input>) -> bool {
+    let end = Span::of(attributes.unwrap_or_else(|| name.name()), input).end;
+
+    input[end..].iter().find(|byte| !byte.is_ascii_whitespace()) == Some(&b'/')
+}
+
+fn format_name(name: &BlockName, output: &mut Vec<u8>) {
+    if name.namespace() != IMPLICIT_NAMESPACE {
+        output.extend_from_slice(name.namespace());
+        output.push(b'/');
+    }
+
+    output.extend_from_slice(name.name());
+}
+
+/// Replace the indentation of the current line if it contains only
+/// whitespaces, i.e. if the next delimiter starts the line.
+fn indent(options: &FormatOptions, depth: usize, output: &mut Vec<u8>) {
+    let indentation = match options.indentation {
+        Some(indentation) => indentation,
+        None => return
+    };
+
+    let line_start = match output.iter().rposition(|&byte| byte == b'\n') {
+        Some(index) => index + 1,
+        None => 0
+    };
+
+    if output[line_start..].iter().all(|&byte| byte == b' ' || byte == b'\t') {
+        output.truncate(line_start);
+        output.extend((0..indentation * depth).map(|_| b' '));
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! assert_format (
+        ($input:expr, $output:expr) => (
+            assert_format!($input, $output, FormatOptions::default());
+        );
+
+        ($input:expr, $output:expr, $options:expr) => (
+            let output = format(&$input[..], &$options).unwrap();
+
+            assert_eq!(String::from_utf8_lossy(&output), String::from_utf8_lossy(&$output[..]));
+
+            // Formatting is idempotent.
+            assert_eq!(format(&output, &$options).unwrap(), output);
+        )
+    );
+
+    #[test]
+    fn test_delimiters() {
+        assert_format!(
+            b"a<!--wp:foo\n{\"x\":1}\n-->b<!--\t/wp:foo   -->c<!--wp:bar  /-->",
+            b"a<!-- wp:foo {\"x\":1} -->b<!-- /wp:foo -->c<!-- wp:bar /-->"
+        );
+    }
+
+    #[test]
+    fn test_names() {
+        assert_format!(
+            b"<!-- wp:core/foo /--><!-- wp:ns/bar /--><!-- wp:baz /-->",
+            b"<!-- wp:foo /--><!-- wp:ns/bar /--><!-- wp:baz /-->"
+        );
+    }
+
+    #[test]
+    fn test_attributes() {
+        assert_format!(
+            b"<!-- wp:foo { \"z\": [1, 2.0], \"a\": \"--> & <\" } /--><!-- wp:bar {} /-->",
+            b"<!-- wp:foo {\"a\":\"\\u002d\\u002d\\u003e \\u0026 \\u003c\",\"z\":[1,2.0]} /--><!-- wp:bar /-->"
+        );
+    }
+
+    #[test]
+    fn test_empty_balanced_block() {
+        assert_format!(
+            b"<!--wp:foo   --><!--\t/wp:foo -->",
+            b"<!-- wp:foo --><!-- /wp:foo -->"
+        );
+    }
+
+    #[test]
+    fn test_void_empty_blocks() {
+        assert_format!(
+            b"<!-- wp:foo --><!-- /wp:foo --><!-- wp:bar {\"a\":1} --><!-- /wp:bar -->",
+            b"<!-- wp:foo /--><!-- wp:bar {\"a\":1} /-->",
+            FormatOptions { void_empty_blocks: true, ..FormatOptions::default() }
+        );
+    }
+
+    #[test]
+    fn test_indentation() {
+        assert_format!(
+            b"<!-- wp:a -->\n<!-- wp:b -->\n<div>\n<!-- wp:c /-->\n</div>\n    <!-- /wp:b -->\n<!-- /wp:a --> <!-- wp:d /-->",
+            b"<!-- wp:a -->\n  <!-- wp:b -->\n<div>\n    <!-- wp:c /-->\n</div>\n  <!-- /wp:b -->\n<!-- /wp:a --> <!-- wp:d /-->",
+            FormatOptions { indentation: Some(2), ..FormatOptions::default() }
+        );
+    }
+
+    #[test]
+    fn test_invalid_attributes() {
+        assert_eq!(
+            format(&b"<!-- wp:foo {\"a\": tru} /-->"[..], &FormatOptions::default()),
+            Err(Error { kind: ErrorKind::InvalidAttributes, offset: 21 })
+        );
+    }
+
+    #[test]
+    fn test_leftovers() {
+        assert_eq!(
+            format(&b"<!-- wp:foo -->"[..], &FormatOptions::default()),
+            Err(Error { kind: ErrorKind::UnclosedBlock, offset: 0 })
+        );
+    }
+}
@@ -0,0 +1,646 @@
+/*!
+
+A small JSON parser and serializer for block attributes.
+
+Block attributes are encoded as a JSON object. The parser does not
+analyse them: It only recognizes their boundaries, see
+[`parser::block_attributes`](../parser/fn.block_attributes.html). This
+module decodes them when needed, e.g. to format a post.
+
+Numbers are not decoded: They are kept as written in the input, so
+that they are serialized back without any loss of precision.
+
+# Examples
+
+```
+extern crate gutenberg_post_parser;
+
+use gutenberg_post_parser::json::{parse, Value};
+
+let value = parse(&b"{\"level\": 2, \"content\": \"a <b>\"}"[..]).unwrap();
+
+assert_eq!(value.get("level"), Some(&Value::Number(&b"2"[..])));
+assert_eq!(value.serialize(), "{\"content\":\"a \\u003cb\\u003e\",\"level\":2}");
+```
+
+*/
+
+use super::Input;
+use std::string::String;
+use std::vec::Vec;
+
+/// Maximum nesting of arrays and objects.
+const MAXIMUM_DEPTH: usize = 128;
+
+/// Represent a JSON value.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(not(feature = "no_std"), derive(Debug))]
+pub enum Value<'a> {
+    /// `null`.
+    Null,
+
+    /// `true` or `false`.
+    Boolean(bool),
+
+    /// A number, as written in the input.
+    Number(Input<'a>),
+
+    /// A string, with its escape sequences decoded.
+    String(String),
+
+    /// An array.
+    Array(Vec<Value<'a>>),
+
+    /// An object, with its members in the input order.
+    Object(Vec<(String, Value<'a>)>)
+}
+
+impl<'a> Value<'a> {
+    /// The value of a member if the value is an object. If the key is
+    /// duplicated, the last member wins, like with `JSON.parse`.
+    pub fn get(&self, key: &str) -> Option<&Value<'a>> {
+        match self {
+            Value::Object(members) => {
+                members
+                    .iter()
+                    .rev()
+                    .find(|(member_key, _)| member_key == key)
+                    .map(|(_, value)| value)
+            },
+
+            _ => None
+        }
+    }
+
+    /// The string if the value is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(string) => Some(string),
+            _ => None
+        }
+    }
+
+    /// The number, as written in the input, if the value is a number.
+    pub fn as_number(&self) -> Option<Input<'a>> {
+        match self {
+            Value::Number(number) => Some(number),
+            _ => None
+        }
+    }
+
+    /// Serialize the value like Gutenberg serializes block attributes,
+    /// see [`Style::Attributes`](./enum.Style.html#variant.Attributes).
+    pub fn serialize(&self) -> String {
+        let mut output = String::new();
+        serialize_value(self, Style::Attributes, &mut output);
+
+        output
+    }
+}
+
+/// How values are serialized, see
+/// [`serialize_value`](./fn.serialize_value.html).
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(not(feature = "no_std"), derive(Debug))]
+pub enum Style {
+    /// Like Gutenberg serializes block attributes: With `--`, `<`,
+    /// `>`, `&` and `"` escaped in strings as `\u002d\u002d`,
+    /// `\u003c`, `\u003e`, `\u0026` and `\u0022`, so that the result
+    /// can be safely written in an HTML comment, and with object keys
+    /// sorted, so that the result is stable.
+    Attributes,
+
+    /// Like `JSON.stringify`: Only `"`, `\` and control characters are
+    /// escaped, and object members are in the input order.
+    Plain
+}
+
+/// Serialize a value, without whitespaces, at the end of `output`. A
+/// duplicated key has its last value, at the position of its first
+/// occurrence, like with `JSON.parse`.
+pub fn serialize_value(value: &Value, style: Style, output: &mut String) {
+    match value {
+        Value::Null => output.push_str("null"),
+        Value::Boolean(true) => output.push_str("true"),
+        Value::Boolean(false) => output.push_str("false"),
+
+        // A number only contains ASCII characters.
+        Value::Number(number) => output.extend(number.iter().map(|&byte| byte as char)),
+
+        Value::String(string) => serialize_string(string, style, output),
+
+        Value::Array(items) => {
+            output.push('[');
+
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    output.push(',');
+                }
+
+                serialize_value(item, style, output);
+            }
+
+            output.push(']');
+        },
+
+        Value::Object(members) => {
+            let mut unique_members: Vec<(&String, &Value)> = Vec::with_capacity(members.len());
+
+            // The last duplicated member wins.
+            for (key, value) in members {
+                match unique_members.iter_mut().find(|(unique_key, _)| *unique_key == key) {
+                    Some(unique_member) => unique_member.1 = value,
+                    None => unique_members.push((key, value))
+                }
+            }
+
+            if style == Style::Attributes {
+                unique_members.sort_by(|left, right| left.0.cmp(right.0));
+            }
+
+            output.push('{');
+
+            for (index, (key, value)) in unique_members.into_iter().enumerate() {
+                if index > 0 {
+                    output.push(',');
+                }
+
+                serialize_string(key, style, output);
+                output.push(':');
+                serialize_value(value, style, output);
+            }
+
+            output.push('}');
+        }
+    }
+}
+
+/// Serialize a string, with its quotes, at the end of `output`.
+pub fn serialize_string(string: &str, style: Style, output: &mut String) {
+    const HEXADECIMAL_DIGITS: &[u8] = b"0123456789abcdef";
+
+    let is_attributes = style == Style::Attributes;
+
+    output.push('"');
+
+    let mut characters = string.chars().peekable();
+
+    while let Some(character) = characters.next() {
+        match character {
+            '"' if is_attributes => output.push_str("\\u0022"),
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '<' if is_attributes => output.push_str("\\u003c"),
+            '>' if is_attributes => output.push_str("\\u003e"),
+            '&' if is_attributes => output.push_str("\\u0026"),
+
+            '-' if is_attributes && characters.peek() == Some(&'-') => {
+                characters.next();
+                output.push_str("\\u002d\\u002d");
+            },
+
+            '\u{8}' => output.push_str("\\b"),
+            '\u{c}' => output.push_str("\\f"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+
+            character if (character as u32) < 0x20 => {
+                output.push_str("\\u00");
+                output.push(HEXADECIMAL_DIGITS[(character as usize) >> 4] as char);
+                output.push(HEXADECIMAL_DIGITS[(character as usize) & 0xf] as char);
+            },
+
+            character => output.push(character)
+        }
+    }
+
+    output.push('"');
+}
+
+/// Represent the reason why a JSON value is invalid.
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(not(feature = "no_std"), derive(Debug))]
+pub enum ErrorKind {
+    /// The input ends before the value.
+    UnexpectedEnd,
+
+    /// A byte is not expected here.
+    UnexpectedByte,
+
+    /// A number is malformed.
+    InvalidNumber,
+
+    /// An escape sequence in a string is malformed.
+    InvalidEscape,
+
+    /// A string is not valid UTF-8.
+    InvalidUtf8,
+
+    /// Arrays and objects are nested too deeply.
+    TooDeep,
+
+    /// Data remain after the value.
+    TrailingData
+}
+
+impl ErrorKind {
+    /// Return a human-readable description of the error kind.
+    pub fn description(&self) -> &'static str {
+        match self {
+            ErrorKind::UnexpectedEnd => "Unexpected end of JSON.",
+            ErrorKind::UnexpectedByte => "Unexpected character in JSON.",
+            ErrorKind::InvalidNumber => "Invalid number in JSON.",
+            ErrorKind::InvalidEscape => "Invalid escape sequence in a JSON string.",
+            ErrorKind::InvalidUtf8 => "Invalid UTF-8 sequence in a JSON string.",
+            ErrorKind::TooDeep => "JSON arrays and objects are nested too deeply.",
+            ErrorKind::TrailingData => "Unexpected data after the JSON value."
+        }
+    }
+}
+
+/// Represent a located JSON error.
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(not(feature = "no_std"), derive(Debug))]
+pub struct Error {
+    /// The reason why the value is invalid.
+    pub kind: ErrorKind,
+
+    /// The offset, in bytes from the beginning of the JSON input,
+    /// where the error has been detected.
+    pub offset: usize
+}
+
+/// Parse a JSON value, e.g. block attributes.
+pub fn parse(input: Input) -> Result<Value, Error> {
+    let mut parser = Parser { input, offset: 0 };
+
+    parser.skip_whitespaces();
+    let value = parser.value(0)?;
+    parser.skip_whitespaces();
+
+    if parser.offset < input.len() {
+        return Err(parser.error(ErrorKind::TrailingData));
+    }
+
+    Ok(value)
+}
+
+struct Parser<'a> {
+    input: Input<'a>,
+    offset: usize
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.offset).cloned()
+    }
+
+    fn error(&self, kind: ErrorKind) -> Error {
+        Error {
+            kind: if self.offset >= self.input.len() { ErrorKind::UnexpectedEnd } else { kind },
+            offset: self.offset
+        }
+    }
+
+    fn skip_whitespaces(&mut self) {
+        while let Some(byte) = self.peek() {
+            match byte {
+                b' ' | b'\n' | b'\r' | b'\t' => self.offset += 1,
+                _ => break
+            }
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), Error> {
+        if self.peek() == Some(byte) {
+            self.offset += 1;
+
+            Ok(())
+        } else {
+            Err(self.error(ErrorKind::UnexpectedByte))
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value<'a>, Error> {
+        match self.peek() {
+            Some(b'n') => self.literal(b"null", Value::Null),
+            Some(b't') => self.literal(b"true", Value::Boolean(true)),
+            Some(b'f') => self.literal(b"false", Value::Boolean(false)),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b'[') => self.array(depth),
+            Some(b'{') => self.object(depth),
+            Some(b'-') | Some(b'0'..=b'9') => self.number(),
+            _ => Err(self.error(ErrorKind::UnexpectedByte))
+        }
+    }
+
+    fn literal(&mut self, literal: &[u8], value: Value<'a>) -> Result<Value<'a>, Error> {
+        for &byte in literal {
+            self.expect(byte)?;
+        }
+
+        Ok(value)
+    }
+
+    fn digits(&mut self) -> usize {
+        let start = self.offset;
+
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.offset += 1;
+        }
+
+        self.offset - start
+    }
+
+    fn number(&mut self) -> Result<Value<'a>, Error> {
+        let start = self.offset;
+
+        if self.peek() == Some(b'-') {
+            self.offset += 1;
+        }
+
+        match self.peek() {
+            Some(b'0') => self.offset += 1,
+            Some(b'1'..=b'9') => { self.digits(); },
+            _ => return Err(self.error(ErrorKind::InvalidNumber))
+        }
+
+        if self.peek() == Some(b'.') {
+            self.offset += 1;
+
+            if self.digits() == 0 {
+                return Err(self.error(ErrorKind::InvalidNumber));
+            }
+        }
+
+        if let Some(b'e') | Some(b'E') = self.peek() {
+            self.offset += 1;
+
+            if let Some(b'+') | Some(b'-') = self.peek() {
+                self.offset += 1;
+            }
+
+            if self.digits() == 0 {
+                return Err(self.error(ErrorKind::InvalidNumber));
+            }
+        }
+
+        Ok(Value::Number(&self.input[start..self.offset]))
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.expect(b'"')?;
+
+        let mut string = String::new();
+
+        loop {
+            let start = self.offset;
+
+            while let Some(byte) = self.peek() {
+                if byte == b'"' || byte == b'\\' || byte < 0x20 {
+                    break;
+                }
+
+                self.offset += 1;
+            }
+
+            match ::std::str::from_utf8(&self.input[start..self.offset]) {
+                Ok(chunk) => string.push_str(chunk),
+                Err(error) => {
+                    return Err(
+                        Error {
+                            kind: ErrorKind::InvalidUtf8,
+                            offset: start + error.valid_up_to()
+                        }
+                    );
+                }
+            }
+
+            match self.peek() {
+                Some(b'"') => {
+                    self.offset += 1;
+
+                    return Ok(string);
+                },
+
+                Some(b'\\') => {
+                    self.offset += 1;
+                    self.escape(&mut string)?;
+                },
+
+                _ => return Err(self.error(ErrorKind::UnexpectedByte))
+            }
+        }
+    }
+
+    fn escape(&mut self, string: &mut String) -> Result<(), Error> {
+        let character = match self.peek() {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\u{8}',
+            Some(b'f') => '\u{c}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+
+            Some(b'u') => {
+                self.offset += 1;
+
+                let mut units = vec![self.hexadecimal_unit()?];
+
+                // A high surrogate followed by a low surrogate.
+                if (0xd800..0xdc00).contains(&units[0]) && self.input[self.offset..].starts_with(b"\\u") {
+                    let offset = self.offset;
+                    self.offset += 2;
+
+                    let unit = self.hexadecimal_unit()?;
+
+                    if (0xdc00..0xe000).contains(&unit) {
+                        units.push(unit);
+                    } else {
+                        self.offset = offset;
+                    }
+                }
+
+                // Lone surrogates are replaced by `U+FFFD`.
+                string.push_str(&String::from_utf16_lossy(&units));
+
+                return Ok(());
+            },
+
+            _ => return Err(self.error(ErrorKind::InvalidEscape))
+        };
+
+        self.offset += 1;
+        string.push(character);
+
+        Ok(())
+    }
+
+    fn hexadecimal_unit(&mut self) -> Result<u16, Error> {
+        let mut unit = 0u16;
+
+        for _ in 0..4 {
+            let digit = match self.peek() {
+                Some(byte @ b'0'..=b'9') => byte - b'0',
+                Some(byte @ b'a'..=b'f') => byte - b'a' + 10,
+                Some(byte @ b'A'..=b'F') => byte - b'A' + 10,
+                _ => return Err(self.error(ErrorKind::InvalidEscape))
+            };
+
+            unit = unit * 16 + u16::from(digit);
+            self.offset += 1;
+        }
+
+        Ok(unit)
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Value<'a>, Error> {
+        if depth >= MAXIMUM_DEPTH {
+            return Err(self.error(ErrorKind::TooDeep));
+        }
+
+        self.expect(b'[')?;
+        self.skip_whitespaces();
+
+        let mut items = vec![];
+
+        if self.peek() == Some(b']') {
+            self.offset += 1;
+
+            return Ok(Value::Array(items));
+        }
+
+        loop {
+            self.skip_whitespaces();
+            items.push(self.value(depth + 1)?);
+            self.skip_whitespaces();
+
+            match self.peek() {
+                Some(b',') => self.offset += 1,
+
+                Some(b']') => {
+                    self.offset += 1;
+
+                    return Ok(Value::Array(items));
+                },
+
+                _ => return Err(self.error(ErrorKind::UnexpectedByte))
+            }
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Value<'a>, Error> {
+        if depth >= MAXIMUM_DEPTH {
+            return Err(self.error(ErrorKind::TooDeep));
+        }
+
+        self.expect(b'{')?;
+        self.skip_whitespaces();
+
+        let mut members = vec![];
+
+        if self.peek() == Some(b'}') {
+            self.offset += 1;
+
+            return Ok(Value::Object(members));
+        }
+
+        loop {
+            self.skip_whitespaces();
+            let key = self.string()?;
+            self.skip_whitespaces();
+            self.expect(b':')?;
+            self.skip_whitespaces();
+            let value = self.value(depth + 1)?;
+            members.push((key, value));
+            self.skip_whitespaces();
+
+            match self.peek() {
+                Some(b',') => self.offset += 1,
+
+                Some(b'}') => {
+                    self.offset += 1;
+
+                    return Ok(Value::Object(members));
+                },
+
+                _ => return Err(self.error(ErrorKind::UnexpectedByte))
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scalars() {
+        assert_eq!(parse(&b"null"[..]), Ok(Value::Null));
+        assert_eq!(parse(&b" true "[..]), Ok(Value::Boolean(true)));
+        assert_eq!(parse(&b"false"[..]), Ok(Value::Boolean(false)));
+        assert_eq!(parse(&b"-12.5e+3"[..]), Ok(Value::Number(&b"-12.5e+3"[..])));
+        assert_eq!(parse(&b"\"a\\\"b\\u00e9\\ud83d\\ude00\""[..]), Ok(Value::String("a\"bé😀".to_string())));
+    }
+
+    #[test]
+    fn test_parse_lone_surrogate() {
+        assert_eq!(parse(&b"\"\\ud83dx\""[..]), Ok(Value::String("\u{fffd}x".to_string())));
+    }
+
+    #[test]
+    fn test_parse_object() {
+        let value = parse(&b"{\"a\": [1, {\"b\": null}], \"a\": true}"[..]).unwrap();
+
+        assert_eq!(value.get("a"), Some(&Value::Boolean(true)));
+        assert_eq!(value.get("c"), None);
+        assert_eq!(
+            value,
+            Value::Object(vec![
+                ("a".to_string(), Value::Array(vec![Value::Number(&b"1"[..]), Value::Object(vec![("b".to_string(), Value::Null)])])),
+                ("a".to_string(), Value::Boolean(true))
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse(&b"{\"a\": 1"[..]), Err(Error { kind: ErrorKind::UnexpectedEnd, offset: 7 }));
+        assert_eq!(parse(&b"{\"a\" 1}"[..]), Err(Error { kind: ErrorKind::UnexpectedByte, offset: 5 }));
+        assert_eq!(parse(&b"[01]"[..]), Err(Error { kind: ErrorKind::UnexpectedByte, offset: 2 }));
+        assert_eq!(parse(&b"[1.]"[..]), Err(Error { kind: ErrorKind::InvalidNumber, offset: 3 }));
+        assert_eq!(parse(&b"\"\\x\""[..]), Err(Error { kind: ErrorKind::InvalidEscape, offset: 2 }));
+        assert_eq!(parse(&b"\"\xff\""[..]), Err(Error { kind: ErrorKind::InvalidUtf8, offset: 1 }));
+        assert_eq!(parse(&b"{} {}"[..]), Err(Error { kind: ErrorKind::TrailingData, offset: 3 }));
+        assert_eq!(parse(&[b'['; 200][..]).unwrap_err().kind, ErrorKind::TooDeep);
+    }
+
+    #[test]
+    fn test_serialize() {
+        let value = parse(&b"{ \"z\": 1.50, \"a\": [\"<!-- x -->\", \"a & b\", \"\\\"\\\\\\n\\u0001\"], \"z\": -0 }"[..]).unwrap();
+
+        assert_eq!(
+            value.serialize(),
+            "{\"a\":[\"\\u003c!\\u002d\\u002d x \\u002d\\u002d\\u003e\",\"a \\u0026 b\",\"\\u0022\\\\\\n\\u0001\"],\"z\":-0}"
+        );
+    }
+
+    #[test]
+    fn test_serialize_dashes() {
+        assert_eq!(Value::String("---".to_string()).serialize(), "\"\\u002d\\u002d-\"");
+    }
+
+    #[test]
+    fn test_serialize_plain() {
+        let value = parse(&b"{ \"z\": 1.50, \"a\": [\"<!-- x -->\", \"\\\"\\\\\\n\\u0001\"], \"z\": -0 }"[..]).unwrap();
+        let mut output = String::new();
+
+        serialize_value(&value, Style::Plain, &mut output);
+
+        assert_eq!(output, "{\"z\":-0,\"a\":[\"<!-- x -->\",\"\\\"\\\\\\n\\u0001\"]}");
+    }
+}
@@ -54,6 +54,86 @@ $ just build-binary
 $ ./target/release/gutenberg-post-parser --emit-json tests/fixtures/gutenberg-demo.html
 ```
 
+The JSON output is the canonical output model of the parser, defined
+in the `output` module: The blocks returned by Gutenberg `parse`. The
+bindings expose the same model, and are all checked against the JSON
+fixtures in `tests/fixtures`. Like with WordPress, the model is defined
+for any input: The data that the parser does not recognize, e.g. an
+unclosed block, are recovered like `WP_Block_Parser` does, see
+`output::recover`.
+
+The AST can also be compiled into CBOR (`--emit-cbor`), MessagePack
+(`--emit-msgpack`) or YAML (`--emit-yaml`), with the same structure as
+the JSON output.
+
+To quickly inspect a post, `--emit-tree` prints an indented outline of
+the AST, optionally with the spans of the nodes (`--spans`) and up to
+a given depth (`--max-depth`).
+
+The binary also parses several files or directories at once (directories
+are walked recursively), or a stream of posts where each line is a JSON
+object with a `post_content` field, such as an export of the `wp_posts`
+table. One result is written per line, and items are parsed across
+several threads:
+
+```sh
+$ ./target/release/gutenberg-post-parser --threads 8 tests/fixtures/
+$ ./target/release/gutenberg-post-parser --ndjson posts.ndjson > parsed.ndjson
+```
+
+When some data are not recognized by the parser, a located diagnostic
+explains why on `stderr` (use `--diagnostics-format=json` for tooling),
+and the binary exits with `2`. It exits with `1` when an input cannot
+be read or parsed. The `error::diagnose` function provides the same
+diagnostics to the library users.
+
+The `fmt` subcommand rewrites posts into a canonical form: normalized
+delimiters, attributes serialized like Gutenberg does with sorted keys,
+no implicit `core/` namespace, optionally indented nested blocks
+(`--indent`), and optionally void blocks without children
+(`--void-empty-blocks`). Files are formatted in place, and `stdin` on
+`stdout`.
+With `--check`, nothing is written, and the binary exits with `3` if
+some posts are not formatted, e.g. in a continuous integration. The
+`formatter::format` function provides the same to the library users.
+
+```sh
+$ ./target/release/gutenberg-post-parser fmt --check posts/
+```
+
+The `lint` subcommand reports likely mistakes in the blocks, such as
+empty paragraphs, headings skipping levels, images without an
+alternative text, deprecated block names, unknown namespaces,
+duplicate anchors, oversized attributes, or blocks nested too deeply.
+Rules are configured by a TOML file (`--config`, or
+`.gutenberg-lint.toml` if it exists), and findings are written in a
+human format, or in [SARIF] for code scanning tools (`--format
+sarif`). The binary exits with `4` if some findings are errors. The
+`lint::lint` function provides the same to the library users.
+
+```toml
+namespaces = ["core", "core-embed", "acme"]
+maximum-depth = 8
+
+[levels]
+unknown-namespace = "error"
+empty-paragraph = "off"
+
+[deprecated-names]
+"acme/old-gallery" = "acme/gallery"
+```
+
+The `watch` subcommand parses files again each time they change, and
+writes their AST and diagnostics, e.g. to keep an outline up to date
+while editing a post. Files are polled (`--interval`, in
+milliseconds), so it works without any service. The modification time
+and the length of a file tell it has changed, or the hash of its
+content if the modification time is too recent to be trusted:
+
+```sh
+$ ./target/release/gutenberg-post-parser watch --emit-tree posts/
+```
+
 #### Static library
 
 To compile the parser to a static library, run:
@@ -119,6 +199,44 @@ $ just build-c
 $ ./bindings/c/bin/gutenberg-post-parser tests/fixtures/gutenberg-demo.html
 ```
 
+`gutenberg_parse(pointer, length)` returns an opaque
+`GutenbergDocument*`, which owns a copy of the input and the AST.
+Nodes are read with the `gutenberg_document_node*` and
+`gutenberg_node_*` accessors, and the document is released with
+`gutenberg_document_free`. Nothing returned by the binding must be
+released with `free()`. The input may contain NUL bytes. When it is
+not parsed entirely, `gutenberg_document_error` returns the kind of
+error, its byte offset and a message.
+
+To avoid building the tree of the whole document, e.g. in a server
+module,
+`gutenberg_parse_with_callbacks(pointer, length, options, callbacks,
+user_data, error)` streams the nodes to the `on_block_start`,
+`on_phrase` and `on_block_end` function pointers of a
+`GutenbergCallbacks` structure, with `user_data`. The events are
+emitted as each delimiter or phrase is recognized, and only the names
+of the open blocks are kept, so no tree is built. `options`, if not
+`NULL`, is a `GutenbergParseOptions` structure to adjust the grammar,
+like `ParseOptions`. The slices point into the caller's buffer, and
+`error`, if not `NULL`, is filled when the input is not parsed
+entirely.
+
+To install the shared and static libraries, the header and a
+`gutenberg_post_parser.pc` file for `pkg-config`, generated for the
+given prefix and with the native libraries that the static library
+needs, run:
+
+```sh
+$ just install-c /usr/local
+$ cc -o program program.c $(pkg-config --cflags --libs gutenberg_post_parser)
+```
+
+The SONAME of the shared library carries the major version of the
+ABI, e.g. `libgutenberg_post_parser_c.so.2`. `gutenberg_abi_version()`
+returns the version of the loaded library as `(major << 16) | minor`,
+to be compared with the `GUTENBERG_ABI_VERSION_MAJOR` and
+`GUTENBERG_ABI_VERSION_MINOR` macros of the header.
+
 #### PHP
 
 To compile the parser to a [PHP extension][PHP], run:
@@ -223,6 +341,7 @@ The license is a classic `BSD-3-Clause`:
 [Justfile]: https://github.com/casey/just/
 [WebAssembly]: http://webassembly.org/
 [ASM.js]: http://asmjs.org/spec/latest/
+[SARIF]: https://sarifweb.azurewebsites.net/
 [NodeJS]: https://nodejs.org/
 [C]: https://en.wikipedia.org/wiki/C_(programming_language)
 [PHP]: https://php.net/
@@ -243,13 +362,23 @@ The license is a classic `BSD-3-Clause`:
 
 #[cfg(feature = "no_std")] #[macro_use] extern crate alloc as std;
 #[macro_use] extern crate nom;
+#[cfg(not(feature = "no_std"))] extern crate memchr;
+#[cfg(feature = "parallel")] extern crate rayon;
 
 use std::vec::Vec;
 
 // Export modules.
 pub mod ast;
 #[macro_use] pub mod combinators;
+pub mod error;
+pub mod formatter;
+pub mod json;
+pub mod lint;
+pub mod output;
 pub mod parser;
+#[cfg(feature = "parallel")] pub mod parallel;
+pub mod scanner;
+pub mod wire;
 
 
 /// Represent the type of a parser input element. See
@@ -284,7 +413,7 @@ pub type Input<'a> = &'a [InputElement];
 /// ```
 /// extern crate gutenberg_post_parser;
 ///
-/// use gutenberg_post_parser::{root, ast::Node};
+/// use gutenberg_post_parser::{root, ast::{BlockName, Node}};
 ///
 /// let input = &b"<!-- wp:foo {\"bar\": true} /-->"[..];
 /// let output = Ok(
@@ -295,7 +424,7 @@ pub type Input<'a> = &'a [InputElement];
 ///         // The Abstract Syntax Tree.
 ///         vec![
 ///             Node::Block {
-///                 name: (&b"core"[..], &b"foo"[..]),
+///                 name: BlockName::implicit(&b"core"[..], &b"foo"[..]),
 ///                 attributes: Some(&b"{\"bar\": true}"[..]),
 ///                 children: vec![]
 ///             }
@@ -308,3 +437,76 @@ pub type Input<'a> = &'a [InputElement];
 pub fn root(input: Input) -> Result<(Input, Vec<ast::Node>), nom::Err<Input>> {
     parser::block_list(input)
 }
+
+/// Options to adjust the grammar, so that forks of the block format can
+/// be parsed with the same engine.
+///
+/// The default options describe the Gutenberg post format, i.e. the
+/// default namespace is `core`, block names are strict, and the
+/// delimiter prefix is `wp`.
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(not(feature = "no_std"), derive(Debug))]
+pub struct ParseOptions<'a> {
+    /// The namespace of a block name that has no explicit namespace,
+    /// e.g. `core` in `<!-- wp:paragraph -->`.
+    pub default_namespace: Input<'a>,
+
+    /// By default, a block name part is restricted to
+    /// `[a-z][a-z0-9_-]*`. When lenient, uppercase letters are
+    /// accepted, and a part can start with a digit, as seen in the
+    /// wild.
+    pub lenient_names: bool,
+
+    /// The prefix of the block delimiters, e.g. `wp` in `<!-- wp:foo
+    /// -->` and `<!-- /wp:foo -->`.
+    pub delimiter_prefix: Input<'a>
+}
+
+impl<'a> Default for ParseOptions<'a> {
+    fn default() -> Self {
+        ParseOptions {
+            default_namespace: &b"core"[..],
+            lenient_names: false,
+            delimiter_prefix: &b"wp"[..]
+        }
+    }
+}
+
+/// The `parse_with_options` function is similar to
+/// [`root`](./fn.root.html), but the grammar is adjusted with the given
+/// options.
+///
+/// # Examples
+///
+/// ```
+/// extern crate gutenberg_post_parser;
+///
+/// use gutenberg_post_parser::{parse_with_options, ast::{BlockName, Node}, ParseOptions};
+///
+/// let input = &b"<!-- acme:Foo {\"bar\": true} /-->"[..];
+/// let options = ParseOptions {
+///     default_namespace: &b"acme"[..],
+///     lenient_names: true,
+///     delimiter_prefix: &b"acme"[..]
+/// };
+/// let output = Ok(
+///     (
+///         // The remaining data.
+///         &b""[..],
+///
+///         // The Abstract Syntax Tree.
+///         vec![
+///             Node::Block {
+///                 name: BlockName::implicit(&b"acme"[..], &b"Foo"[..]),
+///                 attributes: Some(&b"{\"bar\": true}"[..]),
+///                 children: vec![]
+///             }
+///         ]
+///     )
+/// );
+///
+/// assert_eq!(parse_with_options(input, &options), output);
+/// ```
+pub fn parse_with_options<'a>(input: Input<'a>, options: &ParseOptions<'a>) -> Result<(Input<'a>, Vec<ast::Node<'a>>), nom::Err<Input<'a>>> {
+    parser::block_list_with_options(input, options)
+}
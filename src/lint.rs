@@ -0,0 +1,679 @@
+/*!
+
+Lint the blocks of a post.
+
+The linter walks the AST, and reports the blocks that are likely to be
+mistakes, e.g. an empty paragraph, or an image without an alternative
+text. Each [`Rule`](./enum.Rule.html) has a [`Level`](./enum.Level.html)
+and some parameters set by a [`Config`](./struct.Config.html), and
+each [`Finding`](./struct.Finding.html) carries the span of the block
+within the input.
+
+Block attributes that are not valid JSON are ignored by the rules
+depending on them.
+
+# Examples
+
+```
+extern crate gutenberg_post_parser;
+
+use gutenberg_post_parser::{root, ast::Span, lint::{lint, Config, Level, Rule}};
+
+let input = &b"<!-- wp:paragraph --><p> </p><!-- /wp:paragraph -->"[..];
+let (_remaining, nodes) = root(input).unwrap();
+let findings = lint(input, &nodes, &Config::default());
+
+assert_eq!(findings.len(), 1);
+assert_eq!(findings[0].rule, Rule::EmptyParagraph);
+assert_eq!(findings[0].level, Level::Warning);
+assert_eq!(findings[0].span, Span { start: 0, end: 51 });
+```
+
+*/
+
+use super::Input;
+use super::ast::{Node, Span};
+use super::json;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str;
+use std::string::String;
+use std::vec::Vec;
+
+/// All the rules, in the order of their declaration.
+pub const RULES: [Rule; 8] = [
+    Rule::EmptyParagraph,
+    Rule::HeadingLevel,
+    Rule::ImageAlt,
+    Rule::DeprecatedName,
+    Rule::UnknownNamespace,
+    Rule::DuplicateAnchor,
+    Rule::OversizedAttributes,
+    Rule::MaximumDepth
+];
+
+/// Level of the heading blocks without a `level` attribute.
+const DEFAULT_HEADING_LEVEL: usize = 2;
+
+/// Represent a lint rule.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(not(feature = "no_std"), derive(Debug))]
+pub enum Rule {
+    /// A paragraph has no text, e.g. `<!-- wp:paragraph --><p></p><!--
+    /// /wp:paragraph -->`.
+    EmptyParagraph,
+
+    /// A heading skips a level, e.g. a `h4` following a `h2`.
+    HeadingLevel,
+
+    /// An image has no alternative text.
+    ImageAlt,
+
+    /// A block name is deprecated, see `Config::deprecated_names`.
+    DeprecatedName,
+
+    /// A block namespace is not known, see `Config::namespaces`.
+    UnknownNamespace,
+
+    /// An anchor is used by more than one block.
+    DuplicateAnchor,
+
+    /// Block attributes are too long, see
+    /// `Config::maximum_attributes_length`.
+    OversizedAttributes,
+
+    /// A block is nested too deeply, see `Config::maximum_depth`.
+    MaximumDepth
+}
+
+impl Rule {
+    /// A short identifier of the rule, e.g. `empty-paragraph`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Rule::EmptyParagraph => "empty-paragraph",
+            Rule::HeadingLevel => "heading-level",
+            Rule::ImageAlt => "image-alt",
+            Rule::DeprecatedName => "deprecated-name",
+            Rule::UnknownNamespace => "unknown-namespace",
+            Rule::DuplicateAnchor => "duplicate-anchor",
+            Rule::OversizedAttributes => "oversized-attributes",
+            Rule::MaximumDepth => "maximum-depth"
+        }
+    }
+
+    /// Find a rule by its short identifier.
+    pub fn from_name(name: &str) -> Option<Rule> {
+        RULES.iter().find(|rule| rule.as_str() == name).cloned()
+    }
+
+    /// A human readable description of the rule.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Rule::EmptyParagraph => "Paragraphs must not be empty.",
+            Rule::HeadingLevel => "Headings must not skip levels.",
+            Rule::ImageAlt => "Images must have an alternative text.",
+            Rule::DeprecatedName => "Deprecated blocks must be replaced.",
+            Rule::UnknownNamespace => "Block namespaces must be known.",
+            Rule::DuplicateAnchor => "Anchors must be unique.",
+            Rule::OversizedAttributes => "Block attributes must not be too long.",
+            Rule::MaximumDepth => "Blocks must not be nested too deeply."
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(self.as_str())
+    }
+}
+
+/// Represent the level of a rule, and of its findings.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(not(feature = "no_std"), derive(Debug))]
+pub enum Level {
+    /// The rule is disabled.
+    Off,
+
+    /// The findings are reported, but are acceptable.
+    Warning,
+
+    /// The findings must be fixed.
+    Error
+}
+
+impl Level {
+    /// A short identifier of the level, e.g. `warning`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Level::Off => "off",
+            Level::Warning => "warning",
+            Level::Error => "error"
+        }
+    }
+
+    /// Find a level by its short identifier.
+    pub fn from_name(name: &str) -> Option<Level> {
+        [Level::Off, Level::Warning, Level::Error].iter().find(|level| level.as_str() == name).cloned()
+    }
+}
+
+/// Configuration of the linter.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(not(feature = "no_std"), derive(Debug))]
+pub struct Config {
+    /// The level of each rule, indexed as `RULES`.
+    levels: [Level; 8],
+
+    /// Fully-qualified deprecated block names, with their replacement
+    /// if any.
+    pub deprecated_names: Vec<(String, Option<String>)>,
+
+    /// Known block namespaces.
+    pub namespaces: Vec<String>,
+
+    /// Maximum length of the block attributes, in bytes.
+    pub maximum_attributes_length: usize,
+
+    /// Maximum depth of the blocks, where top-level blocks have a
+    /// depth of 1.
+    pub maximum_depth: usize
+}
+
+impl Config {
+    /// The level of a rule.
+    pub fn level(&self, rule: Rule) -> Level {
+        self.levels[rule as usize]
+    }
+
+    /// Set the level of a rule.
+    pub fn set_level(&mut self, rule: Rule, level: Level) {
+        self.levels[rule as usize] = level;
+    }
+}
+
+impl Default for Config {
+    /// All rules are enabled, except `UnknownNamespace`. Images
+    /// without an alternative text and duplicate anchors are errors;
+    /// other findings are warnings.
+    fn default() -> Self {
+        Config {
+            levels: [
+                Level::Warning,
+                Level::Warning,
+                Level::Error,
+                Level::Warning,
+                Level::Off,
+                Level::Error,
+                Level::Warning,
+                Level::Warning
+            ],
+            deprecated_names: vec![
+                ("core/cover-image".into(), Some("core/cover".into())),
+                ("core/subhead".into(), None),
+                ("core/text-columns".into(), Some("core/columns".into()))
+            ],
+            namespaces: vec!["core".into(), "core-embed".into()],
+            maximum_attributes_length: 4096,
+            maximum_depth: 8
+        }
+    }
+}
+
+/// Represent a finding, i.e. a block breaking a rule.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(not(feature = "no_std"), derive(Debug))]
+pub struct Finding {
+    /// The broken rule.
+    pub rule: Rule,
+
+    /// The level of the rule.
+    pub level: Level,
+
+    /// The span of the block within the input.
+    pub span: Span,
+
+    /// A message explaining the finding.
+    pub message: String
+}
+
+/// Lint the nodes produced by the parser from `input`. The findings
+/// are sorted in the order of the blocks within `input`.
+pub fn lint(input: Input, nodes: &[Node], config: &Config) -> Vec<Finding> {
+    let mut linter = Linter {
+        input,
+        config,
+        findings: vec![],
+        heading_level: None,
+        anchors: BTreeSet::new()
+    };
+
+    linter.lint_nodes(nodes, 1);
+
+    linter.findings
+}
+
+struct Linter<'a, 'c> {
+    input: Input<'a>,
+    config: &'c Config,
+    findings: Vec<Finding>,
+
+    /// The level of the previous heading, if any.
+    heading_level: Option<usize>,
+
+    /// The anchors used so far.
+    anchors: BTreeSet<String>
+}
+
+impl<'a, 'c> Linter<'a, 'c> {
+    fn lint_nodes(&mut self, nodes: &[Node], depth: usize) {
+        for node in nodes {
+            if let Node::Block { .. } = node {
+                self.lint_block(node, depth);
+            }
+        }
+    }
+
+    fn lint_block(&mut self, node: &Node, depth: usize) {
+        let (name, attributes, children) = match node {
+            Node::Block { name, attributes, children } => (name, attributes, children),
+            Node::Phrase(_) => return
+        };
+
+        let span = node.span(self.input);
+        let fully_qualified_name = name.fully_qualified();
+        let value = attributes.and_then(|attributes| json::parse(attributes).ok());
+        let html = phrases(children);
+
+        if depth == self.config.maximum_depth + 1 {
+            self.report(
+                Rule::MaximumDepth,
+                span,
+                format!("The block is nested at depth {}, more than {}.", depth, self.config.maximum_depth)
+            );
+        }
+
+        if let Some(attributes) = attributes {
+            if attributes.len() > self.config.maximum_attributes_length {
+                self.report(
+                    Rule::OversizedAttributes,
+                    span,
+                    format!("The attributes are {} bytes long, more than {}.", attributes.len(), self.config.maximum_attributes_length)
+                );
+            }
+        }
+
+        let namespace = String::from_utf8_lossy(name.namespace());
+
+        if !self.config.namespaces.iter().any(|known| *known == namespace) {
+            self.report(
+                Rule::UnknownNamespace,
+                span,
+                format!("The namespace `{}` is unknown.", namespace)
+            );
+        }
+
+        let deprecation = self.config.deprecated_names
+            .iter()
+            .find(|(deprecated_name, _)| *deprecated_name == fully_qualified_name)
+            .map(|(_, replacement)| replacement.clone());
+
+        match deprecation {
+            Some(Some(replacement)) => self.report(
+                Rule::DeprecatedName,
+                span,
+                format!("The block `{}` is deprecated, use `{}` instead.", fully_qualified_name, replacement)
+            ),
+
+            Some(None) => self.report(
+                Rule::DeprecatedName,
+                span,
+                format!("The block `{}` is deprecated.", fully_qualified_name)
+            ),
+
+            None => ()
+        }
+
+        match fully_qualified_name.as_str() {
+            "core/paragraph" if is_paragraph_empty(children) => {
+                self.report(Rule::EmptyParagraph, span, "The paragraph is empty.".into());
+            },
+
+            "core/heading" => {
+                let level = match value.as_ref().and_then(|value| value.get("level")).and_then(|level| level.as_number()) {
+                    Some(level) => str::from_utf8(level).ok().and_then(|level| level.parse::<usize>().ok()),
+                    None => Some(DEFAULT_HEADING_LEVEL)
+                };
+
+                if let Some(level) = level {
+                    if let Some(previous_level) = self.heading_level {
+                        if level > previous_level + 1 {
+                            self.report(
+                                Rule::HeadingLevel,
+                                span,
+                                format!("The heading level jumps from {} to {}.", previous_level, level)
+                            );
+                        }
+                    }
+
+                    self.heading_level = Some(level);
+                }
+            },
+
+            "core/image" => {
+                let alt = find_tag(&html, b"img").map(|tag| attribute(tag, b"alt"));
+
+                match alt {
+                    Some(None) => self.report(Rule::ImageAlt, span, "The image has no alternative text.".into()),
+
+                    Some(Some(alt)) if alt.iter().all(|byte| byte.is_ascii_whitespace()) => {
+                        self.report(Rule::ImageAlt, span, "The image has an empty alternative text.".into())
+                    },
+
+                    _ => ()
+                }
+            },
+
+            _ => ()
+        }
+
+        let anchor = match value.as_ref().and_then(|value| value.get("anchor")).and_then(|anchor| anchor.as_str()) {
+            Some(anchor) => Some(String::from(anchor)),
+            None => first_tag(&html).and_then(|tag| attribute(tag, b"id")).map(|id| String::from_utf8_lossy(id).into_owned())
+        };
+
+        if let Some(anchor) = anchor {
+            if !anchor.is_empty() && !self.anchors.insert(anchor.clone()) {
+                self.report(
+                    Rule::DuplicateAnchor,
+                    span,
+                    format!("The anchor `{}` is already used by another block.", anchor)
+                );
+            }
+        }
+
+        self.lint_nodes(children, depth + 1);
+    }
+
+    fn report(&mut self, rule: Rule, span: Span, message: String) {
+        let level = self.config.level(rule);
+
+        if level != Level::Off {
+            self.findings.push(Finding { rule, level, span, message });
+        }
+    }
+}
+
+/// Concatenate the phrases of a block, i.e. its own HTML, without
+/// its children blocks.
+fn phrases(children: &[Node]) -> Vec<u8> {
+    let mut html = vec![];
+
+    for child in children {
+        if let Node::Phrase(phrase) = child {
+            html.extend_from_slice(phrase);
+        }
+    }
+
+    html
+}
+
+/// Whether a paragraph has no text and no children blocks.
+fn is_paragraph_empty(children: &[Node]) -> bool {
+    children.iter().all(|child| {
+        match child {
+            Node::Phrase(phrase) => is_text_empty(phrase),
+            Node::Block { .. } => false
+        }
+    })
+}
+
+/// Whether some HTML has no text, i.e. only tags (except images),
+/// whitespaces and non-breaking spaces.
+fn is_text_empty(html: &[u8]) -> bool {
+    let mut index = 0;
+
+    while index < html.len() {
+        if html[index] == b'<' {
+            if html[index + 1..].starts_with(b"img") {
+                return false;
+            }
+
+            match html[index..].iter().position(|&byte| byte == b'>') {
+                Some(length) => index += length + 1,
+                None => return false
+            }
+        } else if html[index..].starts_with(b"&nbsp;") {
+            index += 6;
+        } else if html[index].is_ascii_whitespace() {
+            index += 1;
+        } else {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Find the first opening tag with the given name, and return its
+/// content, e.g. ` src="a.png"` for `<img src="a.png">`.
+fn find_tag<'h>(html: &'h [u8], name: &[u8]) -> Option<&'h [u8]> {
+    let mut offset = 0;
+
+    while let Some(position) = html[offset..].iter().position(|&byte| byte == b'<') {
+        let start = offset + position + 1;
+        let rest = &html[start..];
+
+        if rest.len() > name.len() && rest[..name.len()].eq_ignore_ascii_case(name) {
+            let after = rest[name.len()];
+
+            if after.is_ascii_whitespace() || after == b'>' || after == b'/' {
+                let end = rest.iter().position(|&byte| byte == b'>').unwrap_or(rest.len());
+
+                return Some(&rest[name.len()..end]);
+            }
+        }
+
+        offset = start;
+    }
+
+    None
+}
+
+/// Find the first opening tag, and return its content without its
+/// name.
+fn first_tag(html: &[u8]) -> Option<&[u8]> {
+    let mut offset = 0;
+
+    while let Some(position) = html[offset..].iter().position(|&byte| byte == b'<') {
+        let start = offset + position + 1;
+
+        match html.get(start) {
+            Some(byte) if byte.is_ascii_alphabetic() => {
+                let rest = &html[start..];
+                let name_length = rest.iter().position(|byte| !byte.is_ascii_alphanumeric()).unwrap_or(rest.len());
+
+                return find_tag(&html[start - 1..], &rest[..name_length]);
+            },
+
+            _ => offset = start
+        }
+    }
+
+    None
+}
+
+/// Find the value of an attribute in the content of a tag. An
+/// attribute without a value has an empty value.
+fn attribute<'h>(tag: &'h [u8], name: &[u8]) -> Option<&'h [u8]> {
+    let mut index = 0;
+
+    while index < tag.len() {
+        while index < tag.len() && (tag[index].is_ascii_whitespace() || tag[index] == b'/') {
+            index += 1;
+        }
+
+        let name_start = index;
+
+        while index < tag.len() && !tag[index].is_ascii_whitespace() && tag[index] != b'=' && tag[index] != b'/' {
+            index += 1;
+        }
+
+        let attribute_name = &tag[name_start..index];
+
+        while index < tag.len() && tag[index].is_ascii_whitespace() {
+            index += 1;
+        }
+
+        let value = if index < tag.len() && tag[index] == b'=' {
+            index += 1;
+
+            while index < tag.len() && tag[index].is_ascii_whitespace() {
+                index += 1;
+            }
+
+            match tag.get(index) {
+                Some(&quote) if quote == b'"' || quote == b'\'' => {
+                    let value_start = index + 1;
+                    let value_end = tag[value_start..].iter().position(|&byte| byte == quote).map_or(tag.len(), |length| value_start + length);
+                    index = value_end + 1;
+
+                    &tag[value_start..value_end]
+                },
+
+                _ => {
+                    let value_start = index;
+
+                    while index < tag.len() && !tag[index].is_ascii_whitespace() {
+                        index += 1;
+                    }
+
+                    &tag[value_start..index]
+                }
+            }
+        } else {
+            &tag[index..index]
+        };
+
+        if !attribute_name.is_empty() && attribute_name.eq_ignore_ascii_case(name) {
+            return Some(value);
+        }
+
+        if attribute_name.is_empty() && index < tag.len() {
+            index += 1;
+        }
+    }
+
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::root;
+
+    fn lint_rules(input: &[u8], config: &Config) -> Vec<(Rule, Span)> {
+        let (remaining, nodes) = root(input).unwrap();
+
+        assert!(remaining.is_empty());
+
+        lint(input, &nodes, config)
+            .into_iter()
+            .map(|finding| (finding.rule, finding.span))
+            .collect()
+    }
+
+    #[test]
+    fn test_rule_names() {
+        for rule in RULES.iter() {
+            assert_eq!(Rule::from_name(rule.as_str()), Some(*rule));
+        }
+
+        assert_eq!(Rule::from_name("foo"), None);
+    }
+
+    #[test]
+    fn test_empty_paragraph() {
+        assert_eq!(
+            lint_rules(b"<!-- wp:paragraph --><p>&nbsp;<br></p><!-- /wp:paragraph --><!-- wp:paragraph /--><!-- wp:paragraph --><p>a</p><!-- /wp:paragraph -->", &Config::default()),
+            vec![
+                (Rule::EmptyParagraph, Span { start: 0, end: 60 }),
+                (Rule::EmptyParagraph, Span { start: 60, end: 82 })
+            ]
+        );
+    }
+
+    #[test]
+    fn test_heading_level() {
+        assert_eq!(
+            lint_rules(b"<!-- wp:heading /--><!-- wp:heading {\"level\":3} /--><!-- wp:heading {\"level\":5} /--><!-- wp:heading {\"level\":2} /-->", &Config::default()),
+            vec![(Rule::HeadingLevel, Span { start: 52, end: 84 })]
+        );
+    }
+
+    #[test]
+    fn test_image_alt() {
+        assert_eq!(
+            lint_rules(b"<!-- wp:image --><img src=\"a.png\"/><!-- /wp:image --><!-- wp:image --><IMG alt=\" \"><!-- /wp:image --><!-- wp:image --><img src=a.png alt='a'><!-- /wp:image -->", &Config::default()),
+            vec![
+                (Rule::ImageAlt, Span { start: 0, end: 53 }),
+                (Rule::ImageAlt, Span { start: 53, end: 101 })
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deprecated_name() {
+        assert_eq!(
+            lint_rules(b"<!-- wp:cover-image /--><!-- wp:cover /-->", &Config::default()),
+            vec![(Rule::DeprecatedName, Span { start: 0, end: 24 })]
+        );
+    }
+
+    #[test]
+    fn test_unknown_namespace() {
+        let mut config = Config::default();
+        config.set_level(Rule::UnknownNamespace, Level::Error);
+
+        assert_eq!(
+            lint_rules(b"<!-- wp:foo /--><!-- wp:core-embed/foo /--><!-- wp:acme/foo /-->", &config),
+            vec![(Rule::UnknownNamespace, Span { start: 43, end: 64 })]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_anchor() {
+        assert_eq!(
+            lint_rules(b"<!-- wp:heading {\"anchor\":\"a\"} --><h2 id=\"a\">A</h2><!-- /wp:heading --><!-- wp:foo --><div class=\"b\" id=\"a\"><!-- /wp:foo -->", &Config::default()),
+            vec![(Rule::DuplicateAnchor, Span { start: 71, end: 124 })]
+        );
+    }
+
+    #[test]
+    fn test_oversized_attributes() {
+        let config = Config { maximum_attributes_length: 8, ..Config::default() };
+
+        assert_eq!(
+            lint_rules(b"<!-- wp:foo {\"a\":1} /--><!-- wp:foo {\"a\":\"bcd\"} /-->", &config),
+            vec![(Rule::OversizedAttributes, Span { start: 24, end: 52 })]
+        );
+    }
+
+    #[test]
+    fn test_maximum_depth() {
+        let config = Config { maximum_depth: 1, ..Config::default() };
+
+        assert_eq!(
+            lint_rules(b"<!-- wp:a --><!-- wp:b --><!-- wp:c /--><!-- /wp:b --><!-- /wp:a -->", &config),
+            vec![(Rule::MaximumDepth, Span { start: 13, end: 54 })]
+        );
+    }
+
+    #[test]
+    fn test_level_off() {
+        let mut config = Config::default();
+        config.set_level(Rule::EmptyParagraph, Level::Off);
+
+        assert_eq!(lint_rules(b"<!-- wp:paragraph /-->", &config), vec![]);
+    }
+}
@@ -1,149 +1,950 @@
+extern crate atty;
 extern crate gutenberg_post_parser;
 #[macro_use] extern crate failure;
 extern crate clap; 
 extern crate nom;
+extern crate rayon;
+extern crate rmp_serde;
+extern crate serde_cbor;
+#[macro_use] extern crate serde_json;
+extern crate serde_yaml;
+extern crate toml;
 
-use gutenberg_post_parser::{root, ast::Node, Input};
+use gutenberg_post_parser::{root, ast::Node, error, formatter, json, lint, output, Input};
 use failure::{Error, ResultExt};
-use clap::{App, Arg};
+use clap::{App, Arg, ArgGroup, ArgMatches, SubCommand};
+use rayon::prelude::*;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::collections::hash_map::DefaultHasher;
 use std::fs;
-use std::io::{self, prelude::*};
+use std::hash::{Hash, Hasher};
+use std::io::{self, prelude::*, BufReader};
+use std::path::{Path, PathBuf};
+use std::process;
 use std::str;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
-macro_rules! to_str (
-    ($slice:expr) => (
-        unsafe { str::from_utf8_unchecked($slice) }
-    )
-);
- 
-fn main() -> Result<(), Error> {
+/// Number of items (files or NDJSON lines) handed to the thread pool
+/// at once in batch mode. Results are written in order, batch after
+/// batch, so that memory stays bounded on large inputs.
+const BATCH_SIZE: usize = 1024;
+
+/// Exit code when every datum has been parsed entirely.
+const EXIT_SUCCESS: i32 = 0;
+
+/// Exit code when a datum cannot be read or parsed.
+const EXIT_FAILURE: i32 = 1;
+
+/// Exit code when every datum has been parsed, but some of them have
+/// leftovers, i.e. data that the parser has not recognized.
+const EXIT_LEFTOVERS: i32 = 2;
+
+/// Exit code when checking the format, and some data are not in their
+/// canonical form.
+const EXIT_UNFORMATTED: i32 = 3;
+
+/// Exit code when linting, and some findings are errors.
+const EXIT_FINDINGS: i32 = 4;
+
+/// Lint configuration read from the current directory if none is
+/// given.
+const DEFAULT_LINT_CONFIG: &str = ".gutenberg-lint.toml";
+
+/// Number of characters shown before and after the error location in
+/// a diagnostic snippet.
+const SNIPPET_CONTEXT: usize = 40;
+
+/// Status of a datum once parsed, from the best to the worst.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Status {
+    Parsed,
+    Unformatted,
+    Findings,
+    Leftovers,
+    Failed
+}
+
+impl Status {
+    fn exit_code(self) -> i32 {
+        match self {
+            Status::Parsed => EXIT_SUCCESS,
+            Status::Unformatted => EXIT_UNFORMATTED,
+            Status::Findings => EXIT_FINDINGS,
+            Status::Leftovers => EXIT_LEFTOVERS,
+            Status::Failed => EXIT_FAILURE
+        }
+    }
+}
+
+/// Number of characters of the abbreviated attributes, and of the
+/// phrase previews, in a tree.
+const TREE_PREVIEW_LENGTH: usize = 48;
+
+/// ANSI styles used in a tree, when colours are enabled.
+const STYLE_BLOCK_NAME: &str = "\x1b[1;34m";
+const STYLE_ATTRIBUTES: &str = "\x1b[33m";
+const STYLE_PHRASE: &str = "\x1b[32m";
+const STYLE_DIMMED: &str = "\x1b[2m";
+const STYLE_RESET: &str = "\x1b[0m";
+
+/// Format of the AST written on `stdout`.
+#[derive(Clone, Copy, PartialEq)]
+enum Emit {
+    Json,
+    Debug,
+    Tree,
+    Cbor,
+    MessagePack,
+    Yaml
+}
+
+/// Format of the diagnostics written on `stderr`.
+#[derive(Clone, Copy, PartialEq)]
+enum DiagnosticsFormat {
+    Human,
+    Json
+}
+
+/// Settings shared by all the parsed data.
+#[derive(Clone, Copy)]
+struct Settings<'a> {
+    emit: Emit,
+    diagnostics_format: DiagnosticsFormat,
+    extension: &'a str,
+
+    /// Whether the tree shows the spans of the nodes.
+    spans: bool,
+
+    /// The maximum depth of the tree, if any.
+    max_depth: Option<usize>,
+
+    /// Whether the tree is coloured.
+    color: bool
+}
+
+fn main() {
+    let exit_code = match run() {
+        Ok(status) => status.exit_code(),
+
+        Err(error) => {
+            eprintln!("error: {}", error);
+
+            let mut cause = error.as_fail().cause();
+
+            while let Some(fail) = cause {
+                eprintln!("  caused by: {}", fail);
+                cause = fail.cause();
+            }
+
+            EXIT_FAILURE
+        }
+    };
+
+    io::stdout().flush().ok();
+    process::exit(exit_code);
+}
+
+fn run() -> Result<Status, Error> {
     let matches =
         App::new("gutenberg-post-parser")
             .version(env!("CARGO_PKG_VERSION"))
             .about("Parse Gutenberg posts!")
             .author("Ivan Enderlin")
+            .after_help("EXIT CODES:\n    0    Everything has been parsed.\n    1    An input cannot be read or parsed.\n    2    Everything has been parsed, but some data have not been recognized (see the diagnostics).\n    3    With `fmt --check`, some data are not formatted.\n    4    With `lint`, some findings are errors.")
             .arg(
                 Arg::with_name("emit-json")
                     .help("Compile the AST into JSON (default).")
                     .short("j")
                     .long("emit-json")
+                    .global(true)
             )
             .arg(
                 Arg::with_name("emit-debug")
                     .help("Compile the AST into Rust debug format.")
                     .short("d")
                     .long("emit-debug")
+                    .global(true)
+            )
+            .arg(
+                Arg::with_name("emit-tree")
+                    .help("Print the AST as an indented outline.")
+                    .long("emit-tree")
+                    .global(true)
+            )
+            .arg(
+                Arg::with_name("emit-cbor")
+                    .help("Compile the AST into CBOR, with the same structure as JSON.")
+                    .long("emit-cbor")
+                    .global(true)
+            )
+            .arg(
+                Arg::with_name("emit-msgpack")
+                    .help("Compile the AST into MessagePack, with the same structure as JSON.")
+                    .long("emit-msgpack")
+                    .global(true)
+            )
+            .arg(
+                Arg::with_name("emit-yaml")
+                    .help("Compile the AST into YAML, with the same structure as JSON.")
+                    .long("emit-yaml")
+                    .global(true)
+            )
+            .group(
+                ArgGroup::with_name("emit")
+                    .args(&["emit-json", "emit-debug", "emit-tree", "emit-cbor", "emit-msgpack", "emit-yaml"])
+            )
+            .arg(
+                Arg::with_name("spans")
+                    .help("Print the span, in bytes, of each node of the tree.")
+                    .long("spans")
+                    .global(true)
+                    .requires("emit-tree")
+            )
+            .arg(
+                Arg::with_name("max-depth")
+                    .help("Maximum depth of the tree; deeper nodes are counted but not printed.")
+                    .long("max-depth")
+                    .global(true)
+                    .takes_value(true)
+                    .requires("emit-tree")
+            )
+            .arg(
+                Arg::with_name("color")
+                    .help("Colour the tree.")
+                    .long("color")
+                    .global(true)
+                    .takes_value(true)
+                    .possible_values(&["auto", "always", "never"])
+                    .default_value("auto")
+            )
+            .arg(
+                Arg::with_name("diagnostics-format")
+                    .help("Format of the diagnostics written on `stderr`.")
+                    .long("diagnostics-format")
+                    .global(true)
+                    .takes_value(true)
+                    .possible_values(&["human", "json"])
+                    .default_value("human")
+            )
+            .arg(
+                Arg::with_name("ndjson")
+                    .help("Read one JSON-encoded post per line, with a `post_content` field (as exported from `wp_posts`), and write one parsed result per line.")
+                    .long("ndjson")
+                    .conflicts_with_all(&["emit-debug", "emit-tree", "emit-cbor", "emit-msgpack", "emit-yaml"])
+            )
+            .arg(
+                Arg::with_name("extension")
+                    .help("Extension of the files to parse when walking a directory.")
+                    .long("extension")
+                    .global(true)
+                    .takes_value(true)
+                    .default_value("html")
+            )
+            .arg(
+                Arg::with_name("threads")
+                    .help("Number of threads used to parse several items (default: number of CPUs).")
+                    .short("t")
+                    .long("threads")
+                    .takes_value(true)
             )
             .arg(
                 Arg::with_name("INPUT")
-                    .help("File containing the input (if absent, read `stdin`).")
+                    .help("Files or directories containing the input (if absent, read `stdin`). Directories are walked recursively.")
                     .required(false)
+                    .multiple(true)
                     .index(1)
             )
+            .subcommand(
+                SubCommand::with_name("fmt")
+                    .about("Format posts into their canonical form. Files are formatted in place; `stdin` is formatted on `stdout`.")
+                    .arg(
+                        Arg::with_name("check")
+                            .help("Do not write anything, but exit with 3 if some data are not formatted.")
+                            .long("check")
+                    )
+                    .arg(
+                        Arg::with_name("indent")
+                            .help("Number of spaces per level of nesting to indent the delimiters starting a line with.")
+                            .long("indent")
+                            .takes_value(true)
+                            .value_name("N")
+                    )
+                    .arg(
+                        Arg::with_name("void-empty-blocks")
+                            .help("Write the blocks without children void, e.g. `<!-- wp:foo /-->`.")
+                            .long("void-empty-blocks")
+                    )
+                    .arg(
+                        Arg::with_name("INPUT")
+                            .help("Files or directories containing the posts to format (if absent, read `stdin`). Directories are walked recursively.")
+                            .required(false)
+                            .multiple(true)
+                            .index(1)
+                    )
+            )
+            .subcommand(
+                SubCommand::with_name("lint")
+                    .about("Lint posts, and write the findings on `stdout`.")
+                    .arg(
+                        Arg::with_name("config")
+                            .help("TOML file configuring the rules (default: `.gutenberg-lint.toml` if it exists).")
+                            .long("config")
+                            .takes_value(true)
+                            .value_name("FILE")
+                    )
+                    .arg(
+                        Arg::with_name("format")
+                            .help("Format of the findings.")
+                            .long("format")
+                            .takes_value(true)
+                            .possible_values(&["human", "sarif"])
+                            .default_value("human")
+                    )
+                    .arg(
+                        Arg::with_name("INPUT")
+                            .help("Files or directories containing the posts to lint (if absent, read `stdin`). Directories are walked recursively.")
+                            .required(false)
+                            .multiple(true)
+                            .index(1)
+                    )
+            )
+            .subcommand(
+                SubCommand::with_name("watch")
+                    .about("Watch files or directories, and parse the files again each time they change.")
+                    .arg(
+                        Arg::with_name("interval")
+                            .help("Number of milliseconds between two checks of the files.")
+                            .long("interval")
+                            .takes_value(true)
+                            .value_name("MS")
+                            .default_value("500")
+                    )
+                    .arg(
+                        Arg::with_name("INPUT")
+                            .help("Files or directories to watch. Directories are walked recursively, so that new files are parsed too.")
+                            .required(true)
+                            .multiple(true)
+                            .index(1)
+                    )
+            )
             .get_matches();
 
-    let mut content;
+    if let Some(threads) = matches.value_of("threads") {
+        let threads = threads.parse::<usize>().context("The number of threads must be a positive integer.")?;
+
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .context("Cannot configure the thread pool.")?;
+    }
+
+    let max_depth = match matches.value_of("max-depth") {
+        Some(max_depth) => Some(max_depth.parse::<usize>().context("The maximum depth must be a positive integer.")?),
+        None => None
+    };
 
-    match matches.value_of("INPUT") {
-        Some(file_name) => {
-            content = fs::read_to_string(file_name).context("Cannot open or read the given file to parse.")?;
+    let settings = Settings {
+        emit: if matches.is_present("emit-tree") {
+            Emit::Tree
+        } else if matches.is_present("emit-debug") {
+            Emit::Debug
+        } else if matches.is_present("emit-cbor") {
+            Emit::Cbor
+        } else if matches.is_present("emit-msgpack") {
+            Emit::MessagePack
+        } else if matches.is_present("emit-yaml") {
+            Emit::Yaml
+        } else {
+            Emit::Json
+        },
+        diagnostics_format: match matches.value_of("diagnostics-format") {
+            Some("json") => DiagnosticsFormat::Json,
+            _ => DiagnosticsFormat::Human
         },
+        extension: matches.value_of("extension").unwrap_or("html"),
+        spans: matches.is_present("spans"),
+        max_depth,
+        color: match matches.value_of("color") {
+            Some("always") => true,
+            Some("never") => false,
+            _ => atty::is(atty::Stream::Stdout)
+        }
+    };
 
-        None => {
+    if let Some(fmt_matches) = matches.subcommand_matches("fmt") {
+        return format_posts(fmt_matches, &settings);
+    }
+
+    if let Some(lint_matches) = matches.subcommand_matches("lint") {
+        return lint_posts(lint_matches, &settings);
+    }
+
+    if let Some(watch_matches) = matches.subcommand_matches("watch") {
+        return watch(watch_matches, &settings);
+    }
+
+    let inputs: Vec<&str> = matches.values_of("INPUT").map(|values| values.collect()).unwrap_or_default();
+
+    if matches.is_present("ndjson") {
+        return parse_ndjson(&inputs);
+    }
+
+    match inputs.as_slice() {
+        [] => {
             let stdin = io::stdin();
-            content = String::new();
+            let mut content = String::new();
             stdin.lock().read_to_string(&mut content).context("Cannot read from `stdin`.")?;
-        }
+
+            parse_datum("<stdin>", &content, &settings)
+        },
+
+        [file_name] if !Path::new(file_name).is_dir() => {
+            let content = fs::read_to_string(file_name).context("Cannot open or read the given file to parse.")?;
+
+            parse_datum(file_name, &content, &settings)
+        },
+
+        _ => parse_files(&inputs, &settings)
     }
+}
 
-    match root(content.as_bytes()) {
-        Ok((remaining, nodes)) => {
-            if matches.is_present("emit-debug") {
-                let debug: nom::IResult<Input, Vec<Node>> = Ok((remaining, nodes));
+/// Parse a single datum, write the result on `stdout`, and the
+/// diagnostic, if any, on `stderr`.
+fn parse_datum(source: &str, content: &str, settings: &Settings) -> Result<Status, Error> {
+    let (remaining, nodes) = root(content.as_bytes()).map_err(|_| format_err!("Failed to parse the datum."))?;
+    let diagnostic = error::diagnose(content.as_bytes(), remaining);
 
-                print!("{:?}", debug);
-            } else {
-                let mut stdout = io::stdout();
-                let mut lock = stdout.lock();
+    {
+        let stdout = io::stdout();
+        let mut lock = stdout.lock();
 
-                serialize_nodes_to_json(lock, nodes).context("Failed to serialize parser output to JSON.")?;
-            }
+        write_nodes(&mut lock, content, remaining, nodes, settings)?;
+        lock.flush()?;
+    }
+
+    match diagnostic {
+        Some(error) => {
+            let stderr = io::stderr();
+
+            write_diagnostic(&mut stderr.lock(), source, content, &error, settings.diagnostics_format)?;
+
+            Ok(Status::Leftovers)
         },
 
-        Err(_) => {
-            return Err(format_err!("Failed to parse the datum."));
-        }
+        None => Ok(Status::Parsed)
     }
+}
 
-    Ok(())
+/// Location of an error in a datum.
+struct Location {
+    /// The line, starting at 1.
+    line: usize,
+
+    /// The column, starting at 1, counted in characters.
+    column: usize,
+
+    /// An excerpt of the line, centered on the error.
+    snippet: String,
+
+    /// The position of the error in the snippet, in characters.
+    snippet_column: usize
 }
 
-fn serialize_nodes_to_json<W: Write>(mut writer: W, nodes: Vec<Node>) -> Result<(), Error> {
-    writer.write_all(&b"["[..])?;
+impl Location {
+    fn of(content: &str, offset: usize) -> Self {
+        let bytes = content.as_bytes();
+        let before = &bytes[..offset];
+        let line_start = before.iter().rposition(|&byte| byte == b'\n').map_or(0, |index| index + 1);
+        let line_end = bytes[offset..].iter().position(|&byte| byte == b'\n').map_or(bytes.len(), |index| offset + index);
 
-    for (index, node) in nodes.iter().enumerate() {
-        if 0 != index {
-            writer.write_all(&b","[..])?;
+        let prefix: Vec<char> = String::from_utf8_lossy(&bytes[line_start..offset]).chars().collect();
+        let suffix: Vec<char> = String::from_utf8_lossy(&bytes[offset..line_end]).trim_end_matches('\r').chars().collect();
+
+        let prefix_start = prefix.len().saturating_sub(SNIPPET_CONTEXT);
+        let suffix_end = suffix.len().min(SNIPPET_CONTEXT);
+        let mut snippet = String::new();
+
+        if prefix_start > 0 {
+            snippet.push('…');
         }
 
-        serialize_node_to_json(&mut writer, node)?;
+        snippet.extend(&prefix[prefix_start..]);
+        let snippet_column = snippet.chars().count();
+        snippet.extend(&suffix[..suffix_end]);
+
+        if suffix_end < suffix.len() {
+            snippet.push('…');
+        }
+
+        Location {
+            line: before.iter().filter(|&&byte| byte == b'\n').count() + 1,
+            column: prefix.len() + 1,
+            snippet: snippet.replace('\t', " "),
+            snippet_column
+        }
     }
+}
+
+/// Represent a diagnostic as a JSON object.
+fn diagnostic_to_json(content: &str, error: &error::Error) -> Value {
+    let location = Location::of(content, error.offset);
+
+    json!({
+        "kind": error.kind.as_str(),
+        "message": error.kind.description(),
+        "offset": error.offset,
+        "line": location.line,
+        "column": location.column,
+        "snippet": location.snippet
+    })
+}
 
-    writer.write_all(&b"]"[..])?;
+/// Write a located diagnostic, e.g.:
+///
+/// ```text
+/// error[unclosed-block]: The block is opened but never closed.
+///  --> post.html:3:5
+///   |
+/// 3 | abc <!-- wp:foo -->def
+///   |     ^
+/// ```
+///
+/// With the JSON format, the diagnostic is a JSON object on a single
+/// line.
+fn write_diagnostic<W: Write>(writer: &mut W, source: &str, content: &str, error: &error::Error, format: DiagnosticsFormat) -> Result<(), Error> {
+    match format {
+        DiagnosticsFormat::Human => write_located(writer, "error", error.kind.as_str(), error.kind.description(), source, content, error.offset)?,
+
+        DiagnosticsFormat::Json => {
+            let mut diagnostic = diagnostic_to_json(content, error);
+            diagnostic["file"] = Value::from(source);
+
+            writeln!(writer, "{}", diagnostic)?;
+        }
+    }
 
     Ok(())
 }
 
-fn serialize_node_to_json<W: Write>(writer: &mut W, node: &Node) -> Result<(), Error> {
-    match node {
-        Node::Block { name, attributes, children } => {
-            writer.write_all(&b"{"[..])?;
+/// Write a located message, e.g. a diagnostic or a lint finding, in
+/// the human format.
+fn write_located<W: Write>(writer: &mut W, level: &str, code: &str, message: &str, source: &str, content: &str, offset: usize) -> io::Result<()> {
+    let location = Location::of(content, offset);
+    let gutter = " ".repeat(location.line.to_string().len());
+
+    writeln!(writer, "{}[{}]: {}", level, code, message)?;
+    writeln!(writer, "{}--> {}:{}:{}", gutter, source, location.line, location.column)?;
+    writeln!(writer, "{} |", gutter)?;
+    writeln!(writer, "{} | {}", location.line, location.snippet)?;
+    writeln!(writer, "{} | {}^", gutter, " ".repeat(location.snippet_column))
+}
+
+/// Write a diagnostic about a datum that cannot be read or parsed.
+fn write_failure<W: Write>(writer: &mut W, source: &str, error: &Error, format: DiagnosticsFormat) -> Result<(), Error> {
+    match format {
+        DiagnosticsFormat::Human => writeln!(writer, "error: {}: {}", source, error)?,
 
-            write!(
+        DiagnosticsFormat::Json => {
+            writeln!(
                 writer,
-                "\"blockName\":\"{0}/{1}\",\"attrs\":{2}",
-                to_str!(name.0),
-                to_str!(name.1),
-                match attributes {
-                    Some(attributes) => to_str!(attributes),
-                    None => "null"
-                }
+                "{}",
+                json!({
+                    "file": source,
+                    "kind": "failure",
+                    "message": error.to_string()
+                })
             )?;
+        }
+    }
 
-            let mut blocks = vec![];
-            let mut phrases = vec![];
+    Ok(())
+}
+
+/// Count of parsed data in batch mode, per status, reported on
+/// `stderr` once everything has been parsed.
+#[derive(Default)]
+struct Summary {
+    parsed: usize,
+    unformatted: usize,
+    findings: usize,
+    leftovers: usize,
+    failed: usize
+}
+
+impl Summary {
+    fn record(&mut self, status: Status) {
+        match status {
+            Status::Parsed => self.parsed += 1,
+            Status::Unformatted => self.unformatted += 1,
+            Status::Findings => self.findings += 1,
+            Status::Leftovers => self.leftovers += 1,
+            Status::Failed => self.failed += 1
+        }
+    }
+
+    fn report(&self, start: Instant) -> Status {
+        let elapsed = start.elapsed();
+        let mut checks = String::new();
+
+        if self.unformatted > 0 {
+            checks.push_str(&format!("{} not formatted, ", self.unformatted));
+        }
+
+        if self.findings > 0 {
+            checks.push_str(&format!("{} with findings, ", self.findings));
+        }
+
+        eprintln!(
+            "{} item(s): {} parsed, {}{} with leftovers, {} failed, in {}.{:03}s.",
+            self.parsed + self.unformatted + self.findings + self.leftovers + self.failed,
+            self.parsed,
+            checks,
+            self.leftovers,
+            self.failed,
+            elapsed.as_secs(),
+            elapsed.subsec_millis()
+        );
+
+        if self.failed > 0 {
+            Status::Failed
+        } else if self.leftovers > 0 {
+            Status::Leftovers
+        } else if self.findings > 0 {
+            Status::Findings
+        } else if self.unformatted > 0 {
+            Status::Unformatted
+        } else {
+            Status::Parsed
+        }
+    }
+}
+
+/// Output of a datum parsed by a worker thread in batch mode, written
+/// in order by the main thread.
+struct Parsed {
+    status: Status,
+    output: Vec<u8>,
+    diagnostic: Option<Vec<u8>>
+}
+
+/// Parse several files, or files from directories, across the thread
+/// pool. With JSON, one object is written per line and per file,
+/// either `{"path":…,"blocks":[…]}` or `{"path":…,"error":…}`.
+/// Diagnostics are written on `stderr`.
+fn parse_files(inputs: &[&str], settings: &Settings) -> Result<Status, Error> {
+    let start = Instant::now();
+    let stdout = io::stdout();
+    let mut lock = stdout.lock();
+    let stderr = io::stderr();
+    let mut error_lock = stderr.lock();
+    let mut summary = Summary::default();
+
+    for_each_batch(
+        inputs,
+        settings,
+        |path| parse_file(path, settings),
+        |path, outcome| {
+            match outcome {
+                Ok(parsed) => {
+                    summary.record(parsed.status);
+                    lock.write_all(&parsed.output)?;
+
+                    if let Some(diagnostic) = parsed.diagnostic {
+                        error_lock.write_all(&diagnostic)?;
+                    }
+                },
+
+                Err(error) => {
+                    summary.record(Status::Failed);
 
-            for child in children {
-                match child {
-                    Node::Block { .. } => blocks.push(child),
-                    Node::Phrase(phrase) => phrases.push(phrase)
+                    if settings.emit != Emit::Json {
+                        write_failure(&mut error_lock, &path.to_string_lossy(), &error, settings.diagnostics_format)?;
+                    } else {
+                        writeln!(
+                            lock,
+                            "{{\"path\":{},\"error\":{}}}",
+                            Value::from(path.to_string_lossy()),
+                            Value::from(error.to_string())
+                        )?;
+                    }
                 }
             }
 
-            writer.write_all(&b",\"innerBlocks\":["[..])?;
+            Ok(())
+        }
+    )?;
+
+    lock.flush()?;
+
+    Ok(summary.report(start))
+}
+
+/// Collect the files of `inputs`, process them across the thread pool
+/// with `process`, by batches of `BATCH_SIZE`, and hand the outcomes to
+/// `write` in the order of the files, so that memory stays bounded.
+fn for_each_batch<T, P, W>(inputs: &[&str], settings: &Settings, process: P, mut write: W) -> Result<(), Error>
+where
+    T: Send,
+    P: Fn(&Path) -> Result<T, Error> + Sync,
+    W: FnMut(&Path, Result<T, Error>) -> Result<(), Error>
+{
+    let mut paths = vec![];
+
+    for input in inputs {
+        collect_files(Path::new(input), settings.extension, &mut paths).with_context(|_| format!("Cannot walk `{}`.", input))?;
+    }
 
-            for (index, block) in blocks.iter().enumerate() {
-                if 0 != index {
-                    writer.write_all(&b","[..])?;
+    for batch in paths.chunks(BATCH_SIZE) {
+        let outcomes: Vec<Result<T, Error>> =
+            batch
+                .par_iter()
+                .map(|path| process(path))
+                .collect();
+
+        for (path, outcome) in batch.iter().zip(outcomes) {
+            write(path, outcome)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Collect the files to parse from `path`. A directory is walked
+/// recursively, and only the files with the given extension are kept;
+/// symbolic links to directories are not followed. A file that is
+/// explicitly given is always kept.
+fn collect_files(path: &Path, extension: &str, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(path)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.path());
+
+    for entry in entries {
+        let entry_path = entry.path();
+
+        if entry.file_type()?.is_dir() {
+            collect_files(&entry_path, extension, files)?;
+        } else if entry_path.is_file() && entry_path.extension() == Some(extension.as_ref()) {
+            files.push(entry_path);
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_file(path: &Path, settings: &Settings) -> Result<Parsed, Error> {
+    let content = fs::read_to_string(path).context("Cannot open or read the file to parse.")?;
+    let (remaining, nodes) = root(content.as_bytes()).map_err(|_| format_err!("Failed to parse the datum."))?;
+    let mut output = vec![];
+
+    let diagnostic = match error::diagnose(content.as_bytes(), remaining) {
+        Some(error) => {
+            let mut diagnostic = vec![];
+            write_diagnostic(&mut diagnostic, &path.to_string_lossy(), &content, &error, settings.diagnostics_format)?;
+
+            Some(diagnostic)
+        },
+
+        None => None
+    };
+
+    match settings.emit {
+        Emit::Json => {
+            write!(output, "{{\"path\":{},\"blocks\":", Value::from(path.to_string_lossy()))?;
+            write_nodes(&mut output, &content, remaining, nodes, settings)?;
+            output.write_all(&b"}\n"[..])?;
+        },
+
+        Emit::Debug | Emit::Tree => {
+            writeln!(output, "{}:", path.display())?;
+            write_nodes(&mut output, &content, remaining, nodes, settings)?;
+            writeln!(output)?;
+        },
+
+        // One item per file, in a sequence of items.
+        Emit::Cbor | Emit::MessagePack | Emit::Yaml => {
+            let item = json!({
+                "path": path.to_string_lossy(),
+                "blocks": nodes_to_value(&output::recover(content.as_bytes(), remaining, nodes))
+            });
+
+            write_value(&mut output, &item, settings.emit)?;
+        }
+    }
+
+    Ok(
+        Parsed {
+            status: if diagnostic.is_some() { Status::Leftovers } else { Status::Parsed },
+            output,
+            diagnostic
+        }
+    )
+}
+
+/// Message of the diagnostic about a datum that is not formatted.
+const UNFORMATTED_MESSAGE: &str = "The datum is not in its canonical form.";
+
+/// Outcome of formatting a datum.
+enum Formatted {
+    /// The datum is already in its canonical form.
+    Unchanged,
+
+    /// The datum in its canonical form.
+    Changed(Vec<u8>),
+
+    /// The datum cannot be formatted, because it cannot be parsed
+    /// entirely.
+    Rejected(error::Error)
+}
+
+fn format_content(content: &str, options: &formatter::FormatOptions) -> Formatted {
+    match formatter::format(content.as_bytes(), options) {
+        Ok(ref output) if output.as_slice() == content.as_bytes() => Formatted::Unchanged,
+        Ok(output) => Formatted::Changed(output),
+        Err(error) => Formatted::Rejected(error)
+    }
+}
+
+/// Format posts into their canonical form, see the `fmt` subcommand.
+/// `stdin` is formatted on `stdout`, and files are formatted in place.
+/// With `--check`, nothing is written but the diagnostics.
+fn format_posts(matches: &ArgMatches, settings: &Settings) -> Result<Status, Error> {
+    let options = formatter::FormatOptions {
+        indentation: match matches.value_of("indent") {
+            Some(indentation) => Some(indentation.parse::<usize>().context("The indentation must be a positive integer.")?),
+            None => None
+        },
+        void_empty_blocks: matches.is_present("void-empty-blocks")
+    };
+    let check = matches.is_present("check");
+    let inputs: Vec<&str> = matches.values_of("INPUT").map(|values| values.collect()).unwrap_or_default();
+
+    if inputs.is_empty() {
+        let stdin = io::stdin();
+        let mut content = String::new();
+        stdin.lock().read_to_string(&mut content).context("Cannot read from `stdin`.")?;
+
+        let stdout = io::stdout();
+        let stderr = io::stderr();
+
+        return match format_content(&content, &options) {
+            Formatted::Unchanged => {
+                if !check {
+                    stdout.lock().write_all(content.as_bytes())?;
                 }
 
-                serialize_node_to_json(writer, block)?;
+                Ok(Status::Parsed)
+            },
+
+            Formatted::Changed(_) if check => {
+                write_unformatted(&mut stderr.lock(), "<stdin>", settings.diagnostics_format)?;
+
+                Ok(Status::Unformatted)
+            },
+
+            Formatted::Changed(output) => {
+                stdout.lock().write_all(&output)?;
+
+                Ok(Status::Parsed)
+            },
+
+            Formatted::Rejected(error) => {
+                write_diagnostic(&mut stderr.lock(), "<stdin>", &content, &error, settings.diagnostics_format)?;
+
+                Ok(Status::Leftovers)
             }
+        };
+    }
+
+    let start = Instant::now();
+    let stderr = io::stderr();
+    let mut error_lock = stderr.lock();
+    let mut summary = Summary::default();
 
-            writer.write_all(&b"],\"innerHTML\":\""[..])?;
+    for_each_batch(
+        &inputs,
+        settings,
+        |path| format_file(path, check, &options, settings),
+        |path, outcome| {
+            match outcome {
+                Ok(parsed) => {
+                    summary.record(parsed.status);
 
-            for phrase in phrases {
-                writer.write_all(escape_json_literal(to_str!(phrase)).as_bytes())?;
+                    if let Some(diagnostic) = parsed.diagnostic {
+                        error_lock.write_all(&diagnostic)?;
+                    }
+                },
+
+                Err(error) => {
+                    summary.record(Status::Failed);
+                    write_failure(&mut error_lock, &path.to_string_lossy(), &error, settings.diagnostics_format)?;
+                }
             }
 
-            writer.write_all(&b"\"}"[..])?;
+            Ok(())
+        }
+    )?;
+
+    Ok(summary.report(start))
+}
+
+/// Format a file in place, or only check it is formatted.
+fn format_file(path: &Path, check: bool, options: &formatter::FormatOptions, settings: &Settings) -> Result<Parsed, Error> {
+    let content = fs::read_to_string(path).context("Cannot open or read the file to format.")?;
+    let source = path.to_string_lossy();
+    let mut diagnostic = vec![];
+
+    let status = match format_content(&content, options) {
+        Formatted::Unchanged => Status::Parsed,
+
+        Formatted::Changed(_) if check => {
+            write_unformatted(&mut diagnostic, &source, settings.diagnostics_format)?;
+
+            Status::Unformatted
         },
 
-        Node::Phrase(phrase) => {
-            write!(
+        Formatted::Changed(output) => {
+            fs::write(path, output).context("Cannot write the formatted file.")?;
+
+            Status::Parsed
+        },
+
+        Formatted::Rejected(error) => {
+            write_diagnostic(&mut diagnostic, &source, &content, &error, settings.diagnostics_format)?;
+
+            Status::Leftovers
+        }
+    };
+
+    Ok(
+        Parsed {
+            status,
+            output: vec![],
+            diagnostic: if diagnostic.is_empty() { None } else { Some(diagnostic) }
+        }
+    )
+}
+
+/// Write a diagnostic about a datum that is not in its canonical form.
+fn write_unformatted<W: Write>(writer: &mut W, source: &str, format: DiagnosticsFormat) -> Result<(), Error> {
+    match format {
+        DiagnosticsFormat::Human => writeln!(writer, "error: {}: {}", source, UNFORMATTED_MESSAGE)?,
+
+        DiagnosticsFormat::Json => {
+            writeln!(
                 writer,
-                "{{\"attrs\":{{}},\"innerHTML\":\"{0}\"}}",
-                escape_json_literal(to_str!(phrase))
+                "{}",
+                json!({
+                    "file": source,
+                    "kind": "unformatted",
+                    "message": UNFORMATTED_MESSAGE
+                })
             )?;
         }
     }
@@ -151,12 +952,818 @@ fn serialize_node_to_json<W: Write>(writer: &mut W, node: &Node) -> Result<(), E
     Ok(())
 }
 
-fn escape_json_literal(str: &str) -> String {
-    str
-        .replace("\n", "\\n")
-        .replace("\r", "\\r")
-        .replace("\t", "\\t")
-        .replace("\"", "\\\"")
-        .replace("\u{0008}", "\\b")
-        .replace("\u{000C}", "\\f")
+/// Format of the lint findings written on `stdout`.
+#[derive(Clone, Copy, PartialEq)]
+enum LintFormat {
+    Human,
+    Sarif
+}
+
+/// Findings of a datum linted by a worker thread, written in order by
+/// the main thread.
+struct Linted {
+    status: Status,
+
+    /// The findings in the human format.
+    output: Vec<u8>,
+
+    /// The findings as SARIF results.
+    results: Vec<Value>,
+
+    diagnostic: Option<Vec<u8>>
+}
+
+/// Lint posts, see the `lint` subcommand. With the human format, the
+/// findings are written as they come; with SARIF, a single log is
+/// written once everything has been linted. Diagnostics are written on
+/// `stderr`.
+fn lint_posts(matches: &ArgMatches, settings: &Settings) -> Result<Status, Error> {
+    let config = match matches.value_of("config") {
+        Some(path) => load_lint_config(Path::new(path))?,
+        None if Path::new(DEFAULT_LINT_CONFIG).is_file() => load_lint_config(Path::new(DEFAULT_LINT_CONFIG))?,
+        None => lint::Config::default()
+    };
+    let format = match matches.value_of("format") {
+        Some("sarif") => LintFormat::Sarif,
+        _ => LintFormat::Human
+    };
+    let inputs: Vec<&str> = matches.values_of("INPUT").map(|values| values.collect()).unwrap_or_default();
+
+    let stdout = io::stdout();
+    let mut lock = stdout.lock();
+    let stderr = io::stderr();
+    let mut error_lock = stderr.lock();
+    let mut results = vec![];
+
+    let status = if inputs.is_empty() {
+        let mut content = String::new();
+        io::stdin().read_to_string(&mut content).context("Cannot read from `stdin`.")?;
+
+        let linted = lint_content("<stdin>", &content, &config, format, settings)?;
+        lock.write_all(&linted.output)?;
+        results.extend(linted.results);
+
+        if let Some(diagnostic) = linted.diagnostic {
+            error_lock.write_all(&diagnostic)?;
+        }
+
+        linted.status
+    } else {
+        let start = Instant::now();
+        let mut summary = Summary::default();
+
+        for_each_batch(
+            &inputs,
+            settings,
+            |path| {
+                let content = fs::read_to_string(path).context("Cannot open or read the file to lint.")?;
+
+                lint_content(&path.to_string_lossy(), &content, &config, format, settings)
+            },
+            |path, outcome| {
+                match outcome {
+                    Ok(linted) => {
+                        summary.record(linted.status);
+                        lock.write_all(&linted.output)?;
+                        results.extend(linted.results);
+
+                        if let Some(diagnostic) = linted.diagnostic {
+                            error_lock.write_all(&diagnostic)?;
+                        }
+                    },
+
+                    Err(error) => {
+                        summary.record(Status::Failed);
+                        write_failure(&mut error_lock, &path.to_string_lossy(), &error, settings.diagnostics_format)?;
+                    }
+                }
+
+                Ok(())
+            }
+        )?;
+
+        summary.report(start)
+    };
+
+    if format == LintFormat::Sarif {
+        writeln!(lock, "{}", sarif_log(&config, results))?;
+    }
+
+    lock.flush()?;
+
+    Ok(status)
+}
+
+/// Lint a single datum.
+fn lint_content(source: &str, content: &str, config: &lint::Config, format: LintFormat, settings: &Settings) -> Result<Linted, Error> {
+    let input = content.as_bytes();
+    let (remaining, nodes) = root(input).map_err(|_| format_err!("Failed to parse the datum."))?;
+    let findings = lint::lint(input, &nodes, config);
+    let mut output = vec![];
+    let mut results = vec![];
+
+    for finding in &findings {
+        match format {
+            LintFormat::Human => {
+                write_located(&mut output, finding.level.as_str(), finding.rule.as_str(), &finding.message, source, content, finding.span.start)?;
+                writeln!(output)?;
+            },
+
+            LintFormat::Sarif => results.push(sarif_result(source, content, finding))
+        }
+    }
+
+    let diagnostic = match error::diagnose(input, remaining) {
+        Some(error) => {
+            let mut diagnostic = vec![];
+            write_diagnostic(&mut diagnostic, source, content, &error, settings.diagnostics_format)?;
+
+            Some(diagnostic)
+        },
+
+        None => None
+    };
+
+    let status = if diagnostic.is_some() {
+        Status::Leftovers
+    } else if findings.iter().any(|finding| finding.level == lint::Level::Error) {
+        Status::Findings
+    } else {
+        Status::Parsed
+    };
+
+    Ok(Linted { status, output, results, diagnostic })
+}
+
+/// Load a lint configuration from a TOML file, e.g.:
+///
+/// ```toml
+/// namespaces = ["core", "core-embed", "acme"]
+/// maximum-attributes-length = 4096
+/// maximum-depth = 8
+///
+/// [levels]
+/// unknown-namespace = "error"
+/// empty-paragraph = "off"
+///
+/// [deprecated-names]
+/// "acme/old-gallery" = "acme/gallery"
+/// "acme/marquee" = ""
+/// ```
+///
+/// Omitted keys keep their default value; deprecated names are added
+/// to the default ones, and an empty replacement means there is none.
+fn load_lint_config(path: &Path) -> Result<lint::Config, Error> {
+    let content = fs::read_to_string(path).with_context(|_| format!("Cannot open or read the lint configuration `{}`.", path.display()))?;
+    let table: toml::value::Table = toml::from_str(&content).with_context(|_| format!("The lint configuration `{}` is invalid.", path.display()))?;
+    let mut config = lint::Config::default();
+
+    for (key, value) in table {
+        match (key.as_str(), value) {
+            ("levels", toml::Value::Table(levels)) => {
+                for (rule_name, level) in levels {
+                    let rule = lint::Rule::from_name(&rule_name).ok_or_else(|| format_err!("The lint rule `{}` does not exist.", rule_name))?;
+                    let level =
+                        level
+                            .as_str()
+                            .and_then(lint::Level::from_name)
+                            .ok_or_else(|| format_err!("The level of `{}` must be `off`, `warning` or `error`.", rule_name))?;
+
+                    config.set_level(rule, level);
+                }
+            },
+
+            ("deprecated-names", toml::Value::Table(names)) => {
+                for (name, replacement) in names {
+                    let replacement = replacement.as_str().ok_or_else(|| format_err!("The replacement of `{}` must be a string.", name))?;
+
+                    config.deprecated_names.retain(|(deprecated_name, _)| *deprecated_name != name);
+                    config.deprecated_names.push((name, if replacement.is_empty() { None } else { Some(replacement.to_string()) }));
+                }
+            },
+
+            ("namespaces", toml::Value::Array(namespaces)) => {
+                config.namespaces =
+                    namespaces
+                        .iter()
+                        .map(|namespace| namespace.as_str().map(String::from).ok_or_else(|| format_err!("The namespaces must be strings.")))
+                        .collect::<Result<_, _>>()?;
+            },
+
+            ("maximum-attributes-length", toml::Value::Integer(length)) if length >= 0 => config.maximum_attributes_length = length as usize,
+
+            ("maximum-depth", toml::Value::Integer(depth)) if depth >= 0 => config.maximum_depth = depth as usize,
+
+            (key, _) => bail!("The lint configuration key `{}` is unknown, or its value is invalid.", key)
+        }
+    }
+
+    Ok(config)
+}
+
+/// The SARIF level of a lint level.
+fn sarif_level(level: lint::Level) -> &'static str {
+    match level {
+        lint::Level::Off => "none",
+        lint::Level::Warning => "warning",
+        lint::Level::Error => "error"
+    }
+}
+
+/// Represent a finding as a SARIF result. Columns are counted in
+/// Unicode code points, see `columnKind` in `sarif_log`.
+fn sarif_result(source: &str, content: &str, finding: &lint::Finding) -> Value {
+    let start = Location::of(content, finding.span.start);
+    let end = Location::of(content, finding.span.end);
+
+    json!({
+        "ruleId": finding.rule.as_str(),
+        "ruleIndex": finding.rule as usize,
+        "level": sarif_level(finding.level),
+        "message": { "text": finding.message },
+        "locations": [{
+            "physicalLocation": {
+                "artifactLocation": { "uri": source },
+                "region": {
+                    "startLine": start.line,
+                    "startColumn": start.column,
+                    "endLine": end.line,
+                    "endColumn": end.column,
+                    "byteOffset": finding.span.start,
+                    "byteLength": finding.span.end - finding.span.start
+                }
+            }
+        }]
+    })
+}
+
+/// Build a SARIF 2.1.0 log, with a single run.
+fn sarif_log(config: &lint::Config, results: Vec<Value>) -> Value {
+    let rules: Vec<Value> =
+        lint::RULES
+            .iter()
+            .map(|rule| {
+                json!({
+                    "id": rule.as_str(),
+                    "shortDescription": { "text": rule.description() },
+                    "defaultConfiguration": { "level": sarif_level(config.level(*rule)) }
+                })
+            })
+            .collect();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "gutenberg-post-parser",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/Hywan/gutenberg-parser-rs",
+                    "rules": rules
+                }
+            },
+            "columnKind": "unicodeCodePoints",
+            "results": results
+        }]
+    })
+}
+
+/// Granularity of the modification times, at worst: 2 seconds on
+/// FAT file systems.
+const MODIFICATION_TIME_GRANULARITY: Duration = Duration::from_secs(2);
+
+/// Stamp of a watched file: a file has changed when its stamp has
+/// changed.
+#[derive(Clone, PartialEq)]
+struct Stamp {
+    modified: SystemTime,
+    length: u64,
+
+    /// Hash of the content.
+    hash: u64
+}
+
+impl Stamp {
+    /// Stamp a file. The content is hashed, unless the modification
+    /// time and the length are the ones of the `previous` stamp, and
+    /// the modification time is older than `previous_scan` by more than
+    /// the granularity of the modification times: A file rewritten
+    /// with the same length within the same tick keeps its
+    /// modification time, but not its hash.
+    fn new(path: &Path, previous: Option<&Stamp>, previous_scan: SystemTime) -> io::Result<Stamp> {
+        let metadata = fs::metadata(path)?;
+        let modified = metadata.modified()?;
+        let length = metadata.len();
+
+        if let Some(previous) = previous {
+            if previous.modified == modified && previous.length == length && modified + MODIFICATION_TIME_GRANULARITY < previous_scan {
+                return Ok(previous.clone());
+            }
+        }
+
+        let mut hasher = DefaultHasher::new();
+        fs::read(path)?.hash(&mut hasher);
+
+        Ok(Stamp { modified, length, hash: hasher.finish() })
+    }
+}
+
+/// Watch files or directories, see the `watch` subcommand. Changes
+/// are detected by polling the modification time and the length of
+/// the files, which works everywhere without any service, and by
+/// comparing the hash of their content when the modification time is
+/// too recent to be trusted, see `Stamp::new`. Each changed file is
+/// parsed again, and written like in batch mode; the diagnostics are
+/// written on `stderr`. It never returns, except if the arguments are
+/// invalid.
+fn watch(matches: &ArgMatches, settings: &Settings) -> Result<Status, Error> {
+    let interval = matches.value_of("interval").unwrap_or("500").parse::<u64>().context("The interval must be a positive integer.")?;
+    let interval = Duration::from_millis(interval);
+    let inputs: Vec<&str> = matches.values_of("INPUT").map(|values| values.collect()).unwrap_or_default();
+    let mut stamps: BTreeMap<PathBuf, Stamp> = BTreeMap::new();
+    let mut previous_scan = SystemTime::UNIX_EPOCH;
+
+    loop {
+        let start = Instant::now();
+        let scan = SystemTime::now();
+        let stdout = io::stdout();
+        let mut lock = stdout.lock();
+        let stderr = io::stderr();
+        let mut error_lock = stderr.lock();
+        let mut paths = vec![];
+
+        for input in &inputs {
+            if let Err(error) = collect_files(Path::new(input), settings.extension, &mut paths) {
+                write_failure(&mut error_lock, input, &Error::from(error), settings.diagnostics_format)?;
+            }
+        }
+
+        let mut new_stamps = BTreeMap::new();
+
+        for path in paths {
+            if let Ok(stamp) = Stamp::new(&path, stamps.get(&path), previous_scan) {
+                new_stamps.insert(path, stamp);
+            }
+        }
+
+        for path in stamps.keys().filter(|path| !new_stamps.contains_key(*path)) {
+            writeln!(error_lock, "{}: removed.", path.display())?;
+        }
+
+        let changed: Vec<&PathBuf> =
+            new_stamps
+                .iter()
+                .filter(|(path, stamp)| stamps.get(*path) != Some(stamp))
+                .map(|(path, _)| path)
+                .collect();
+
+        if !changed.is_empty() {
+            let mut summary = Summary::default();
+
+            for path in changed {
+                match parse_file(path, settings) {
+                    Ok(parsed) => {
+                        summary.record(parsed.status);
+                        lock.write_all(&parsed.output)?;
+                        lock.flush()?;
+
+                        if let Some(diagnostic) = parsed.diagnostic {
+                            error_lock.write_all(&diagnostic)?;
+                        }
+                    },
+
+                    Err(error) => {
+                        summary.record(Status::Failed);
+                        write_failure(&mut error_lock, &path.to_string_lossy(), &error, settings.diagnostics_format)?;
+                    }
+                }
+            }
+
+            summary.report(start);
+        }
+
+        stamps = new_stamps;
+        previous_scan = scan;
+
+        thread::sleep(interval);
+    }
+}
+
+/// Parse NDJSON streams, from the given files or from `stdin`. Each
+/// line is a JSON object representing a post, with a `post_content`
+/// field, and optionally an `ID` field. One object is written per line
+/// and per post, either `{"line":…,"id":…,"blocks":[…]}` or
+/// `{"line":…,"id":…,"error":…}`, in the order of the input. A post
+/// with leftovers also has a `diagnostic` object.
+fn parse_ndjson(inputs: &[&str]) -> Result<Status, Error> {
+    let start = Instant::now();
+    let stdout = io::stdout();
+    let mut lock = stdout.lock();
+    let mut summary = Summary::default();
+
+    if inputs.is_empty() {
+        let stdin = io::stdin();
+
+        parse_ndjson_stream(stdin.lock(), &mut lock, &mut summary).context("Cannot read from `stdin`.")?;
+    } else {
+        for input in inputs {
+            let file = fs::File::open(input).with_context(|_| format!("Cannot open `{}`.", input))?;
+
+            parse_ndjson_stream(BufReader::new(file), &mut lock, &mut summary).with_context(|_| format!("Cannot read `{}`.", input))?;
+        }
+    }
+
+    lock.flush()?;
+
+    Ok(summary.report(start))
+}
+
+fn parse_ndjson_stream<R: BufRead, W: Write>(mut reader: R, writer: &mut W, summary: &mut Summary) -> Result<(), Error> {
+    let mut line_number = 0;
+
+    loop {
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        let mut number_of_lines = 0;
+
+        // Lines are read as bytes, so that a line that is not valid
+        // UTF-8 is reported as an error of its own.
+        while number_of_lines < BATCH_SIZE {
+            let mut line = Vec::new();
+
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                break;
+            }
+
+            number_of_lines += 1;
+            line_number += 1;
+
+            if line.ends_with(b"\n") {
+                line.pop();
+
+                if line.ends_with(b"\r") {
+                    line.pop();
+                }
+            }
+
+            if !line.iter().all(u8::is_ascii_whitespace) {
+                batch.push((line_number, line));
+            }
+        }
+
+        if number_of_lines == 0 {
+            break;
+        }
+
+        let outcomes: Vec<(Value, Result<Parsed, Error>)> =
+            batch
+                .par_iter()
+                .map(|(line_number, line)| parse_post(*line_number, line))
+                .collect();
+
+        for ((line_number, _), (id, outcome)) in batch.iter().zip(outcomes) {
+            match outcome {
+                Ok(parsed) => {
+                    summary.record(parsed.status);
+                    writer.write_all(&parsed.output)?;
+                },
+
+                Err(error) => {
+                    summary.record(Status::Failed);
+
+                    writeln!(
+                        writer,
+                        "{{\"line\":{},\"id\":{},\"error\":{}}}",
+                        line_number,
+                        id,
+                        Value::from(error.to_string())
+                    )?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse one NDJSON line. The post ID is returned alongside the
+/// outcome so that errors can be reported against it.
+fn parse_post(line_number: usize, line: &[u8]) -> (Value, Result<Parsed, Error>) {
+    let line = match str::from_utf8(line) {
+        Ok(line) => line,
+        Err(error) => return (Value::Null, Err(format_err!("Invalid UTF-8: {}.", error)))
+    };
+
+    let post: Value = match serde_json::from_str(line) {
+        Ok(post) => post,
+        Err(error) => return (Value::Null, Err(format_err!("Invalid JSON: {}.", error)))
+    };
+
+    let id = post.get("ID").cloned().unwrap_or(Value::Null);
+
+    let content = match post.get("post_content").and_then(Value::as_str) {
+        Some(content) => content,
+        None => return (id, Err(format_err!("The `post_content` field is missing or is not a string.")))
+    };
+
+    let outcome = parse_post_content(line_number, &id, content);
+
+    (id, outcome)
+}
+
+fn parse_post_content(line_number: usize, id: &Value, content: &str) -> Result<Parsed, Error> {
+    let (remaining, nodes) = root(content.as_bytes()).map_err(|_| format_err!("Failed to parse the datum."))?;
+    let diagnostic = error::diagnose(content.as_bytes(), remaining);
+    let mut output = vec![];
+
+    write!(output, "{{\"line\":{},\"id\":{},\"blocks\":", line_number, id)?;
+    serialize_nodes_to_json(&mut output, output::recover(content.as_bytes(), remaining, nodes)).context("Failed to serialize parser output to JSON.")?;
+
+    let status = match diagnostic {
+        Some(error) => {
+            write!(output, ",\"diagnostic\":{}", diagnostic_to_json(content, &error))?;
+
+            Status::Leftovers
+        },
+
+        None => Status::Parsed
+    };
+
+    output.write_all(&b"}\n"[..])?;
+
+    Ok(
+        Parsed {
+            status,
+            output,
+            diagnostic: None
+        }
+    )
+}
+
+/// Write the AST of a datum in the requested format.
+fn write_nodes<W: Write>(writer: &mut W, content: &str, remaining: Input, nodes: Vec<Node>, settings: &Settings) -> Result<(), Error> {
+    match settings.emit {
+        Emit::Json => {
+            serialize_nodes_to_json(writer, output::recover(content.as_bytes(), remaining, nodes)).context("Failed to serialize parser output to JSON.")?;
+        },
+
+        Emit::Debug => {
+            let debug: nom::IResult<Input, Vec<Node>> = Ok((remaining, nodes));
+
+            write!(writer, "{:?}", debug)?;
+        },
+
+        Emit::Tree => {
+            write_tree(writer, content.as_bytes(), &nodes, "", 1, settings)?;
+        },
+
+        Emit::Cbor | Emit::MessagePack | Emit::Yaml => {
+            write_value(writer, &nodes_to_value(&output::recover(content.as_bytes(), remaining, nodes)), settings.emit)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Serialize a value into CBOR, MessagePack, or YAML.
+fn write_value<W: Write>(writer: &mut W, value: &Value, emit: Emit) -> Result<(), Error> {
+    match emit {
+        Emit::Cbor => serde_cbor::to_writer(writer, value).context("Failed to serialize parser output to CBOR.")?,
+        Emit::MessagePack => rmp_serde::encode::write(writer, value).context("Failed to serialize parser output to MessagePack.")?,
+
+        Emit::Yaml => {
+            serde_yaml::to_writer(&mut *writer, value).context("Failed to serialize parser output to YAML.")?;
+            writeln!(writer)?;
+        },
+
+        _ => unreachable!()
+    }
+
+    Ok(())
+}
+
+/// Represent the nodes with the same structure as the JSON output, so
+/// that they can be serialized into other formats, see the
+/// `gutenberg_post_parser::output` module.
+fn nodes_to_value(nodes: &[Node]) -> Value {
+    blocks_to_value(&output::blocks(nodes))
+}
+
+fn blocks_to_value(blocks: &[output::Block]) -> Value {
+    Value::Array(blocks.iter().map(block_to_value).collect())
+}
+
+fn block_to_value(block: &output::Block) -> Value {
+    let inner_content: Vec<Value> =
+        block
+            .inner_content
+            .iter()
+            .map(|item| match item {
+                Some(phrase) => Value::from(String::from_utf8_lossy(phrase).into_owned()),
+                None => Value::Null
+            })
+            .collect();
+
+    json!({
+        "blockName": block.name.map(|name| name.fully_qualified()),
+        "attrs": match block.attributes {
+            output::Attributes::Empty => json!({}),
+            output::Attributes::Value(_, ref value) => json_to_value(value),
+            output::Attributes::Invalid(..) => Value::Null
+        },
+        "innerBlocks": blocks_to_value(&block.inner_blocks),
+        "innerHTML": String::from_utf8_lossy(&block.inner_html()),
+        "innerContent": inner_content
+    })
+}
+
+fn json_to_value(value: &json::Value) -> Value {
+    match value {
+        json::Value::Null => Value::Null,
+        json::Value::Boolean(boolean) => Value::from(*boolean),
+
+        // A number out of the range of `f64`, e.g. `1e400`, is infinite,
+        // like with `JSON.parse`, and is written `null`, like
+        // `JSON.stringify` does, since JSON has no infinity.
+        json::Value::Number(number) => {
+            let number = String::from_utf8_lossy(number);
+
+            serde_json::from_str(&number).unwrap_or_else(|_| {
+                number
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(serde_json::Number::from_f64)
+                    .map_or(Value::Null, Value::Number)
+            })
+        },
+
+        json::Value::String(string) => Value::from(string.as_str()),
+        json::Value::Array(items) => Value::Array(items.iter().map(json_to_value).collect()),
+
+        // The last duplicated member wins.
+        json::Value::Object(members) => {
+            let mut object = serde_json::Map::new();
+
+            for (key, value) in members {
+                object.insert(key.clone(), json_to_value(value));
+            }
+
+            Value::Object(object)
+        }
+    }
+}
+
+/// Write the nodes as an indented outline, e.g.:
+///
+/// ```text
+/// core/columns {"columns":2}
+/// ├─ core/paragraph
+/// │  └─ phrase (12 bytes) "Hello, World"
+/// └─ phrase (1 bytes) ""
+/// ```
+fn write_tree<W: Write>(writer: &mut W, input: Input, nodes: &[Node], prefix: &str, depth: usize, settings: &Settings) -> io::Result<()> {
+    let style = |style: &'static str| if settings.color { style } else { "" };
+
+    for (index, node) in nodes.iter().enumerate() {
+        let (branch, continuation) =
+            if depth == 1 {
+                ("", "")
+            } else if index == nodes.len() - 1 {
+                ("└─ ", "   ")
+            } else {
+                ("├─ ", "│  ")
+            };
+
+        write!(writer, "{}{}", prefix, branch)?;
+
+        match node {
+            Node::Block { name, attributes, .. } => {
+                write!(writer, "{}{}{}", style(STYLE_BLOCK_NAME), name.fully_qualified(), style(STYLE_RESET))?;
+
+                if let Some(attributes) = attributes {
+                    write!(writer, " {}{}{}", style(STYLE_ATTRIBUTES), abbreviate(attributes), style(STYLE_RESET))?;
+                }
+            },
+
+            Node::Phrase(phrase) => {
+                write!(
+                    writer,
+                    "{}phrase{} ({} bytes) {}",
+                    style(STYLE_PHRASE),
+                    style(STYLE_RESET),
+                    phrase.len(),
+                    Value::from(abbreviate(phrase))
+                )?;
+            }
+        }
+
+        if settings.spans {
+            let span = node.span(input);
+
+            write!(writer, " {}[{}..{}]{}", style(STYLE_DIMMED), span.start, span.end, style(STYLE_RESET))?;
+        }
+
+        let is_collapsed = match settings.max_depth {
+            Some(max_depth) => depth >= max_depth,
+            None => false
+        };
+
+        match node {
+            Node::Block { ref children, .. } if is_collapsed && !children.is_empty() => {
+                writeln!(writer, " {}… {} inner node(s){}", style(STYLE_DIMMED), children.len(), style(STYLE_RESET))?;
+            },
+
+            Node::Block { ref children, .. } => {
+                writeln!(writer)?;
+                write_tree(writer, input, children, &format!("{}{}", prefix, continuation), depth + 1, settings)?;
+            },
+
+            Node::Phrase(_) => writeln!(writer)?
+        }
+    }
+
+    Ok(())
+}
+
+/// Abbreviate a slice of the input for a tree: Whitespaces are
+/// collapsed, and the result is truncated.
+fn abbreviate(input: Input) -> String {
+    let text = String::from_utf8_lossy(input);
+    let mut abbreviation = String::new();
+
+    for (index, word) in text.split_whitespace().enumerate() {
+        if index > 0 {
+            abbreviation.push(' ');
+        }
+
+        abbreviation.push_str(word);
+
+        if abbreviation.chars().count() > TREE_PREVIEW_LENGTH {
+            abbreviation = abbreviation.chars().take(TREE_PREVIEW_LENGTH).collect();
+            abbreviation.push('…');
+
+            break;
+        }
+    }
+
+    abbreviation
+}
+
+fn serialize_nodes_to_json<W: Write>(mut writer: W, nodes: Vec<Node>) -> Result<(), Error> {
+    writer.write_all(output::serialize(&output::blocks(&nodes)).as_bytes())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ndjson_stream_reports_invalid_utf8_lines() {
+        let input = &b"{\"ID\":1,\"post_content\":\"a\"}\n{\"ID\":2,\"post_content\":\"\xff\"}\n\n{\"ID\":3,\"post_content\":\"b\"}\r\n"[..];
+        let mut output = vec![];
+        let mut summary = Summary::default();
+
+        parse_ndjson_stream(input, &mut output, &mut summary).unwrap();
+
+        let lines: Vec<Value> =
+            str::from_utf8(&output)
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["line"], 1);
+        assert_eq!(lines[0]["id"], 1);
+        assert_eq!(lines[1]["line"], 2);
+        assert!(lines[1]["error"].as_str().unwrap().starts_with("Invalid UTF-8: "));
+        assert_eq!(lines[2]["line"], 4);
+        assert_eq!(lines[2]["id"], 3);
+        assert_eq!((summary.parsed, summary.failed), (2, 1));
+    }
+
+    #[test]
+    fn test_value_of_numbers_out_of_range_is_a_number_or_null() {
+        let input = &b"{\"a\":1e400,\"b\":-1e400,\"c\":123456789012345678901234567890,\"d\":1.5}"[..];
+
+        let value = json_to_value(&json::parse(input).unwrap());
+
+        assert_eq!(value["a"], Value::Null);
+        assert_eq!(value["b"], Value::Null);
+        assert_eq!(value["c"].as_f64(), Some(123456789012345678901234567890.0));
+        assert_eq!(value["d"].as_f64(), Some(1.5));
+    }
+
+    #[test]
+    fn test_value_keeps_the_order_of_the_json_output() {
+        let input = &b"<!-- wp:foo {\"b\":1,\"a\":{\"d\":2,\"c\":3},\"b\":4} /-->"[..];
+        let (_remaining, nodes) = root(input).unwrap();
+
+        assert_eq!(
+            nodes_to_value(&nodes).to_string(),
+            output::serialize(&output::blocks(&nodes))
+        );
+    }
 }
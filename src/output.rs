@@ -0,0 +1,593 @@
+/*!
+
+The canonical output model.
+
+The binary and the bindings expose the AST with the same structure:
+The blocks returned by `parse` from the
+[`@wordpress/block-serialization-default-parser`][gutenberg] package.
+A block has a name (`blockName`), attributes (`attrs`), inner blocks
+(`innerBlocks`), the concatenation of its phrases (`innerHTML`), and
+its phrases interleaved with a `null` for each inner block
+(`innerContent`). This module defines this model once, so that each
+binding maps it to its own values instead of building its own
+structure. The JSON fixtures of the tests, in `tests/fixtures`, are
+this model serialized to JSON.
+
+The rules are:
+
+  * A phrase at the top-level is a block without a name (a freeform
+    block), and without attributes,
+  * A block without attributes has an empty object as attributes,
+  * Attributes that are not valid JSON are `null` when serialized,
+    like with Gutenberg.
+
+Attributes are also kept as written in the input, so that bindings
+can report invalid ones, or expose them as is.
+
+Like `parse` from Gutenberg, and `WP_Block_Parser::parse` from
+WordPress, the model is defined for any input: [`parse`](./fn.parse.html)
+never fails. The input that [`root`](../fn.root.html) cannot parse is
+recovered like WordPress does, see [`recover`](./fn.recover.html).
+
+# Examples
+
+```
+extern crate gutenberg_post_parser;
+
+use gutenberg_post_parser::{root, output};
+
+let input = &b"<!-- wp:foo {\"bar\":true} -->a<!-- wp:baz /-->b<!-- /wp:foo -->"[..];
+let (_remaining, nodes) = root(input).unwrap();
+
+assert_eq!(
+    output::serialize(&output::blocks(&nodes)),
+    "[{\"blockName\":\"core/foo\",\"attrs\":{\"bar\":true},\
+      \"innerBlocks\":[{\"blockName\":\"core/baz\",\"attrs\":{},\"innerBlocks\":[],\"innerHTML\":\"\",\"innerContent\":[]}],\
+      \"innerHTML\":\"ab\",\"innerContent\":[\"a\",null,\"b\"]}]"
+);
+```
+
+[gutenberg]: https://www.npmjs.com/package/@wordpress/block-serialization-default-parser
+
+*/
+
+use super::{Input, ParseOptions};
+use super::ast::{BlockName, Node};
+use super::json;
+use super::parser::{self, Delimiter};
+use super::scanner;
+use std::string::String;
+use std::vec::Vec;
+
+/// Represent a block of the canonical output model.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(not(feature = "no_std"), derive(Debug))]
+pub struct Block<'a> {
+    /// The block name, or `None` for a freeform block, i.e. a
+    /// top-level phrase.
+    pub name: Option<BlockName<'a>>,
+
+    /// The attributes.
+    pub attributes: Attributes<'a>,
+
+    /// The inner blocks.
+    pub inner_blocks: Vec<Block<'a>>,
+
+    /// The phrases, and `None` where the inner blocks are.
+    pub inner_content: Vec<Option<Input<'a>>>
+}
+
+/// Represent the attributes of a block.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(not(feature = "no_std"), derive(Debug))]
+pub enum Attributes<'a> {
+    /// The block has no attributes, i.e. an empty object.
+    Empty,
+
+    /// Valid attributes, as written in the input, and decoded.
+    Value(Input<'a>, json::Value<'a>),
+
+    /// Attributes that are not valid JSON, as written in the input,
+    /// with the reason.
+    Invalid(Input<'a>, json::Error)
+}
+
+impl<'a> Attributes<'a> {
+    /// The attributes as written in the input, if any.
+    pub fn raw(&self) -> Option<Input<'a>> {
+        match *self {
+            Attributes::Empty => None,
+            Attributes::Value(raw, _) | Attributes::Invalid(raw, _) => Some(raw)
+        }
+    }
+}
+
+impl<'a> Block<'a> {
+    /// Map a node of the AST to a block.
+    pub fn from_node(node: &Node<'a>) -> Block<'a> {
+        match node {
+            Node::Block { name, attributes, children } => {
+                let attributes = match attributes {
+                    Some(attributes) => match json::parse(attributes) {
+                        Ok(value) => Attributes::Value(attributes, value),
+                        Err(error) => Attributes::Invalid(attributes, error)
+                    },
+
+                    None => Attributes::Empty
+                };
+
+                let mut inner_blocks = Vec::new();
+                let mut inner_content = Vec::with_capacity(children.len());
+
+                for child in children {
+                    match child {
+                        Node::Block { .. } => {
+                            inner_blocks.push(Block::from_node(child));
+                            inner_content.push(None);
+                        },
+
+                        Node::Phrase(phrase) => inner_content.push(Some(*phrase))
+                    }
+                }
+
+                Block {
+                    name: Some(*name),
+                    attributes,
+                    inner_blocks,
+                    inner_content
+                }
+            },
+
+            Node::Phrase(phrase) => Block {
+                name: None,
+                attributes: Attributes::Empty,
+                inner_blocks: Vec::new(),
+                inner_content: vec![Some(*phrase)]
+            }
+        }
+    }
+
+    /// The concatenation of the phrases, i.e. `innerHTML`.
+    pub fn inner_html(&self) -> Vec<u8> {
+        let mut inner_html = Vec::new();
+
+        for phrase in self.inner_content.iter().filter_map(|item| *item) {
+            inner_html.extend_from_slice(phrase);
+        }
+
+        inner_html
+    }
+}
+
+/// Map the nodes of the AST to blocks.
+pub fn blocks<'a>(nodes: &[Node<'a>]) -> Vec<Block<'a>> {
+    nodes.iter().map(Block::from_node).collect()
+}
+
+/// Parse `input` entirely, with the default options: The input that
+/// [`root`](../fn.root.html) cannot parse is recovered, see
+/// [`recover`](./fn.recover.html).
+///
+/// # Examples
+///
+/// ```
+/// extern crate gutenberg_post_parser;
+///
+/// use gutenberg_post_parser::{ast::{BlockName, Node}, output};
+///
+/// assert_eq!(
+///     output::parse(&b"a<!-- wp:foo -->b"[..]),
+///     vec![
+///         Node::Phrase(&b"a"[..]),
+///         Node::Block {
+///             name: BlockName::implicit(&b"core"[..], &b"foo"[..]),
+///             attributes: None,
+///             children: vec![Node::Phrase(&b"b"[..])]
+///         }
+///     ]
+/// );
+/// ```
+pub fn parse(input: Input) -> Vec<Node> {
+    parse_with_options(input, &ParseOptions::default())
+}
+
+/// Parse `input` entirely, with specific parse options. See
+/// [`parse`](./fn.parse.html).
+pub fn parse_with_options<'a>(input: Input<'a>, options: &ParseOptions<'a>) -> Vec<Node<'a>> {
+    let (remaining, nodes) = parser::block_list_with_options(input, options).unwrap_or_else(|_| (input, Vec::new()));
+
+    recover_with_options(input, remaining, nodes, options)
+}
+
+/// Recover the `remaining` data that [`root`](../fn.root.html) has not
+/// parsed, and append it to the `nodes` it has returned, with the
+/// default options.
+///
+/// The rest of the input is parsed like `WP_Block_Parser` does, from
+/// the last top-level phrase, delimiter after delimiter, where a
+/// comment that is not a delimiter is a phrase:
+///
+///   * A block that is not closed at the end of the input is closed
+///     there, and so are its open parents, which are then top-level
+///     blocks, from the innermost to the outermost,
+///   * A closing delimiter closes the innermost open block, whatever
+///     its name,
+///   * A closing delimiter without any open block makes the rest of
+///     the input a phrase.
+///
+/// The phrases of the recovered blocks can overlap: The phrase of a
+/// parent, closed at the end of the input, includes its children.
+pub fn recover<'a>(input: Input<'a>, remaining: Input<'a>, nodes: Vec<Node<'a>>) -> Vec<Node<'a>> {
+    recover_with_options(input, remaining, nodes, &ParseOptions::default())
+}
+
+/// Recover with specific parse options. See
+/// [`recover`](./fn.recover.html).
+pub fn recover_with_options<'a>(input: Input<'a>, remaining: Input<'a>, mut nodes: Vec<Node<'a>>, options: &ParseOptions<'a>) -> Vec<Node<'a>> {
+    if remaining.is_empty() {
+        return nodes;
+    }
+
+    let mut offset = input.len() - remaining.len();
+
+    // Like `WP_Block_Parser`, the phrase before the rejected data is
+    // recovered with it, e.g. as a single phrase.
+    if let Some(&Node::Phrase(phrase)) = nodes.last() {
+        offset -= phrase.len();
+        nodes.pop();
+    }
+
+    let mut recovery = Recovery {
+        input,
+        options,
+        offset,
+        stack: Vec::new(),
+        output: nodes
+    };
+
+    recovery.run();
+
+    recovery.output
+}
+
+/// A block that is open during the recovery, like a
+/// `WP_Block_Parser_Frame`.
+struct Frame<'a> {
+    name: BlockName<'a>,
+    attributes: Option<Input<'a>>,
+    children: Vec<Node<'a>>,
+
+    /// The offset of the opening delimiter.
+    token_start: usize,
+
+    /// The offset from which the next phrase of the block starts.
+    previous_offset: usize,
+
+    /// The offset of the phrase before the opening delimiter, if any.
+    leading_phrase_start: Option<usize>
+}
+
+impl<'a> Frame<'a> {
+    fn into_node(self) -> Node<'a> {
+        Node::Block {
+            name: self.name,
+            attributes: self.attributes,
+            children: self.children
+        }
+    }
+}
+
+/// The state of the recovery, like `WP_Block_Parser`.
+struct Recovery<'a, 'b> {
+    input: Input<'a>,
+    options: &'b ParseOptions<'a>,
+    offset: usize,
+    stack: Vec<Frame<'a>>,
+    output: Vec<Node<'a>>
+}
+
+impl<'a, 'b> Recovery<'a, 'b> {
+    fn run(&mut self) {
+        loop {
+            let (start, end, delimiter) = match self.next_delimiter() {
+                Some(token) => token,
+
+                None => {
+                    if self.stack.is_empty() {
+                        self.add_phrase(self.offset, self.input.len());
+                    }
+
+                    while !self.stack.is_empty() {
+                        self.add_block_from_stack(None);
+                    }
+
+                    return;
+                }
+            };
+
+            let leading_phrase_start = if start > self.offset { Some(self.offset) } else { None };
+
+            match delimiter {
+                Delimiter::Void { name, attributes } => {
+                    let block = Node::Block { name, attributes, children: Vec::new() };
+
+                    if self.stack.is_empty() {
+                        if let Some(leading_phrase_start) = leading_phrase_start {
+                            self.add_phrase(leading_phrase_start, start);
+                        }
+
+                        self.output.push(block);
+                    } else {
+                        self.add_inner_block(block, start, end);
+                    }
+                },
+
+                Delimiter::Opening { name, attributes } => {
+                    self.stack.push(
+                        Frame {
+                            name,
+                            attributes,
+                            children: Vec::new(),
+                            token_start: start,
+                            previous_offset: end,
+                            leading_phrase_start
+                        }
+                    );
+                },
+
+                Delimiter::Closing { .. } => {
+                    match self.stack.len() {
+                        0 => {
+                            self.add_phrase(self.offset, self.input.len());
+
+                            return;
+                        },
+
+                        1 => self.add_block_from_stack(Some(start)),
+
+                        _ => {
+                            let mut frame = self.stack.pop().unwrap();
+                            let token_start = frame.token_start;
+
+                            push_phrase(&mut frame.children, &self.input[frame.previous_offset..start]);
+                            self.add_inner_block(frame.into_node(), token_start, end);
+                        }
+                    }
+                }
+            }
+
+            self.offset = end;
+        }
+    }
+
+    /// Find the next delimiter from the current offset, and return its
+    /// start, its end, and itself. Comments that are not delimiters
+    /// are skipped.
+    fn next_delimiter(&self) -> Option<(usize, usize, Delimiter<'a>)> {
+        let mut offset = self.offset;
+
+        while let Some(next_offset) = scanner::find_comment_opening(&self.input[offset..]) {
+            let start = offset + next_offset;
+
+            match parser::delimiter_with_options(&self.input[start..], self.options) {
+                Ok((remaining, delimiter)) => return Some((start, self.input.len() - remaining.len(), delimiter)),
+                Err(_) => offset = start + 1
+            }
+        }
+
+        None
+    }
+
+    /// Add a top-level phrase, if not empty.
+    fn add_phrase(&mut self, start: usize, end: usize) {
+        push_phrase(&mut self.output, &self.input[start..end]);
+    }
+
+    /// Add `block` to the innermost open block, after the phrase
+    /// before `token_start`, and continue the phrases of this block
+    /// from `last_offset`.
+    fn add_inner_block(&mut self, block: Node<'a>, token_start: usize, last_offset: usize) {
+        let input = self.input;
+        let parent = self.stack.last_mut().unwrap();
+
+        push_phrase(&mut parent.children, &input[parent.previous_offset..token_start]);
+        parent.children.push(block);
+        parent.previous_offset = last_offset;
+    }
+
+    /// Close the innermost open block at `end_offset`, or at the end of
+    /// the input, and add it at the top-level, after the phrase before
+    /// it.
+    fn add_block_from_stack(&mut self, end_offset: Option<usize>) {
+        let mut frame = self.stack.pop().unwrap();
+        let end_offset = end_offset.unwrap_or(self.input.len());
+
+        push_phrase(&mut frame.children, &self.input[frame.previous_offset..end_offset]);
+
+        if let Some(leading_phrase_start) = frame.leading_phrase_start {
+            self.add_phrase(leading_phrase_start, frame.token_start);
+        }
+
+        self.output.push(frame.into_node());
+    }
+}
+
+fn push_phrase<'a>(nodes: &mut Vec<Node<'a>>, phrase: Input<'a>) {
+    if !phrase.is_empty() {
+        nodes.push(Node::Phrase(phrase));
+    }
+}
+
+/// Serialize blocks to JSON, without whitespaces. Members of the
+/// attributes are in the input order; a duplicated key has its last
+/// value, at the position of its first occurrence, like with
+/// `JSON.parse`. Invalid UTF-8 sequences in phrases are replaced by
+/// U+FFFD.
+pub fn serialize(blocks: &[Block]) -> String {
+    let mut output = String::new();
+
+    serialize_blocks(blocks, &mut output);
+
+    output
+}
+
+fn serialize_blocks(blocks: &[Block], output: &mut String) {
+    output.push('[');
+
+    for (index, block) in blocks.iter().enumerate() {
+        if index > 0 {
+            output.push(',');
+        }
+
+        serialize_block(block, output);
+    }
+
+    output.push(']');
+}
+
+fn serialize_block(block: &Block, output: &mut String) {
+    output.push_str("{\"blockName\":");
+
+    match block.name {
+        Some(name) => json::serialize_string(&name.fully_qualified(), json::Style::Plain, output),
+        None => output.push_str("null")
+    }
+
+    output.push_str(",\"attrs\":");
+
+    match block.attributes {
+        Attributes::Empty => output.push_str("{}"),
+        Attributes::Value(_, ref value) => json::serialize_value(value, json::Style::Plain, output),
+        Attributes::Invalid(..) => output.push_str("null")
+    }
+
+    output.push_str(",\"innerBlocks\":");
+    serialize_blocks(&block.inner_blocks, output);
+
+    output.push_str(",\"innerHTML\":");
+    json::serialize_string(&String::from_utf8_lossy(&block.inner_html()), json::Style::Plain, output);
+
+    output.push_str(",\"innerContent\":[");
+
+    for (index, item) in block.inner_content.iter().enumerate() {
+        if index > 0 {
+            output.push(',');
+        }
+
+        match item {
+            Some(phrase) => json::serialize_string(&String::from_utf8_lossy(phrase), json::Style::Plain, output),
+            None => output.push_str("null")
+        }
+    }
+
+    output.push_str("]}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::root;
+
+    fn block<'a>(name: &'a str, children: Vec<Node<'a>>) -> Node<'a> {
+        Node::Block {
+            name: BlockName::implicit(&b"core"[..], name.as_bytes()),
+            attributes: None,
+            children
+        }
+    }
+
+    fn phrase<'a>(phrase: &'a str) -> Node<'a> {
+        Node::Phrase(phrase.as_bytes())
+    }
+
+    fn serialize_root(input: Input) -> String {
+        let (_remaining, nodes) = root(input).unwrap();
+
+        serialize(&blocks(&nodes))
+    }
+
+    #[test]
+    fn test_freeform_block() {
+        assert_eq!(
+            serialize_root(b"a\"\n"),
+            "[{\"blockName\":null,\"attrs\":{},\"innerBlocks\":[],\"innerHTML\":\"a\\\"\\n\",\"innerContent\":[\"a\\\"\\n\"]}]"
+        );
+    }
+
+    #[test]
+    fn test_invalid_attributes() {
+        let input = &b"<!-- wp:foo {\"a\":} /-->"[..];
+        let (_remaining, nodes) = root(input).unwrap();
+        let blocks = blocks(&nodes);
+
+        assert_eq!(
+            blocks[0].attributes,
+            Attributes::Invalid(&b"{\"a\":}"[..], json::Error { kind: json::ErrorKind::UnexpectedByte, offset: 5 })
+        );
+        assert_eq!(
+            serialize(&blocks),
+            "[{\"blockName\":\"core/foo\",\"attrs\":null,\"innerBlocks\":[],\"innerHTML\":\"\",\"innerContent\":[]}]"
+        );
+    }
+
+    #[test]
+    fn test_duplicated_keys() {
+        assert_eq!(
+            serialize_root(b"<!-- wp:foo {\"b\":1,\"a\":2,\"b\":3} /-->"),
+            "[{\"blockName\":\"core/foo\",\"attrs\":{\"b\":3,\"a\":2},\"innerBlocks\":[],\"innerHTML\":\"\",\"innerContent\":[]}]"
+        );
+    }
+
+    #[test]
+    fn test_parse_without_recovery() {
+        let input = &b"a<!-- wp:foo -->b<!-- /wp:foo -->"[..];
+
+        assert_eq!(parse(input), root(input).unwrap().1);
+    }
+
+    #[test]
+    fn test_recover_an_unclosed_block() {
+        assert_eq!(
+            parse(b"<!-- wp:foo /-->a<!-- wp:bar -->b<!-- more -->"),
+            vec![block("foo", vec![]), phrase("a"), block("bar", vec![phrase("b<!-- more -->")])]
+        );
+    }
+
+    #[test]
+    fn test_recover_unclosed_nested_blocks() {
+        assert_eq!(
+            parse(b"<!-- wp:foo -->a<!-- wp:baz /-->b<!-- wp:bar -->c"),
+            vec![
+                phrase("b"),
+                block("bar", vec![phrase("c")]),
+                block("foo", vec![phrase("a"), block("baz", vec![]), phrase("b<!-- wp:bar -->c")])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recover_a_mismatched_closing_block() {
+        assert_eq!(
+            parse(b"<!-- wp:foo --><!-- wp:bar -->a<!-- /wp:baz -->b<!-- /wp:qux -->c"),
+            vec![block("foo", vec![block("bar", vec![phrase("a")]), phrase("b")]), phrase("c")]
+        );
+    }
+
+    #[test]
+    fn test_recover_an_unexpected_closing_block() {
+        assert_eq!(
+            parse(b"<!-- wp:foo /-->a<!-- /wp:bar -->b<!-- wp:baz /-->"),
+            vec![block("foo", vec![]), phrase("a<!-- /wp:bar -->b<!-- wp:baz /-->")]
+        );
+    }
+
+    #[test]
+    fn test_recover_with_options() {
+        let options = ParseOptions { delimiter_prefix: &b"acme"[..], .. ParseOptions::default() };
+
+        assert_eq!(
+            parse_with_options(b"<!-- acme:foo --><!-- wp:bar -->", &options),
+            vec![block("foo", vec![phrase("<!-- wp:bar -->")])]
+        );
+    }
+}
@@ -0,0 +1,215 @@
+/*!
+
+Parallel parsing of large documents.
+
+A document is split into chunks at safe split points, i.e. positions
+where a top-level block opens. Chunks are parsed in parallel with
+[`rayon`], and the results are stitched together. The output is
+strictly the same as the output of [`root`](../fn.root.html): If a
+chunk cannot be entirely parsed, for instance because the document is
+malformed, the rest of the document is parsed sequentially from this
+chunk.
+
+This module is available with the `parallel` feature, which is not
+compatible with the `no_std` feature.
+
+[`rayon`]: https://github.com/rayon-rs/rayon
+
+*/
+
+use super::{Input, ParseOptions};
+use super::ast::Node;
+use super::parser::{self, Delimiter};
+use super::scanner;
+use nom;
+use rayon::{self, prelude::*};
+use std::cmp;
+use std::vec::Vec;
+
+/// The minimum size of a chunk, in bytes. A document smaller than
+/// twice this size, or parsed with a single thread, is parsed
+/// sequentially.
+pub const MINIMUM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The `root` function is similar to [`root`](../fn.root.html), but
+/// parses chunks of the input in parallel.
+///
+/// # Examples
+///
+/// ```
+/// extern crate gutenberg_post_parser;
+///
+/// use gutenberg_post_parser::{parallel, root};
+///
+/// let input = &b"<!-- wp:foo /--><!-- wp:bar --><p>baz</p><!-- /wp:bar -->"[..];
+///
+/// assert_eq!(parallel::root(input), root(input));
+/// ```
+pub fn root(input: Input) -> Result<(Input, Vec<Node>), nom::Err<Input>> {
+    parse_with_options(input, &ParseOptions::default())
+}
+
+/// The `parse_with_options` function is similar to
+/// [`parse_with_options`](../fn.parse_with_options.html), but parses
+/// chunks of the input in parallel.
+pub fn parse_with_options<'a>(input: Input<'a>, options: &ParseOptions<'a>) -> Result<(Input<'a>, Vec<Node<'a>>), nom::Err<Input<'a>>> {
+    let number_of_threads = rayon::current_num_threads();
+
+    if number_of_threads < 2 {
+        return parser::block_list_with_options(input, options);
+    }
+
+    let chunk_size = cmp::max(
+        MINIMUM_CHUNK_SIZE,
+        input.len() / (number_of_threads * 4) + 1
+    );
+
+    parse_in_chunks(input, options, chunk_size)
+}
+
+/// Parse `input` in chunks of approximately `chunk_size` bytes.
+fn parse_in_chunks<'a>(input: Input<'a>, options: &ParseOptions<'a>, chunk_size: usize) -> Result<(Input<'a>, Vec<Node<'a>>), nom::Err<Input<'a>>> {
+    let split_points = split_points(input, options, chunk_size);
+
+    if split_points.is_empty() {
+        return parser::block_list_with_options(input, options);
+    }
+
+    let mut chunks = Vec::with_capacity(split_points.len() + 1);
+    let mut chunk_start = 0;
+
+    for split_point in split_points {
+        chunks.push(&input[chunk_start..split_point]);
+        chunk_start = split_point;
+    }
+
+    chunks.push(&input[chunk_start..]);
+
+    let results: Vec<_> =
+        chunks
+            .par_iter()
+            .map(|chunk| parser::block_list_with_options(chunk, options))
+            .collect();
+
+    let last_index = chunks.len() - 1;
+    let mut nodes = Vec::new();
+
+    for (index, (chunk, result)) in chunks.into_iter().zip(results).enumerate() {
+        match result {
+            Ok((remaining, chunk_nodes)) if index == last_index => {
+                nodes.extend(chunk_nodes);
+                nodes.shrink_to_fit();
+
+                return Ok((remaining, nodes));
+            },
+
+            Ok((&[], chunk_nodes)) => {
+                nodes.extend(chunk_nodes);
+            },
+
+            _ => {
+                // The chunk has not been entirely parsed. To get the
+                // same output as `root`, the rest of the input is
+                // parsed sequentially.
+                let chunk_offset = chunk.as_ptr() as usize - input.as_ptr() as usize;
+                let (remaining, rest_nodes) = parser::block_list_with_options(&input[chunk_offset..], options)?;
+
+                nodes.extend(rest_nodes);
+                nodes.shrink_to_fit();
+
+                return Ok((remaining, nodes));
+            }
+        }
+    }
+
+    unreachable!("The last chunk always returns.")
+}
+
+/// Find the split points of `input`, i.e. the offsets of top-level
+/// opening delimiters, such that chunks are at least `chunk_size`
+/// bytes long.
+///
+/// Only the delimiters are recognized, the nesting depth is tracked,
+/// and the rest of the input is skipped with the scanner.
+fn split_points<'a>(input: Input<'a>, options: &ParseOptions<'a>, chunk_size: usize) -> Vec<usize> {
+    let mut split_points = Vec::new();
+    let mut depth = 0usize;
+    let mut chunk_start = 0;
+    let mut offset = 0;
+
+    while let Some(next_offset) = scanner::find_comment_opening(&input[offset..]) {
+        let position = offset + next_offset;
+
+        match parser::delimiter_with_options(&input[position..], options) {
+            Ok((remaining, delimiter)) => {
+                match delimiter {
+                    Delimiter::Opening { .. } | Delimiter::Void { .. } => {
+                        if depth == 0 && position - chunk_start >= chunk_size {
+                            split_points.push(position);
+                            chunk_start = position;
+                        }
+
+                        if let Delimiter::Opening { .. } = delimiter {
+                            depth += 1;
+                        }
+                    },
+
+                    Delimiter::Closing { .. } => {
+                        depth = depth.saturating_sub(1);
+                    }
+                }
+
+                offset = input.len() - remaining.len();
+            },
+
+            Err(_) => {
+                offset = position + 1;
+            }
+        }
+    }
+
+    split_points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::root as sequential_root;
+
+    #[test]
+    fn test_split_points() {
+        let input = &b"a<!-- wp:foo --><!-- wp:bar /--><!-- /wp:foo -->b<!-- wp:baz /-->c<!-- wp:qux /-->"[..];
+
+        assert_eq!(split_points(input, &ParseOptions::default(), 1), vec![1, 49, 66]);
+        assert_eq!(split_points(input, &ParseOptions::default(), 50), vec![66]);
+        assert_eq!(split_points(input, &ParseOptions::default(), 100), vec![]);
+    }
+
+    #[test]
+    fn test_split_points_skip_regular_comments() {
+        let input = &b"<!-- more --><!-- wp:foo {\"a\": \"<!-- wp:bar /-->\"} --><!-- /wp:foo --><!-- wp:baz /-->"[..];
+
+        assert_eq!(split_points(input, &ParseOptions::default(), 1), vec![13, 70]);
+    }
+
+    #[test]
+    fn test_parse_in_chunks() {
+        let input = &b"a<!-- wp:foo --><!-- wp:bar /--><!-- /wp:foo -->b<!-- wp:baz /-->c<!-- wp:qux /-->"[..];
+
+        assert_eq!(parse_in_chunks(input, &ParseOptions::default(), 1), sequential_root(input));
+    }
+
+    #[test]
+    fn test_parse_in_chunks_with_a_malformed_chunk() {
+        let input = &b"<!-- wp:foo /--><!-- wp:bar --><!-- /wp:baz --><!-- wp:qux /-->"[..];
+
+        assert_eq!(parse_in_chunks(input, &ParseOptions::default(), 1), sequential_root(input));
+    }
+
+    #[test]
+    fn test_parse_in_chunks_with_remaining_data() {
+        let input = &b"<!-- wp:foo /--><!-- wp:bar -->x"[..];
+
+        assert_eq!(parse_in_chunks(input, &ParseOptions::default(), 1), sequential_root(input));
+    }
+}
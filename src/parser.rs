@@ -46,6 +46,10 @@ block_void =
     "<!--", [ wss ], "wp:", block_name, wss, block_attributes, [ wss ], "/-->" ;
 ```
 
+The `wp` delimiter prefix can be changed with
+[`ParseOptions::delimiter_prefix`](../struct.ParseOptions.html), e.g. to
+`acme` to recognize `<!-- acme:foo /-->`.
+
 ## `block_name`
 
 A block name is a pair composed of a namespace, and a name. The
@@ -73,6 +77,21 @@ digit =
     "0" | "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9" ;
 ```
 
+The default namespace can be changed with
+[`ParseOptions::default_namespace`](../struct.ParseOptions.html). With
+[`ParseOptions::lenient_names`](../struct.ParseOptions.html), a block
+name part also accepts uppercase letters, and can start with a digit:
+
+```ebnf
+lenient_block_name_part =
+    ( letter | uppercase_letter | digit ), { letter | uppercase_letter | digit | "_" | "-" } ;
+
+uppercase_letter =
+    "A" | "B" | "C" | "D" | "E" | "F" | "G" | "H" | "I" | "J" | "K" |
+    "L" | "M" | "N" | "O" | "P" | "Q" | "R" | "S" | "T" | "U" | "V" |
+    "W" | "X" | "Y" | "Z" ;
+```
+
 ## `block_attributes`
 
 Block attributes must be a valid JSON object, like defined in the
@@ -118,31 +137,38 @@ ws =
 
 */
 
-use super::Input;
-use super::ast::Node;
+use super::{Input, ParseOptions};
+use super::ast::{BlockName, Node};
 use super::combinators;
-use nom::ErrorKind;
+pub(crate) use super::scanner::COMMENT_OPENING;
+use nom::{ErrorKind, IResult};
 use std::vec::Vec;
 
-const COMMENT_OPENING: &[u8] = b"<!--";
-const COMMENT_CLOSING: &[u8] = b"-->";
-const COMMENT_AUTO_CLOSING: &[u8] = b"/-->";
-const WP_OPENING: &[u8] = b"wp:";
-const WP_CLOSING: &[u8] = b"/wp:";
+pub(crate) const COMMENT_CLOSING: &[u8] = b"-->";
+pub(crate) const COMMENT_AUTO_CLOSING: &[u8] = b"/-->";
+const DELIMITER_CLOSING: &[u8] = b"/";
+const DELIMITER_SEPARATOR: &[u8] = b":";
 
 named_attr!(
     #[doc="
         Axiom of the grammar: Recognize a list of blocks.
     "],
     pub block_list<Input, Vec<Node>>,
+    call!(block_list_with_options, &ParseOptions::default())
+);
+
+/// Recognize a list of blocks, with specific parse options. See
+/// [`block_list`](./fn.block_list.html).
+pub fn block_list_with_options<'a>(input: Input<'a>, options: &ParseOptions<'a>) -> IResult<Input<'a>, Vec<Node<'a>>> {
     fold_into_vector_many0!(
+        input,
         alt_complete!(
-            block
-          | phrase
+            call!(block_with_options, options)
+          | call!(phrase_with_options, options)
         ),
         vec![]
     )
-);
+}
 
 named_attr!(
     #[doc="
@@ -170,15 +196,22 @@ named_attr!(
         ```
     "],
     pub phrase<Input, Node>,
+    call!(phrase_with_options, &ParseOptions::default())
+);
+
+/// Recognize a phrase, with specific parse options. See
+/// [`phrase`](./fn.phrase.html).
+pub fn phrase_with_options<'a>(input: Input<'a>, options: &ParseOptions<'a>) -> IResult<Input<'a>, Node<'a>> {
     map_res!(
+        input,
         alt_complete!(
             take_until_terminated!(
                 COMMENT_OPENING,
                 preceded!(
                     opt!(whitespaces),
                     alt!(
-                        tag!(WP_CLOSING)
-                      | tag!(WP_OPENING)
+                        call!(delimiter_closing, options)
+                      | call!(delimiter_opening, options)
                     )
                 )
             )
@@ -186,7 +219,7 @@ named_attr!(
         ),
         phrase_mapper
     )
-);
+}
 
 #[inline(always)]
 fn phrase_mapper(input: Input) -> Result<Node, ErrorKind> {
@@ -206,7 +239,7 @@ named_attr!(
         ```
         extern crate gutenberg_post_parser;
 
-        use gutenberg_post_parser::{ast::Node, parser::block};
+        use gutenberg_post_parser::{ast::{BlockName, Node}, parser::block};
 
         let input = &b\"<!-- wp:ns/foo {\\\"abc\\\": \\\"xyz\\\"} --><!-- /wp:ns/foo -->\"[..];
         let output = Ok(
@@ -216,7 +249,7 @@ named_attr!(
 
                 // The Abstract Syntax Tree.
                 Node::Block {
-                    name: (&b\"ns\"[..], &b\"foo\"[..]),
+                    name: BlockName::new(&b\"ns\"[..], &b\"foo\"[..]),
                     attributes: Some(&b\"{\\\"abc\\\": \\\"xyz\\\"}\"[..]),
                     children: vec![]
                 }
@@ -227,11 +260,18 @@ named_attr!(
         ```
     "],
     pub block<Input, Node>,
+    call!(block_with_options, &ParseOptions::default())
+);
+
+/// Recognize a block, with specific parse options. See
+/// [`block`](./fn.block.html).
+pub fn block_with_options<'a>(input: Input<'a>, options: &ParseOptions<'a>) -> IResult<Input<'a>, Node<'a>> {
     do_parse!(
+        input,
         tag!(COMMENT_OPENING) >>
         opt!(whitespaces) >>
-        tag!(WP_OPENING) >>
-        name: block_name >>
+        call!(delimiter_opening, options) >>
+        name: call!(block_name_with_options, options) >>
         whitespaces >>
         attributes: opt!(block_attributes) >>
         opt!(whitespaces) >>
@@ -241,18 +281,18 @@ named_attr!(
                 tag!(COMMENT_CLOSING) >>
                 children: fold_into_vector_many0!(
                     alt!(
-                        block
-                      | phrase
+                        call!(block_with_options, options)
+                      | call!(phrase_with_options, options)
                     ),
                     vec![]
                 ) >>
                 tag!(COMMENT_OPENING) >>
                 opt!(whitespaces) >>
-                tag!(WP_CLOSING) >>
+                call!(delimiter_closing, options) >>
                 _closing_name: verify!(
-                    block_name,
-                    |closing_name| {
-                        name == closing_name
+                    call!(block_name_with_options, options),
+                    |closing_name: BlockName| {
+                        name.is_equivalent_to(&closing_name)
                     }
                 ) >>
                 opt!(whitespaces) >>
@@ -279,8 +319,128 @@ named_attr!(
         ) >>
         (result)
     )
+}
+
+/// A block delimiter, i.e. a block comment recognized on its own,
+/// without its children nor its matching closing delimiter.
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(not(feature = "no_std"), derive(Debug))]
+pub enum Delimiter<'a> {
+    /// The opening delimiter of a balanced block, e.g. `<!-- wp:foo -->`.
+    Opening {
+        /// The block name.
+        name: BlockName<'a>,
+
+        /// The block attributes, if any.
+        attributes: Option<Input<'a>>
+    },
+
+    /// The delimiter of a void block, e.g. `<!-- wp:foo /-->`.
+    Void {
+        /// The block name.
+        name: BlockName<'a>,
+
+        /// The block attributes, if any.
+        attributes: Option<Input<'a>>
+    },
+
+    /// The closing delimiter of a balanced block, e.g. `<!-- /wp:foo -->`.
+    Closing {
+        /// The block name.
+        name: BlockName<'a>
+    }
+}
+
+named_attr!(
+    #[doc="
+        Recognize a block delimiter. Contrary to [`block`](./fn.block.html),
+        the children and the closing delimiter of a balanced block are not
+        recognized, so that a document can be scanned delimiter by
+        delimiter.
+
+        # Examples
+
+        ```
+        extern crate gutenberg_post_parser;
+
+        use gutenberg_post_parser::{ast::BlockName, parser::{delimiter, Delimiter}};
+
+        let input = &b\"<!-- wp:foo {\\\"abc\\\": \\\"xyz\\\"} -->bar\"[..];
+        let output = Ok(
+            (
+                // The remaining data.
+                &b\"bar\"[..],
+
+                // The delimiter.
+                Delimiter::Opening {
+                    name: BlockName::implicit(&b\"core\"[..], &b\"foo\"[..]),
+                    attributes: Some(&b\"{\\\"abc\\\": \\\"xyz\\\"}\"[..])
+                }
+            )
+        );
+
+        assert_eq!(delimiter(input), output);
+        ```
+    "],
+    pub delimiter<Input, Delimiter>,
+    call!(delimiter_with_options, &ParseOptions::default())
 );
 
+/// Recognize a block delimiter, with specific parse options. See
+/// [`delimiter`](./fn.delimiter.html).
+pub fn delimiter_with_options<'a>(input: Input<'a>, options: &ParseOptions<'a>) -> IResult<Input<'a>, Delimiter<'a>> {
+    preceded!(
+        input,
+        pair!(
+            tag!(COMMENT_OPENING),
+            opt!(whitespaces)
+        ),
+        alt!(
+            do_parse!(
+                call!(delimiter_closing, options) >>
+                name: call!(block_name_with_options, options) >>
+                opt!(whitespaces) >>
+                tag!(COMMENT_CLOSING) >>
+                (Delimiter::Closing { name })
+            )
+          | do_parse!(
+                call!(delimiter_opening, options) >>
+                name: call!(block_name_with_options, options) >>
+                whitespaces >>
+                attributes: opt!(block_attributes) >>
+                opt!(whitespaces) >>
+                delimiter: alt!(
+                    value!(Delimiter::Void { name, attributes }, tag!(COMMENT_AUTO_CLOSING))
+                  | value!(Delimiter::Opening { name, attributes }, tag!(COMMENT_CLOSING))
+                ) >>
+                (delimiter)
+            )
+        )
+    )
+}
+
+/// Recognize the delimiter of an opening block, e.g. `wp:`.
+pub(crate) fn delimiter_opening<'a>(input: Input<'a>, options: &ParseOptions<'a>) -> IResult<Input<'a>, Input<'a>> {
+    recognize!(
+        input,
+        pair!(
+            tag!(options.delimiter_prefix),
+            tag!(DELIMITER_SEPARATOR)
+        )
+    )
+}
+
+/// Recognize the delimiter of a closing block, e.g. `/wp:`.
+pub(crate) fn delimiter_closing<'a>(input: Input<'a>, options: &ParseOptions<'a>) -> IResult<Input<'a>, Input<'a>> {
+    recognize!(
+        input,
+        preceded!(
+            tag!(DELIMITER_CLOSING),
+            call!(delimiter_opening, options)
+        )
+    )
+}
+
 named_attr!(
     #[doc="
         Recognize a fully-qualified block name.
@@ -290,7 +450,7 @@ named_attr!(
         ```
         extern crate gutenberg_post_parser;
 
-        use gutenberg_post_parser::parser::block_name;
+        use gutenberg_post_parser::{ast::BlockName, parser::block_name};
 
         let input = &b\"foo/bar baz\"[..];
         let output = Ok(
@@ -299,19 +459,26 @@ named_attr!(
                 &b\" baz\"[..],
 
                 // The Abstract Syntax Tree.
-                (&b\"foo\"[..], &b\"bar\"[..])
+                BlockName::new(&b\"foo\"[..], &b\"bar\"[..])
             )
         );
 
         assert_eq!(block_name(input), output);
         ```
     "],
-    pub block_name<Input, (Input, Input)>,
+    pub block_name<Input, BlockName>,
+    call!(block_name_with_options, &ParseOptions::default())
+);
+
+/// Recognize a fully-qualified block name, with specific parse
+/// options. See [`block_name`](./fn.block_name.html).
+pub fn block_name_with_options<'a>(input: Input<'a>, options: &ParseOptions<'a>) -> IResult<Input<'a>, BlockName<'a>> {
     alt!(
-        namespaced_block_name
-      | core_block_name
+        input,
+        call!(namespaced_block_name_with_options, options)
+      | call!(core_block_name_with_options, options)
     )
-);
+}
 
 named_attr!(
     #[doc="
@@ -322,7 +489,7 @@ named_attr!(
         ```
         extern crate gutenberg_post_parser;
 
-        use gutenberg_post_parser::parser::namespaced_block_name;
+        use gutenberg_post_parser::{ast::BlockName, parser::namespaced_block_name};
 
         let input = &b\"foo/bar baz\"[..];
         let output = Ok(
@@ -331,23 +498,29 @@ named_attr!(
                 &b\" baz\"[..],
 
                 // The Abstract Syntax Tree.
-                (&b\"foo\"[..], &b\"bar\"[..])
+                BlockName::new(&b\"foo\"[..], &b\"bar\"[..])
             )
         );
 
         assert_eq!(namespaced_block_name(input), output);
         ```
     "],
-    pub namespaced_block_name<Input, (Input, Input)>,
-    tuple!(
-        block_name_part,
-        preceded!(
-            tag!(b"/"),
-            block_name_part
-        )
-    )
+    pub namespaced_block_name<Input, BlockName>,
+    call!(namespaced_block_name_with_options, &ParseOptions::default())
 );
 
+/// Recognize a namespaced block name, with specific parse
+/// options. See [`namespaced_block_name`](./fn.namespaced_block_name.html).
+pub fn namespaced_block_name_with_options<'a>(input: Input<'a>, options: &ParseOptions<'a>) -> IResult<Input<'a>, BlockName<'a>> {
+    do_parse!(
+        input,
+        namespace: call!(block_name_part_with_options, options) >>
+        tag!(b"/") >>
+        name: call!(block_name_part_with_options, options) >>
+        (BlockName::new(namespace, name))
+    )
+}
+
 named_attr!(
     #[doc="
         Recognize a globally-namespaced block name.
@@ -357,7 +530,7 @@ named_attr!(
         ```
         extern crate gutenberg_post_parser;
 
-        use gutenberg_post_parser::parser::core_block_name;
+        use gutenberg_post_parser::{ast::BlockName, parser::core_block_name};
 
         let input = &b\"foo bar\"[..];
         let output = Ok(
@@ -366,21 +539,29 @@ named_attr!(
                 &b\" bar\"[..],
 
                 // The Abstract Syntax Tree.
-                (&b\"core\"[..], &b\"foo\"[..])
+                BlockName::implicit(&b\"core\"[..], &b\"foo\"[..])
             )
         );
 
         assert_eq!(core_block_name(input), output);
         ```
     "],
-    pub core_block_name<Input, (Input, Input)>,
+    pub core_block_name<Input, BlockName>,
+    call!(core_block_name_with_options, &ParseOptions::default())
+);
+
+/// Recognize a globally-namespaced block name, with specific parse
+/// options: The namespace is `options.default_namespace`. See
+/// [`core_block_name`](./fn.core_block_name.html).
+pub fn core_block_name_with_options<'a>(input: Input<'a>, options: &ParseOptions<'a>) -> IResult<Input<'a>, BlockName<'a>> {
     map_res!(
-        block_name_part,
-        |block_name_part| -> Result<(Input, Input), ()> {
-            Ok((&b"core"[..], block_name_part))
+        input,
+        call!(block_name_part_with_options, options),
+        |block_name_part| -> Result<BlockName<'a>, ()> {
+            Ok(BlockName::implicit(options.default_namespace, block_name_part))
         }
     )
-);
+}
 
 named_attr!(
     #[doc="
@@ -408,14 +589,32 @@ named_attr!(
         ```
     "],
     pub block_name_part,
-    recognize!(
-        pair!(
-            take_while!(combinators::is_alpha),
-            take_while!(combinators::is_alphanumeric_extended)
-        )
-    )
+    call!(block_name_part_with_options, &ParseOptions::default())
 );
 
+/// Recognize a block name part, with specific parse options: If
+/// `options.lenient_names` is true, uppercase letters are accepted. See
+/// [`block_name_part`](./fn.block_name_part.html).
+pub fn block_name_part_with_options<'a>(input: Input<'a>, options: &ParseOptions<'a>) -> IResult<Input<'a>, Input<'a>> {
+    if options.lenient_names {
+        recognize!(
+            input,
+            pair!(
+                take_while!(combinators::is_lenient_alphanumeric),
+                take_while!(combinators::is_lenient_alphanumeric_extended)
+            )
+        )
+    } else {
+        recognize!(
+            input,
+            pair!(
+                take_while!(combinators::is_alpha),
+                take_while!(combinators::is_alphanumeric_extended)
+            )
+        )
+    }
+}
+
 named_attr!(
     #[doc="
         Recognize block attributes.
@@ -490,7 +689,7 @@ named_attr!(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use super::super::ast::Node;
+    use super::super::ast::{BlockName, Node};
     use nom::Err::Error;
     use nom::ErrorKind;
     use nom::simple_errors::Context;
@@ -504,11 +703,11 @@ mod tests {
                 vec![
                     Node::Phrase(&b"abc "[..]),
                     Node::Block {
-                        name: (&b"core"[..], &b"foo"[..]),
+                        name: BlockName::implicit(&b"core"[..], &b"foo"[..]),
                         attributes: None,
                         children: vec![
                             Node::Block {
-                                name: (&b"core"[..], &b"bar"[..]),
+                                name: BlockName::implicit(&b"core"[..], &b"bar"[..]),
                                 attributes: None,
                                 children: vec![]
                             },
@@ -532,7 +731,7 @@ mod tests {
                 vec![
                     Node::Phrase(&b"<p><!-- more --></p>"[..]),
                     Node::Block {
-                        name: (&b"core"[..], &b"foo"[..]),
+                        name: BlockName::implicit(&b"core"[..], &b"foo"[..]),
                         attributes: None,
                         children: vec![]
                     }
@@ -565,7 +764,7 @@ mod tests {
         let output = Ok((
             &b""[..],
             Node::Block {
-                name: (&b"core"[..], &b"foo"[..]),
+                name: BlockName::implicit(&b"core"[..], &b"foo"[..]),
                 attributes: None,
                 children: vec![]
             }
@@ -580,7 +779,7 @@ mod tests {
         let output = Ok((
             &b""[..],
             Node::Block {
-                name: (&b"ns"[..], &b"foo"[..]),
+                name: BlockName::new(&b"ns"[..], &b"foo"[..]),
                 attributes: None,
                 children: vec![]
             }
@@ -595,7 +794,7 @@ mod tests {
         let output = Ok((
             &b""[..],
             Node::Block {
-                name: (&b"ns"[..], &b"foo"[..]),
+                name: BlockName::new(&b"ns"[..], &b"foo"[..]),
                 attributes: Some(&b"{\"abc\": \"xyz\"}"[..]),
                 children: vec![]
             }
@@ -610,20 +809,20 @@ mod tests {
         let output = Ok((
             &b""[..],
             Node::Block {
-                name: (&b"core"[..], &b"foo"[..]),
+                name: BlockName::implicit(&b"core"[..], &b"foo"[..]),
                 attributes: None,
                 children: vec![
                     Node::Block {
-                        name: (&b"core"[..], &b"bar"[..]),
+                        name: BlockName::implicit(&b"core"[..], &b"bar"[..]),
                         attributes: Some(&b"{\"abc\": true}"[..]),
                         children: vec![]
                     },
                     Node::Block {
-                        name: (&b"core"[..], &b"baz"[..]),
+                        name: BlockName::implicit(&b"core"[..], &b"baz"[..]),
                         attributes: None,
                         children: vec![
                             Node::Block {
-                                name: (&b"core"[..], &b"qux"[..]),
+                                name: BlockName::implicit(&b"core"[..], &b"qux"[..]),
                                 attributes: None,
                                 children: vec![]
                             }
@@ -642,23 +841,23 @@ mod tests {
         let output = Ok((
             &b""[..],
             Node::Block {
-                name: (&b"core"[..], &b"foo"[..]),
+                name: BlockName::implicit(&b"core"[..], &b"foo"[..]),
                 attributes: None,
                 children: vec![
                     Node::Phrase(&b" abc "[..]),
                     Node::Block {
-                        name: (&b"core"[..], &b"bar"[..]),
+                        name: BlockName::implicit(&b"core"[..], &b"bar"[..]),
                         attributes: Some(&b"{\"abc\": true}"[..]),
                         children: vec![]
                     },
                     Node::Phrase(&b" def "[..]),
                     Node::Block {
-                        name: (&b"core"[..], &b"baz"[..]),
+                        name: BlockName::implicit(&b"core"[..], &b"baz"[..]),
                         attributes: None,
                         children: vec![
                             Node::Phrase(&b" ghi "[..]),
                             Node::Block {
-                                name: (&b"core"[..], &b"qux"[..]),
+                                name: BlockName::implicit(&b"core"[..], &b"qux"[..]),
                                 attributes: None,
                                 children: vec![]
                             },
@@ -681,13 +880,28 @@ mod tests {
         assert_eq!(block(input), output);
     }
 
+    #[test]
+    fn test_block_balanced_implicit_and_explicit_core_namespace() {
+        let input = &b"<!-- wp:foo --><!-- /wp:core/foo -->"[..];
+        let output = Ok((
+            &b""[..],
+            Node::Block {
+                name: BlockName::implicit(&b"core"[..], &b"foo"[..]),
+                attributes: None,
+                children: vec![]
+            }
+        ));
+
+        assert_eq!(block(input), output);
+    }
+
     #[test]
     fn test_block_void_default_namespace_without_attributes() {
         let input = &b"<!-- wp:foo /-->"[..];
         let output = Ok((
             &b""[..],
             Node::Block {
-                name: (&b"core"[..], &b"foo"[..]),
+                name: BlockName::implicit(&b"core"[..], &b"foo"[..]),
                 attributes: None,
                 children: vec![]
             }
@@ -702,7 +916,7 @@ mod tests {
         let output = Ok((
             &b""[..],
             Node::Block {
-                name: (&b"ns"[..], &b"foo"[..]),
+                name: BlockName::new(&b"ns"[..], &b"foo"[..]),
                 attributes: None,
                 children: vec![]
             }
@@ -717,7 +931,7 @@ mod tests {
         let output = Ok((
             &b""[..],
             Node::Block {
-                name: (&b"ns"[..], &b"foo"[..]),
+                name: BlockName::new(&b"ns"[..], &b"foo"[..]),
                 attributes: Some(&b"{\"abc\": \"xyz\"}"[..]),
                 children: vec![]
             }
@@ -726,10 +940,103 @@ mod tests {
         assert_eq!(block(input), output);
     }
 
+    #[test]
+    fn test_block_with_a_custom_delimiter_prefix() {
+        let options = ParseOptions { delimiter_prefix: &b"acme"[..], .. ParseOptions::default() };
+        let input = &b"<!-- acme:foo --><!-- wp:bar /--><!-- /acme:foo -->"[..];
+        let output = Ok((
+            &b""[..],
+            Node::Block {
+                name: BlockName::implicit(&b"core"[..], &b"foo"[..]),
+                attributes: None,
+                children: vec![
+                    Node::Phrase(&b"<!-- wp:bar /-->"[..])
+                ]
+            }
+        ));
+
+        assert_eq!(block_with_options(input, &options), output);
+    }
+
+    #[test]
+    fn test_delimiter() {
+        let name = BlockName::new(&b"ns"[..], &b"foo"[..]);
+
+        assert_eq!(
+            delimiter(&b"<!-- wp:ns/foo {\"a\":1} -->x"[..]),
+            Ok((&b"x"[..], Delimiter::Opening { name, attributes: Some(&b"{\"a\":1}"[..]) }))
+        );
+        assert_eq!(
+            delimiter(&b"<!--wp:ns/foo /-->x"[..]),
+            Ok((&b"x"[..], Delimiter::Void { name, attributes: None }))
+        );
+        assert_eq!(
+            delimiter(&b"<!-- /wp:ns/foo -->x"[..]),
+            Ok((&b"x"[..], Delimiter::Closing { name }))
+        );
+        assert!(delimiter(&b"<!-- more -->"[..]).is_err());
+        assert!(delimiter(&b"<!-- /wp:foo {} -->"[..]).is_err());
+    }
+
+    #[test]
+    fn test_delimiter_with_a_custom_delimiter_prefix() {
+        let options = ParseOptions { delimiter_prefix: &b"acme"[..], .. ParseOptions::default() };
+
+        assert_eq!(
+            delimiter_with_options(&b"<!-- /acme:foo -->"[..], &options),
+            Ok((&b""[..], Delimiter::Closing { name: BlockName::implicit(&b"core"[..], &b"foo"[..]) }))
+        );
+        assert!(delimiter_with_options(&b"<!-- wp:foo /-->"[..], &options).is_err());
+    }
+
+    #[test]
+    fn test_block_list_with_a_custom_default_namespace() {
+        let options = ParseOptions { default_namespace: &b"acme"[..], .. ParseOptions::default() };
+        let input = &b"<!-- wp:foo /--><!-- wp:ns/bar /-->"[..];
+        let output = Ok((
+            &b""[..],
+            vec![
+                Node::Block {
+                    name: BlockName::implicit(&b"acme"[..], &b"foo"[..]),
+                    attributes: None,
+                    children: vec![]
+                },
+                Node::Block {
+                    name: BlockName::new(&b"ns"[..], &b"bar"[..]),
+                    attributes: None,
+                    children: vec![]
+                }
+            ]
+        ));
+
+        assert_eq!(block_list_with_options(input, &options), output);
+    }
+
+    #[test]
+    fn test_block_name_part_with_uppercase_letters() {
+        let input = &b"Foo x"[..];
+
+        assert_eq!(block_name_part(input), Ok((input, &b""[..])));
+
+        let options = ParseOptions { lenient_names: true, .. ParseOptions::default() };
+        let output = Ok((&b" x"[..], &b"Foo"[..]));
+
+        assert_eq!(block_name_part_with_options(input, &options), output);
+    }
+
+    #[test]
+    fn test_block_name_part_lenient() {
+        let options = ParseOptions { lenient_names: true, .. ParseOptions::default() };
+        let input = &b"2Col_B-ar x"[..];
+        let output = Ok((&b" x"[..], &b"2Col_B-ar"[..]));
+
+        assert_eq!(block_name_part_with_options(input, &options), output);
+    }
+
     #[test]
     fn test_namespaced_block_name() {
         let input = &b"foo_bar/baz42 x"[..];
-        let output = Ok((&b" x"[..], (&b"foo_bar"[..], &b"baz42"[..])));
+        let output = Ok((&b" x"[..], BlockName::new(&b"foo_bar"[..], &b"baz42"[..])));
 
         assert_eq!(namespaced_block_name(input), output);
         assert_eq!(block_name(input), output);
@@ -738,12 +1045,48 @@ mod tests {
     #[test]
     fn test_core_block_name() {
         let input = &b"foo x"[..];
-        let output = Ok((&b" x"[..], (&b"core"[..], &b"foo"[..])));
+        let output = Ok((&b" x"[..], BlockName::implicit(&b"core"[..], &b"foo"[..])));
 
         assert_eq!(core_block_name(input), output);
         assert_eq!(block_name(input), output);
     }
 
+    #[test]
+    fn test_block_name_implicit_namespace_is_distinguishable() {
+        let implicit = block_name(&b"paragraph x"[..]).unwrap().1;
+        let explicit = block_name(&b"core/paragraph x"[..]).unwrap().1;
+
+        assert!(implicit.is_namespace_implicit());
+        assert!(!explicit.is_namespace_implicit());
+        assert!(implicit != explicit);
+        assert!(implicit.is_equivalent_to(&explicit));
+        assert_eq!(implicit.fully_qualified(), "core/paragraph");
+        assert_eq!(explicit.fully_qualified(), "core/paragraph");
+    }
+
+    #[test]
+    fn test_block_name_spans() {
+        use super::super::ast::Span;
+
+        let input = &b"<!-- wp:ns/foo /--><!-- wp:bar /-->"[..];
+        let output = block_list(input).unwrap().1;
+
+        match (&output[0], &output[1]) {
+            (Node::Block { name: namespaced, .. }, Node::Block { name: implicit, .. }) => {
+                assert_eq!(namespaced.namespace_span(input), Some(Span { start: 8, end: 10 }));
+                assert_eq!(namespaced.name_span(input), Span { start: 11, end: 14 });
+                assert_eq!(namespaced.span(input), Span { start: 8, end: 14 });
+
+                assert_eq!(implicit.namespace_span(input), None);
+                assert!(!input.as_ptr_range().contains(&implicit.namespace().as_ptr()));
+                assert_eq!(implicit.name_span(input), Span { start: 27, end: 30 });
+                assert_eq!(implicit.span(input), Span { start: 27, end: 30 });
+            },
+
+            _ => panic!("expected a block")
+        }
+    }
+
     #[test]
     fn test_block_name_part_shortest() {
         let input = &b"a x"[..];
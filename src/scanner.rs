@@ -0,0 +1,144 @@
+/*!
+
+Fast scanning of the input.
+
+The parser spends most of its time looking for the `<!--` delimiter
+in phrases, which are mostly made of HTML. The `<` byte is very
+frequent in HTML, so the search is anchored on the `!` byte, which is
+rarer, and then the surroundings are verified.
+
+When the standard library is available, the search of a byte is
+vectorised by the [`memchr`] crate. Otherwise, e.g. with the `no_std`
+feature (used by the WASM binding), a portable fallback is used.
+
+The `naive-scanner` feature replaces the search by
+`nom::FindSubstring`, i.e. the search used before this module existed,
+so that `root` can be benchmarked against it, see `just
+bench-scanner`.
+
+[`memchr`]: https://github.com/BurntSushi/rust-memchr
+
+*/
+
+use super::Input;
+
+/// The opening of a comment, i.e. `<!--`.
+pub const COMMENT_OPENING: &[u8] = b"<!--";
+
+/// Find the position of the first occurrence of `needle` in
+/// `haystack`.
+///
+/// # Examples
+///
+/// ```
+/// extern crate gutenberg_post_parser;
+///
+/// use gutenberg_post_parser::scanner::find;
+///
+/// assert_eq!(find(&b"<p><!-- more --></p>"[..], &b"<!--"[..]), Some(3));
+/// assert_eq!(find(&b"<p>foo</p>"[..], &b"<!--"[..]), None);
+/// ```
+#[cfg(not(feature = "naive-scanner"))]
+pub fn find(haystack: Input, needle: Input) -> Option<usize> {
+    match needle.len() {
+        0 => Some(0),
+        1 => find_byte(needle[0], haystack),
+        _ if needle == COMMENT_OPENING => find_comment_opening(haystack),
+        _ => find_anchored(haystack, needle, 0)
+    }
+}
+
+/// Find the position of the first occurrence of `needle` in
+/// `haystack`, with `nom::FindSubstring`.
+#[cfg(feature = "naive-scanner")]
+pub fn find(haystack: Input, needle: Input) -> Option<usize> {
+    use nom::FindSubstring;
+
+    haystack.find_substring(needle)
+}
+
+/// Find the position of the first `<!--` in `haystack`.
+pub fn find_comment_opening(haystack: Input) -> Option<usize> {
+    // Anchor on `!`, at position 1 in `<!--`.
+    find_anchored(haystack, COMMENT_OPENING, 1)
+}
+
+/// Find `needle` in `haystack` by searching for the byte at position
+/// `anchor` in `needle`, and then by verifying the whole needle
+/// around it.
+fn find_anchored(haystack: Input, needle: Input, anchor: usize) -> Option<usize> {
+    let needle_length = needle.len();
+    let anchor_byte = needle[anchor];
+    let mut offset = anchor;
+
+    if haystack.len() < needle_length {
+        return None;
+    }
+
+    let last_anchor = haystack.len() - needle_length + anchor;
+
+    while offset <= last_anchor {
+        match find_byte(anchor_byte, &haystack[offset..=last_anchor]) {
+            Some(position) => {
+                let start = offset + position - anchor;
+
+                if &haystack[start..start + needle_length] == needle {
+                    return Some(start);
+                }
+
+                offset += position + 1;
+            },
+
+            None => return None
+        }
+    }
+
+    None
+}
+
+/// Find the position of the first occurrence of `byte` in `haystack`.
+#[cfg(not(feature = "no_std"))]
+#[inline(always)]
+fn find_byte(byte: u8, haystack: Input) -> Option<usize> {
+    ::memchr::memchr(byte, haystack)
+}
+
+/// Find the position of the first occurrence of `byte` in `haystack`.
+#[cfg(feature = "no_std")]
+#[inline(always)]
+fn find_byte(byte: u8, haystack: Input) -> Option<usize> {
+    haystack.iter().position(|&current| current == byte)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_empty_needle() {
+        assert_eq!(find(&b"abc"[..], &b""[..]), Some(0));
+    }
+
+    #[test]
+    fn test_find_one_byte() {
+        assert_eq!(find(&b"abc"[..], &b"c"[..]), Some(2));
+        assert_eq!(find(&b"abc"[..], &b"d"[..]), None);
+    }
+
+    #[test]
+    fn test_find_substring() {
+        assert_eq!(find(&b"abcdcba"[..], &b"cb"[..]), Some(4));
+        assert_eq!(find(&b"abcdcba"[..], &b"ab"[..]), Some(0));
+        assert_eq!(find(&b"abcdcba"[..], &b"ba"[..]), Some(5));
+        assert_eq!(find(&b"abcdcba"[..], &b"bb"[..]), None);
+        assert_eq!(find(&b"a"[..], &b"ab"[..]), None);
+    }
+
+    #[test]
+    fn test_find_comment_opening() {
+        assert_eq!(find_comment_opening(&b"<!--"[..]), Some(0));
+        assert_eq!(find_comment_opening(&b"!<!-- -->"[..]), Some(1));
+        assert_eq!(find_comment_opening(&b"<p>Hello! <!-> <!"[..]), None);
+        assert_eq!(find_comment_opening(&b"<p>Hello!</p><!-- wp:foo /-->"[..]), Some(13));
+    }
+}
@@ -0,0 +1,1026 @@
+/*!
+
+A compact binary encoding of the AST, e.g. to transfer it from
+WebAssembly memory to JavaScript.
+
+Phrases and attributes are not copied: They are referenced by their
+position in the input, counted in UTF-16 code units, i.e. as indices
+of a JavaScript string. Block names are copied once each into a string
+table, and nodes refer to them by index. Optionally, attributes are
+encoded as parsed JSON values, so that the decoder does not have to
+parse them again.
+
+# Format
+
+All integers are unsigned [LEB128] varints, unless specified
+otherwise; a `gap` is a signed integer, [zigzag-encoded] into an
+unsigned varint, i.e. `n` is `2n` if positive, and `-2n - 1` if
+negative. The encoding of a list of nodes is:
+
+```text
+encoding     = version flags status string_table nodes
+version      = u8, `VERSION`
+flags        = u8, a combination of `FLAG_*`
+status       = STATUS_PARSED
+             | STATUS_FAILED kind offset message
+kind         = length utf8_bytes
+message      = length utf8_bytes
+string_table = count (length utf8_bytes)*
+nodes        = count node*
+node         = TAG_PHRASE gap length
+             | TAG_BLOCK name_index count node*
+             | TAG_BLOCK_WITH_ATTRIBUTES name_index attributes count node*
+attributes   = gap length                 without FLAG_ATTRIBUTE_VALUES
+             | value | VALUE_RAW gap length   with FLAG_ATTRIBUTE_VALUES
+value        = VALUE_NULL | VALUE_FALSE | VALUE_TRUE
+             | VALUE_NUMBER length ascii_bytes
+             | VALUE_STRING length utf8_bytes
+             | VALUE_ARRAY count value*
+             | VALUE_OBJECT count (length utf8_bytes value)*
+```
+
+The status tells whether the input has been parsed entirely. If not,
+it carries the error: Its kind, as
+[`ErrorKind::as_str`](../error/enum.ErrorKind.html#method.as_str),
+its offset in UTF-16 code units from the beginning of the input, and a
+human-readable message; the nodes are the ones before the rejected
+data.
+
+A block name in the string table is its fully-qualified name, i.e.
+`namespace/name`. The `gap` is the distance between the end of the
+previous phrase or attributes in the input (or its beginning) and the
+beginning of the current phrase or attributes; the `length` is their
+length. The nodes being ordered as in the input, gaps keep the varints
+small. A gap is negative when spans overlap, like the phrases of the
+blocks recovered by [`output::recover`](../output/fn.recover.html). It also means that the children of a block interleave phrases
+and blocks like the `innerContent` of a Gutenberg block.
+
+With `FLAG_ATTRIBUTE_VALUES`, numbers are kept as written in the
+input, strings have their escape sequences decoded, and object members
+are in the input order, see the [`json`](../json/index.html) module.
+Attributes that cannot be decoded are encoded as written in the input,
+with `VALUE_RAW`.
+
+The version is bumped on any change of the format, so that decoders
+can reject what they do not understand.
+
+# Examples
+
+```
+extern crate gutenberg_post_parser;
+
+use gutenberg_post_parser::{root, wire};
+
+let input = &b"a<!-- wp:foo {\"b\":1} /-->"[..];
+let (_remaining, nodes) = root(input).unwrap();
+let mut encoding = vec![];
+
+wire::encode(input, &nodes, None, &mut encoding);
+
+assert_eq!(
+    encoding,
+    vec![
+        wire::VERSION,
+        0,
+        wire::STATUS_PARSED,
+        1, 8, b'c', b'o', b'r', b'e', b'/', b'f', b'o', b'o',
+        2,
+        wire::TAG_PHRASE, 0, 1,
+        wire::TAG_BLOCK_WITH_ATTRIBUTES, 0, 24, 7, 0,
+    ]
+);
+
+let (decoded_nodes, error) = wire::decode(input, &encoding).unwrap();
+
+assert_eq!(decoded_nodes.len(), 2);
+assert_eq!(error, None);
+```
+
+[LEB128]: https://en.wikipedia.org/wiki/LEB128
+[zigzag-encoded]: https://developers.google.com/protocol-buffers/docs/encoding#signed-ints
+
+*/
+
+use super::Input;
+use super::ast::{self, BlockName};
+use super::error;
+use super::json;
+use std::collections::BTreeMap;
+use std::str;
+use std::string::String;
+use std::vec::Vec;
+
+/// Version of the format, written in the first byte of an encoding.
+pub const VERSION: u8 = 4;
+
+/// Flag of an encoding where attributes are parsed JSON values.
+pub const FLAG_ATTRIBUTE_VALUES: u8 = 1;
+
+/// Status of an input parsed entirely.
+pub const STATUS_PARSED: u8 = 0;
+
+/// Status of an input not parsed entirely, followed by the error.
+pub const STATUS_FAILED: u8 = 1;
+
+/// Tag of a phrase.
+pub const TAG_PHRASE: u8 = 0;
+
+/// Tag of a block without attributes.
+pub const TAG_BLOCK: u8 = 1;
+
+/// Tag of a block with attributes.
+pub const TAG_BLOCK_WITH_ATTRIBUTES: u8 = 2;
+
+/// Tag of the JSON `null` value.
+pub const VALUE_NULL: u8 = 0;
+
+/// Tag of the JSON `false` value.
+pub const VALUE_FALSE: u8 = 1;
+
+/// Tag of the JSON `true` value.
+pub const VALUE_TRUE: u8 = 2;
+
+/// Tag of a JSON number.
+pub const VALUE_NUMBER: u8 = 3;
+
+/// Tag of a JSON string.
+pub const VALUE_STRING: u8 = 4;
+
+/// Tag of a JSON array.
+pub const VALUE_ARRAY: u8 = 5;
+
+/// Tag of a JSON object.
+pub const VALUE_OBJECT: u8 = 6;
+
+/// Tag of attributes that cannot be decoded, referenced as written in
+/// the input.
+pub const VALUE_RAW: u8 = 7;
+
+/// Maximum nesting of decoded arrays and objects.
+const MAXIMUM_DEPTH: usize = 128;
+
+/// Options of the encoding.
+///
+/// By default, attributes are referenced as written in the input.
+#[derive(Clone, Copy, Default, PartialEq)]
+#[cfg_attr(not(feature = "no_std"), derive(Debug))]
+pub struct EncodeOptions {
+    /// Encode attributes as parsed JSON values, i.e. set
+    /// `FLAG_ATTRIBUTE_VALUES`.
+    pub attribute_values: bool
+}
+
+/// Encode `nodes`, parsed from `input`, and the `error` explaining
+/// why the input has not been parsed entirely if any, see
+/// [`error::diagnose`](../error/fn.diagnose.html), at the end of
+/// `output`.
+pub fn encode(input: Input, nodes: &[ast::Node], error: Option<error::Error>, output: &mut Vec<u8>) {
+    encode_with_options(input, nodes, error, &EncodeOptions::default(), output)
+}
+
+/// Encode with specific options. See [`encode`](./fn.encode.html).
+pub fn encode_with_options(input: Input, nodes: &[ast::Node], error: Option<error::Error>, options: &EncodeOptions, output: &mut Vec<u8>) {
+    let mut names = BTreeMap::new();
+    let mut table = Vec::new();
+
+    collect_names(nodes, &mut names, &mut table);
+
+    output.push(VERSION);
+    output.push(if options.attribute_values { FLAG_ATTRIBUTE_VALUES } else { 0 });
+
+    match error {
+        Some(error) => {
+            let kind = error.kind.as_str();
+            let message = error.kind.description();
+
+            output.push(STATUS_FAILED);
+            push_bytes(kind.as_bytes(), output);
+            push_varint(count_utf16(&input[..error.offset]), output);
+            push_bytes(message.as_bytes(), output);
+        },
+
+        None => output.push(STATUS_PARSED)
+    }
+
+    push_varint(table.len(), output);
+
+    for name in &table {
+        push_varint(name.namespace().len() + 1 + name.name().len(), output);
+        output.extend_from_slice(name.namespace());
+        output.push(b'/');
+        output.extend_from_slice(name.name());
+    }
+
+    let mut encoder = Encoder {
+        input,
+        names: &names,
+        options,
+        byte_cursor: 0,
+        output
+    };
+
+    encoder.nodes(nodes);
+}
+
+fn collect_names<'a>(nodes: &[ast::Node<'a>], names: &mut BTreeMap<(Input<'a>, Input<'a>), usize>, table: &mut Vec<BlockName<'a>>) {
+    for node in nodes {
+        if let ast::Node::Block { name, children, .. } = node {
+            let index = table.len();
+
+            names.entry((name.namespace(), name.name())).or_insert_with(|| {
+                table.push(*name);
+
+                index
+            });
+
+            collect_names(children, names, table);
+        }
+    }
+}
+
+struct Encoder<'a, 'b> {
+    input: Input<'a>,
+    names: &'b BTreeMap<(Input<'a>, Input<'a>), usize>,
+    options: &'b EncodeOptions,
+    byte_cursor: usize,
+    output: &'b mut Vec<u8>
+}
+
+impl<'a, 'b> Encoder<'a, 'b> {
+    fn nodes(&mut self, nodes: &[ast::Node<'a>]) {
+        push_varint(nodes.len(), self.output);
+
+        for node in nodes {
+            match node {
+                ast::Node::Phrase(phrase) => {
+                    self.output.push(TAG_PHRASE);
+                    self.span(phrase);
+                },
+
+                ast::Node::Block { name, attributes, children } => {
+                    let index = self.names[&(name.namespace(), name.name())];
+
+                    match attributes {
+                        Some(attributes) => {
+                            self.output.push(TAG_BLOCK_WITH_ATTRIBUTES);
+                            push_varint(index, self.output);
+                            self.attributes(attributes);
+                        },
+
+                        None => {
+                            self.output.push(TAG_BLOCK);
+                            push_varint(index, self.output);
+                        }
+                    }
+
+                    self.nodes(children);
+                }
+            }
+        }
+    }
+
+    fn attributes(&mut self, attributes: Input<'a>) {
+        if !self.options.attribute_values {
+            return self.span(attributes);
+        }
+
+        match json::parse(attributes) {
+            Ok(value) => push_value(&value, self.output),
+
+            Err(_) => {
+                self.output.push(VALUE_RAW);
+                self.span(attributes);
+            }
+        }
+    }
+
+    /// Push the gap and the length of `slice`, a slice of the input,
+    /// in UTF-16 code units.
+    fn span(&mut self, slice: Input<'a>) {
+        let start = slice.as_ptr() as usize - self.input.as_ptr() as usize;
+
+        let gap = if start >= self.byte_cursor {
+            2 * count_utf16(&self.input[self.byte_cursor..start])
+        } else {
+            2 * count_utf16(&self.input[start..self.byte_cursor]) - 1
+        };
+
+        push_varint(gap, self.output);
+        push_varint(count_utf16(slice), self.output);
+
+        self.byte_cursor = start + slice.len();
+    }
+}
+
+fn push_value(value: &json::Value, output: &mut Vec<u8>) {
+    match value {
+        json::Value::Null => output.push(VALUE_NULL),
+        json::Value::Boolean(false) => output.push(VALUE_FALSE),
+        json::Value::Boolean(true) => output.push(VALUE_TRUE),
+
+        json::Value::Number(number) => {
+            output.push(VALUE_NUMBER);
+            push_bytes(number, output);
+        },
+
+        json::Value::String(string) => {
+            output.push(VALUE_STRING);
+            push_bytes(string.as_bytes(), output);
+        },
+
+        json::Value::Array(items) => {
+            output.push(VALUE_ARRAY);
+            push_varint(items.len(), output);
+
+            for item in items {
+                push_value(item, output);
+            }
+        },
+
+        json::Value::Object(members) => {
+            output.push(VALUE_OBJECT);
+            push_varint(members.len(), output);
+
+            for (key, value) in members {
+                push_bytes(key.as_bytes(), output);
+                push_value(value, output);
+            }
+        }
+    }
+}
+
+fn push_bytes(bytes: &[u8], output: &mut Vec<u8>) {
+    push_varint(bytes.len(), output);
+    output.extend_from_slice(bytes);
+}
+
+fn push_varint(mut value: usize, output: &mut Vec<u8>) {
+    while value >= 0x80 {
+        output.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+
+    output.push(value as u8);
+}
+
+/// Count the UTF-16 code units of UTF-8 bytes: One per character, two
+/// for the characters encoded with 4 bytes (outside the Basic
+/// Multilingual Plane).
+fn count_utf16(input: Input) -> usize {
+    input
+        .iter()
+        .map(|&byte| match byte {
+            0x80..=0xbf => 0,
+            0xf0..=0xff => 2,
+            _ => 1
+        })
+        .sum()
+}
+
+/// Represent the reason why an encoding is invalid.
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(not(feature = "no_std"), derive(Debug))]
+pub enum ErrorKind {
+    /// The version is not `VERSION`.
+    UnsupportedVersion,
+
+    /// A flag is unknown.
+    UnsupportedFlags,
+
+    /// The encoding ends before its end.
+    UnexpectedEnd,
+
+    /// A varint overflows.
+    InvalidVarint,
+
+    /// A status is unknown.
+    InvalidStatus,
+
+    /// An error kind is unknown, or its offset is out of the input.
+    InvalidError,
+
+    /// A node tag is unknown.
+    InvalidTag,
+
+    /// A block name is not valid, or its index is out of the string
+    /// table.
+    InvalidName,
+
+    /// A span is out of the input, or does not fall on a character
+    /// boundary.
+    InvalidSpan,
+
+    /// A value tag is unknown, a string is not valid UTF-8, or arrays
+    /// and objects are nested too deeply.
+    InvalidValue,
+
+    /// Data remain after the nodes.
+    TrailingData
+}
+
+impl ErrorKind {
+    /// Return a human-readable description of the error kind.
+    pub fn description(&self) -> &'static str {
+        match self {
+            ErrorKind::UnsupportedVersion => "The version of the encoding is not supported.",
+            ErrorKind::UnsupportedFlags => "A flag of the encoding is not supported.",
+            ErrorKind::UnexpectedEnd => "Unexpected end of the encoding.",
+            ErrorKind::InvalidVarint => "A varint overflows.",
+            ErrorKind::InvalidStatus => "Unknown status.",
+            ErrorKind::InvalidError => "Unknown error kind, or error offset out of the input.",
+            ErrorKind::InvalidTag => "Unknown node tag.",
+            ErrorKind::InvalidName => "Invalid block name, or block name index.",
+            ErrorKind::InvalidSpan => "A span is out of the input.",
+            ErrorKind::InvalidValue => "Invalid attribute value.",
+            ErrorKind::TrailingData => "Unexpected data after the nodes."
+        }
+    }
+}
+
+/// Represent a located decoding error.
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(not(feature = "no_std"), derive(Debug))]
+pub struct Error {
+    /// The reason why the encoding is invalid.
+    pub kind: ErrorKind,
+
+    /// The offset, in bytes from the beginning of the encoding, where
+    /// the error has been detected.
+    pub offset: usize
+}
+
+/// Represent a decoded node. It is similar to
+/// [`ast::Node`](../ast/enum.Node.html), except for the attributes,
+/// which can be decoded values.
+#[derive(PartialEq)]
+#[cfg_attr(not(feature = "no_std"), derive(Debug))]
+pub enum Node<'a> {
+    /// A block, with its fully-qualified name.
+    Block {
+        /// The block name, with an explicit namespace.
+        name: BlockName<'a>,
+
+        /// The attributes, if any.
+        attributes: Option<Attributes<'a>>,
+
+        /// The inner blocks and phrases.
+        children: Vec<Node<'a>>
+    },
+
+    /// A phrase.
+    Phrase(Input<'a>)
+}
+
+/// Represent the decoded attributes of a block.
+#[derive(PartialEq)]
+#[cfg_attr(not(feature = "no_std"), derive(Debug))]
+pub enum Attributes<'a> {
+    /// The attributes as written in the input.
+    Raw(Input<'a>),
+
+    /// The attributes as a JSON value, with `FLAG_ATTRIBUTE_VALUES`.
+    Value(json::Value<'a>)
+}
+
+/// Decode nodes, and the error if any, encoded with `encode`, with the
+/// same `input`. Block names are slices of `encoding`, with an
+/// explicit namespace. The offset of the error is in bytes.
+pub fn decode<'a>(input: Input<'a>, encoding: &'a [u8]) -> Result<(Vec<Node<'a>>, Option<error::Error>), Error> {
+    let mut decoder = Decoder {
+        input,
+        encoding,
+        offset: 0,
+        byte_cursor: 0,
+        attribute_values: false,
+        names: Vec::new()
+    };
+
+    if decoder.byte()? != VERSION {
+        return Err(Error { kind: ErrorKind::UnsupportedVersion, offset: 0 });
+    }
+
+    let flags_offset = decoder.offset;
+
+    match decoder.byte()? {
+        0 => (),
+        FLAG_ATTRIBUTE_VALUES => decoder.attribute_values = true,
+        _ => return Err(Error { kind: ErrorKind::UnsupportedFlags, offset: flags_offset })
+    }
+
+    let status_offset = decoder.offset;
+
+    let parse_error = match decoder.byte()? {
+        STATUS_PARSED => None,
+
+        STATUS_FAILED => {
+            let error_offset = decoder.offset;
+            let kind = decoder.length_and_bytes()?;
+            let offset = decoder.varint()?;
+
+            decoder.length_and_bytes()?;
+
+            let kind = str::from_utf8(kind).ok().and_then(error::ErrorKind::from_name);
+
+            match (kind, advance_utf16(input, 0, offset)) {
+                (Some(kind), Some(offset)) => Some(error::Error { kind, offset }),
+                _ => return Err(Error { kind: ErrorKind::InvalidError, offset: error_offset })
+            }
+        },
+
+        _ => return Err(Error { kind: ErrorKind::InvalidStatus, offset: status_offset })
+    };
+
+    for _ in 0..decoder.varint()? {
+        let offset = decoder.offset;
+        let name = decoder.length_and_bytes()?;
+
+        match name.iter().position(|&byte| byte == b'/') {
+            Some(separator) => decoder.names.push(BlockName::new(&name[..separator], &name[separator + 1..])),
+            None => return Err(Error { kind: ErrorKind::InvalidName, offset })
+        }
+    }
+
+    let nodes = decoder.nodes()?;
+
+    if decoder.offset < encoding.len() {
+        return Err(decoder.error(ErrorKind::TrailingData));
+    }
+
+    Ok((nodes, parse_error))
+}
+
+struct Decoder<'a> {
+    input: Input<'a>,
+    encoding: &'a [u8],
+    offset: usize,
+    byte_cursor: usize,
+    attribute_values: bool,
+    names: Vec<BlockName<'a>>
+}
+
+impl<'a> Decoder<'a> {
+    fn error(&self, kind: ErrorKind) -> Error {
+        Error { kind, offset: self.offset }
+    }
+
+    fn byte(&mut self) -> Result<u8, Error> {
+        match self.encoding.get(self.offset) {
+            Some(&byte) => {
+                self.offset += 1;
+
+                Ok(byte)
+            },
+
+            None => Err(self.error(ErrorKind::UnexpectedEnd))
+        }
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], Error> {
+        if length > self.encoding.len() - self.offset {
+            return Err(self.error(ErrorKind::UnexpectedEnd));
+        }
+
+        let bytes = &self.encoding[self.offset..self.offset + length];
+        self.offset += length;
+
+        Ok(bytes)
+    }
+
+    fn length_and_bytes(&mut self) -> Result<&'a [u8], Error> {
+        let length = self.varint()?;
+
+        self.bytes(length)
+    }
+
+    fn varint(&mut self) -> Result<usize, Error> {
+        let offset = self.offset;
+        let mut value: usize = 0;
+        let mut shift = 0;
+
+        loop {
+            let byte = self.byte()?;
+            let bits = (byte & 0x7f) as usize;
+
+            match bits.checked_shl(shift) {
+                Some(shifted) if shifted >> shift == bits => value |= shifted,
+                _ => return Err(Error { kind: ErrorKind::InvalidVarint, offset })
+            }
+
+            shift += 7;
+
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn nodes(&mut self) -> Result<Vec<Node<'a>>, Error> {
+        let count = self.varint()?;
+        let mut nodes = Vec::new();
+
+        for _ in 0..count {
+            let offset = self.offset;
+
+            let node = match self.byte()? {
+                TAG_PHRASE => Node::Phrase(self.span()?),
+
+                tag @ TAG_BLOCK | tag @ TAG_BLOCK_WITH_ATTRIBUTES => {
+                    let name_offset = self.offset;
+                    let index = self.varint()?;
+                    let name = match self.names.get(index) {
+                        Some(name) => *name,
+                        None => return Err(Error { kind: ErrorKind::InvalidName, offset: name_offset })
+                    };
+
+                    let attributes = if tag == TAG_BLOCK_WITH_ATTRIBUTES {
+                        Some(self.attributes()?)
+                    } else {
+                        None
+                    };
+
+                    Node::Block {
+                        name,
+                        attributes,
+                        children: self.nodes()?
+                    }
+                },
+
+                _ => return Err(Error { kind: ErrorKind::InvalidTag, offset })
+            };
+
+            nodes.push(node);
+        }
+
+        Ok(nodes)
+    }
+
+    fn attributes(&mut self) -> Result<Attributes<'a>, Error> {
+        if !self.attribute_values {
+            return Ok(Attributes::Raw(self.span()?));
+        }
+
+        if self.encoding.get(self.offset) == Some(&VALUE_RAW) {
+            self.offset += 1;
+
+            return Ok(Attributes::Raw(self.span()?));
+        }
+
+        Ok(Attributes::Value(self.value(0)?))
+    }
+
+    fn value(&mut self, depth: usize) -> Result<json::Value<'a>, Error> {
+        let offset = self.offset;
+        let invalid_value = Error { kind: ErrorKind::InvalidValue, offset };
+
+        if depth > MAXIMUM_DEPTH {
+            return Err(invalid_value);
+        }
+
+        let value = match self.byte()? {
+            VALUE_NULL => json::Value::Null,
+            VALUE_FALSE => json::Value::Boolean(false),
+            VALUE_TRUE => json::Value::Boolean(true),
+            VALUE_NUMBER => json::Value::Number(self.length_and_bytes()?),
+            VALUE_STRING => json::Value::String(self.string()?),
+
+            VALUE_ARRAY => {
+                let mut items = Vec::new();
+
+                for _ in 0..self.varint()? {
+                    items.push(self.value(depth + 1)?);
+                }
+
+                json::Value::Array(items)
+            },
+
+            VALUE_OBJECT => {
+                let mut members = Vec::new();
+
+                for _ in 0..self.varint()? {
+                    let key = self.string()?;
+                    members.push((key, self.value(depth + 1)?));
+                }
+
+                json::Value::Object(members)
+            },
+
+            _ => return Err(invalid_value)
+        };
+
+        Ok(value)
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        let offset = self.offset;
+
+        match str::from_utf8(self.length_and_bytes()?) {
+            Ok(string) => Ok(String::from(string)),
+            Err(_) => Err(Error { kind: ErrorKind::InvalidValue, offset })
+        }
+    }
+
+    /// Read a gap and a length, and return the corresponding slice of
+    /// the input.
+    fn span(&mut self) -> Result<Input<'a>, Error> {
+        let offset = self.offset;
+        let gap = self.varint()?;
+        let length = self.varint()?;
+
+        let start = if gap % 2 == 0 {
+            advance_utf16(self.input, self.byte_cursor, gap / 2)
+        } else {
+            retreat_utf16(self.input, self.byte_cursor, gap / 2 + 1)
+        };
+        let end = start.and_then(|start| advance_utf16(self.input, start, length));
+
+        match (start, end) {
+            (Some(start), Some(end)) => {
+                self.byte_cursor = end;
+
+                Ok(&self.input[start..end])
+            },
+
+            _ => Err(Error { kind: ErrorKind::InvalidSpan, offset })
+        }
+    }
+}
+
+/// Advance `offset` in `input` by `units` UTF-16 code units, and
+/// return the new offset in bytes, if it is within `input`, and not in
+/// the middle of a character.
+fn advance_utf16(input: Input, mut offset: usize, mut units: usize) -> Option<usize> {
+    while units > 0 {
+        let (bytes, width) = match *input.get(offset)? {
+            0x00..=0x7f => (1, 1),
+            0xc0..=0xdf => (2, 1),
+            0xe0..=0xef => (3, 1),
+            0xf0..=0xff => (4, 2),
+            _ => return None
+        };
+
+        if width > units {
+            return None;
+        }
+
+        offset += bytes;
+        units -= width;
+    }
+
+    if offset > input.len() {
+        None
+    } else {
+        Some(offset)
+    }
+}
+
+/// Move `offset` in `input` back by `units` UTF-16 code units, and
+/// return the new offset in bytes, if it is within `input`, and not in
+/// the middle of a character.
+fn retreat_utf16(input: Input, mut offset: usize, mut units: usize) -> Option<usize> {
+    while units > 0 {
+        let start = input[..offset].iter().rposition(|&byte| !(0x80..0xc0).contains(&byte))?;
+
+        let (bytes, width) = match input[start] {
+            0x00..=0x7f => (1, 1),
+            0xc0..=0xdf => (2, 1),
+            0xe0..=0xef => (3, 1),
+            _ => (4, 2)
+        };
+
+        if start + bytes != offset || width > units {
+            return None;
+        }
+
+        offset = start;
+        units -= width;
+    }
+
+    Some(offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{output, root};
+
+    fn encode_root(input: Input) -> Vec<u8> {
+        let (_remaining, nodes) = root(input).unwrap();
+        let mut encoding = vec![];
+
+        encode(input, &nodes, None, &mut encoding);
+
+        encoding
+    }
+
+    fn encode_root_with_attribute_values(input: Input) -> Vec<u8> {
+        let (_remaining, nodes) = root(input).unwrap();
+        let mut encoding = vec![];
+
+        encode_with_options(input, &nodes, None, &EncodeOptions { attribute_values: true }, &mut encoding);
+
+        encoding
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(encode_root(b""), vec![VERSION, 0, STATUS_PARSED, 0, 0]);
+        assert_eq!(encode_root_with_attribute_values(b""), vec![VERSION, FLAG_ATTRIBUTE_VALUES, STATUS_PARSED, 0, 0]);
+        assert_eq!(decode(b"", &[VERSION, 0, STATUS_PARSED, 0, 0]), Ok((vec![], None)));
+    }
+
+    #[test]
+    fn test_error() {
+        let input = "é<!-- wp:foo -->".as_bytes();
+        let (remaining, nodes) = root(input).unwrap();
+        let parse_error = error::diagnose(input, remaining);
+        let mut encoding = vec![];
+
+        encode(input, &nodes, parse_error, &mut encoding);
+
+        let message = error::ErrorKind::UnclosedBlock.description();
+        let mut expected = vec![VERSION, 0, STATUS_FAILED, 14];
+        expected.extend_from_slice(b"unclosed-block");
+        expected.extend_from_slice(&[1, message.len() as u8]);
+        expected.extend_from_slice(message.as_bytes());
+        expected.extend_from_slice(&[0, 1, TAG_PHRASE, 0, 1]);
+
+        assert_eq!(encoding, expected);
+        assert_eq!(
+            decode(input, &encoding),
+            Ok((vec![Node::Phrase("é".as_bytes())], Some(error::Error { kind: error::ErrorKind::UnclosedBlock, offset: 2 })))
+        );
+    }
+
+    #[test]
+    fn test_string_table_is_deduplicated() {
+        let input = &b"<!-- wp:foo /--><!-- wp:ns/bar --><!-- wp:foo /--><!-- /wp:ns/bar -->"[..];
+
+        assert_eq!(
+            encode_root(input),
+            vec![
+                VERSION,
+                0,
+                STATUS_PARSED,
+                2, 8, b'c', b'o', b'r', b'e', b'/', b'f', b'o', b'o', 6, b'n', b's', b'/', b'b', b'a', b'r',
+                2,
+                TAG_BLOCK, 0, 0,
+                TAG_BLOCK, 1, 1,
+                TAG_BLOCK, 0, 0,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spans_are_counted_in_utf16_code_units() {
+        let input = "é😀<!-- wp:foo {\"a\":\"ß\"} /-->x".as_bytes();
+        let encoding = encode_root(input);
+
+        assert_eq!(
+            &encoding[13..],
+            &[
+                3,
+                TAG_PHRASE, 0, 3,
+                TAG_BLOCK_WITH_ATTRIBUTES, 0, 24, 9, 0,
+                TAG_PHRASE, 10, 1,
+            ][..]
+        );
+
+        assert_eq!(
+            decode(input, &encoding),
+            Ok((
+                vec![
+                    Node::Phrase("é😀".as_bytes()),
+                    Node::Block {
+                        name: BlockName::new(&encoding[5..9], &encoding[10..13]),
+                        attributes: Some(Attributes::Raw("{\"a\":\"ß\"}".as_bytes())),
+                        children: vec![]
+                    },
+                    Node::Phrase(b"x")
+                ],
+                None
+            ))
+        );
+    }
+
+    #[test]
+    fn test_attribute_values() {
+        let input = &b"<!-- wp:foo {\"a\":[1.5,null,true],\"b\":\"\\u00e9\"} /--><!-- wp:foo {\"a\":} /-->"[..];
+        let encoding = encode_root_with_attribute_values(input);
+
+        assert_eq!(
+            &encoding[13..],
+            &[
+                2,
+                TAG_BLOCK_WITH_ATTRIBUTES, 0,
+                    VALUE_OBJECT, 2,
+                        1, b'a', VALUE_ARRAY, 3, VALUE_NUMBER, 3, b'1', b'.', b'5', VALUE_NULL, VALUE_TRUE,
+                        1, b'b', VALUE_STRING, 2, 0xc3, 0xa9,
+                    0,
+                TAG_BLOCK_WITH_ATTRIBUTES, 0, VALUE_RAW, 126, 6, 0,
+            ][..]
+        );
+
+        assert_eq!(
+            decode(input, &encoding),
+            Ok((
+                vec![
+                    Node::Block {
+                        name: BlockName::new(&encoding[5..9], &encoding[10..13]),
+                        attributes: Some(Attributes::Value(json::Value::Object(vec![
+                            (
+                                String::from("a"),
+                                json::Value::Array(vec![json::Value::Number(b"1.5"), json::Value::Null, json::Value::Boolean(true)])
+                            ),
+                            (String::from("b"), json::Value::String(String::from("é")))
+                        ]))),
+                        children: vec![]
+                    },
+                    Node::Block {
+                        name: BlockName::new(&encoding[5..9], &encoding[10..13]),
+                        attributes: Some(Attributes::Raw(b"{\"a\":}")),
+                        children: vec![]
+                    }
+                ],
+                None
+            ))
+        );
+    }
+
+    #[test]
+    fn test_overlapping_spans() {
+        let input = "é<!-- wp:foo -->😀<!-- wp:bar -->x".as_bytes();
+        let nodes = output::parse(input);
+        let mut encoding = vec![];
+
+        encode(input, &nodes, None, &mut encoding);
+
+        // The phrase `é`, before `foo`, follows `bar`, which ends
+        // after it: Its gap is negative.
+        assert_eq!(
+            &encoding[22..],
+            &[
+                4,
+                TAG_PHRASE, 32, 2,
+                TAG_BLOCK, 0, 1, TAG_PHRASE, 30, 1,
+                TAG_PHRASE, 67, 1,
+                TAG_BLOCK, 1, 1, TAG_PHRASE, 30, 18,
+            ][..]
+        );
+
+        assert_eq!(
+            decode(input, &encoding),
+            Ok((
+                vec![
+                    Node::Phrase("😀".as_bytes()),
+                    Node::Block {
+                        name: BlockName::new(&encoding[5..9], &encoding[10..13]),
+                        attributes: None,
+                        children: vec![Node::Phrase(b"x")]
+                    },
+                    Node::Phrase("é".as_bytes()),
+                    Node::Block {
+                        name: BlockName::new(&encoding[14..18], &encoding[19..22]),
+                        attributes: None,
+                        children: vec![Node::Phrase("😀<!-- wp:bar -->x".as_bytes())]
+                    }
+                ],
+                None
+            ))
+        );
+    }
+
+    #[test]
+    fn test_large_varint() {
+        let mut output = vec![];
+
+        push_varint(300, &mut output);
+
+        assert_eq!(output, vec![0xac, 0x02]);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(decode(b"", &[]), Err(Error { kind: ErrorKind::UnexpectedEnd, offset: 0 }));
+        assert_eq!(decode(b"", &[VERSION - 1, 0, STATUS_PARSED, 0, 0]), Err(Error { kind: ErrorKind::UnsupportedVersion, offset: 0 }));
+        assert_eq!(decode(b"", &[VERSION, 2, STATUS_PARSED, 0, 0]), Err(Error { kind: ErrorKind::UnsupportedFlags, offset: 1 }));
+        assert_eq!(decode(b"", &[VERSION, 0, 2, 0, 0]), Err(Error { kind: ErrorKind::InvalidStatus, offset: 2 }));
+        assert_eq!(decode(b"", &[VERSION, 0, STATUS_FAILED, 3, b'f', b'o', b'o', 0, 0, 0, 0]), Err(Error { kind: ErrorKind::InvalidError, offset: 3 }));
+        assert_eq!(decode(b"", &[VERSION, 0, STATUS_FAILED, 7, b'u', b'n', b'k', b'n', b'o', b'w', b'n', 1, 0, 0, 0]), Err(Error { kind: ErrorKind::InvalidError, offset: 3 }));
+        assert_eq!(decode(b"", &[VERSION, 0, STATUS_PARSED, 0, 1, 7]), Err(Error { kind: ErrorKind::InvalidTag, offset: 5 }));
+        assert_eq!(decode(b"", &[VERSION, 0, STATUS_PARSED, 0, 1, TAG_BLOCK, 0, 0]), Err(Error { kind: ErrorKind::InvalidName, offset: 6 }));
+        assert_eq!(decode(b"", &[VERSION, 0, STATUS_PARSED, 1, 3, b'f', b'o', b'o', 0]), Err(Error { kind: ErrorKind::InvalidName, offset: 4 }));
+        assert_eq!(decode(b"ab", &[VERSION, 0, STATUS_PARSED, 0, 1, TAG_PHRASE, 2, 2]), Err(Error { kind: ErrorKind::InvalidSpan, offset: 6 }));
+        assert_eq!(decode(b"ab", &[VERSION, 0, STATUS_PARSED, 0, 1, TAG_PHRASE, 1, 1]), Err(Error { kind: ErrorKind::InvalidSpan, offset: 6 }));
+        assert_eq!(decode("é".as_bytes(), &[VERSION, 0, STATUS_PARSED, 0, 2, TAG_PHRASE, 0, 1, TAG_PHRASE, 1, 1]), Ok((vec![Node::Phrase("é".as_bytes()), Node::Phrase("é".as_bytes())], None)));
+        assert_eq!(decode("é".as_bytes(), &[VERSION, 0, STATUS_PARSED, 0, 1, TAG_PHRASE, 0, 1, 0]), Err(Error { kind: ErrorKind::TrailingData, offset: 8 }));
+        assert_eq!(decode(b"", &[VERSION, 0, STATUS_PARSED, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]), Err(Error { kind: ErrorKind::InvalidVarint, offset: 3 }));
+        assert_eq!(
+            decode(b"", &[VERSION, FLAG_ATTRIBUTE_VALUES, STATUS_PARSED, 1, 8, b'c', b'o', b'r', b'e', b'/', b'f', b'o', b'o', 1, TAG_BLOCK_WITH_ATTRIBUTES, 0, 8, 0]),
+            Err(Error { kind: ErrorKind::InvalidValue, offset: 16 })
+        );
+        assert_eq!(
+            decode(b"", &[VERSION, FLAG_ATTRIBUTE_VALUES, STATUS_PARSED, 1, 8, b'c', b'o', b'r', b'e', b'/', b'f', b'o', b'o', 1, TAG_BLOCK_WITH_ATTRIBUTES, 0, VALUE_STRING, 1, 0xff, 0]),
+            Err(Error { kind: ErrorKind::InvalidValue, offset: 17 })
+        );
+    }
+}
@@ -0,0 +1,166 @@
+#![cfg(feature = "bin")]
+
+extern crate gutenberg_post_parser;
+extern crate serde_json;
+
+use gutenberg_post_parser::root;
+
+use std::env;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::ffi::OsStr;
+use std::process::{Command, Output, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Run the executable with `arguments`, and `input` on `stdin`.
+fn run(arguments: &[&str], input: &str) -> Output {
+    let mut child =
+        Command::new(env!("CARGO_BIN_EXE_gutenberg-post-parser"))
+        .args(arguments)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute `gutenberg-post-parser`.");
+
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn exit_code_is_0_when_everything_is_parsed() {
+    let output = run(&[], "<!-- wp:foo /-->");
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        r#"[{"blockName":"core/foo","attrs":{},"innerBlocks":[],"innerHTML":"","innerContent":[]}]"#
+    );
+}
+
+#[test]
+fn exit_code_is_1_when_an_input_cannot_be_read() {
+    let output = run(&["./tests/fixtures/does-not-exist.html"], "");
+
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn exit_code_is_2_when_some_data_are_not_recognized() {
+    let output = run(&[], "a<!-- wp:foo -->b");
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr).unwrap().starts_with("error[unclosed-block]: "));
+}
+
+#[test]
+fn exit_code_is_3_when_some_data_are_not_formatted() {
+    assert_eq!(run(&["fmt", "--check"], "<!--wp:foo   /-->").status.code(), Some(3));
+    assert_eq!(run(&["fmt", "--check"], "<!-- wp:foo /-->").status.code(), Some(0));
+}
+
+#[test]
+fn exit_code_is_4_when_some_findings_are_errors() {
+    let output = run(&["lint"], "<!-- wp:image --><figure><img src=\"a.png\"/></figure><!-- /wp:image -->");
+
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8(output.stdout).unwrap().starts_with("error[image-alt]: "));
+}
+
+#[test]
+fn json_output_is_the_json_fixtures() {
+    let fixtures_directory = Path::new("./tests/fixtures");
+    let html = OsStr::new("html");
+
+    for entry in fs::read_dir(fixtures_directory).unwrap() {
+        let entry = entry.unwrap();
+        let input_path = entry.path();
+
+        if let Some(extension) = input_path.extension() {
+            if extension == html {
+                let output = run(&["--emit-json", input_path.to_str().unwrap()], "");
+
+                // Malformed fixtures are recovered, but not recognized.
+                let input = fs::read(&input_path).unwrap();
+                let (remaining, _nodes) = root(&input).unwrap();
+                let exit_code = if remaining.is_empty() { 0 } else { 2 };
+
+                assert_eq!(output.status.code(), Some(exit_code), "file = {:?}", input_path);
+
+                let expected: serde_json::Value = serde_json::from_str(&fs::read_to_string(input_path.with_extension("json")).unwrap()).unwrap();
+                let output: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+                assert!(expected == output, "file = {:?}", input_path);
+            }
+        }
+    }
+}
+
+#[test]
+fn watch_parses_a_file_again_when_it_changes() {
+    let mut input_path = env::temp_dir();
+    input_path.push("gutenberg-post-parser-watch.html");
+
+    fs::write(&input_path, "<!-- wp:foo /-->").unwrap();
+
+    let mut child =
+        Command::new(env!("CARGO_BIN_EXE_gutenberg-post-parser"))
+        .args(["watch", "--interval", "20", input_path.to_str().unwrap()])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to execute `gutenberg-post-parser`.");
+
+    let mut stdout = child.stdout.take().unwrap();
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(
+        move || {
+            let mut buffer = [0; 1024];
+
+            while let Ok(length) = stdout.read(&mut buffer) {
+                if length == 0 || sender.send(buffer[..length].to_vec()).is_err() {
+                    break;
+                }
+            }
+        }
+    );
+
+    let mut output = vec![];
+    let mut wait_for = |name: &str| {
+        let deadline = Instant::now() + Duration::from_secs(10);
+
+        while !String::from_utf8_lossy(&output).contains(name) {
+            let timeout = deadline.checked_duration_since(Instant::now()).unwrap_or_default();
+
+            match receiver.recv_timeout(timeout) {
+                Ok(bytes) => output.extend(bytes),
+                Err(_) => return false
+            }
+        }
+
+        true
+    };
+
+    let parsed = wait_for("core/foo");
+
+    // Same length, and same modification time if the file system is
+    // coarse enough: Only the content tells the change.
+    let modified = fs::metadata(&input_path).unwrap().modified().unwrap();
+
+    fs::write(&input_path, "<!-- wp:bar /-->").unwrap();
+    fs::File::options().write(true).open(&input_path).unwrap().set_modified(modified).unwrap();
+
+    let parsed_again = wait_for("core/bar");
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+    fs::remove_file(&input_path).unwrap();
+
+    assert!(parsed, "The file is not parsed.");
+    assert!(parsed_again, "The file is not parsed again after a change.");
+}
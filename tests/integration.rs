@@ -1,6 +1,6 @@
 extern crate gutenberg_post_parser;
 
-use gutenberg_post_parser::root;
+use gutenberg_post_parser::{ast::Node, error, json, output, root, wire};
 
 use std::fs;
 use std::path::Path;
@@ -33,3 +33,167 @@ fn compare_html_to_parser_output() {
         }
     }
 }
+
+#[cfg(feature = "parallel")]
+#[test]
+fn compare_parallel_parser_output_to_parser_output() {
+    use gutenberg_post_parser::parallel;
+
+    let fixtures_directory = Path::new("./tests/fixtures");
+    let html = OsStr::new("html");
+
+    for entry in fs::read_dir(fixtures_directory).unwrap() {
+        let entry = entry.unwrap();
+        let input_path = entry.path();
+
+        if let Some(extension) = input_path.extension() {
+            if extension == html {
+                let html_content = fs::read_to_string(&input_path).unwrap();
+
+                assert!(
+                    parallel::root(html_content.as_bytes()) == root(html_content.as_bytes()),
+                    "The parallel parser output for {:?} differs from the parser output.",
+                    input_path
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn decode_wire_encoding_to_parser_output() {
+    fn assert_equivalent(left: &[wire::Node], right: &[Node], attribute_values: bool, input_path: &Path) {
+        assert_eq!(left.len(), right.len(), "The number of nodes differs for {:?}.", input_path);
+
+        for (left, right) in left.iter().zip(right) {
+            match (left, right) {
+                (wire::Node::Phrase(left), Node::Phrase(right)) => assert_eq!(left, right, "A phrase differs for {:?}.", input_path),
+
+                (
+                    wire::Node::Block { name: left_name, attributes: left_attributes, children: left_children },
+                    Node::Block { name: right_name, attributes: right_attributes, children: right_children }
+                ) => {
+                    assert!(left_name.is_equivalent_to(right_name), "A block name differs for {:?}.", input_path);
+
+                    match (left_attributes, right_attributes) {
+                        (None, None) => (),
+
+                        (Some(wire::Attributes::Raw(left)), Some(right)) => {
+                            assert_eq!(left, right, "Block attributes differ for {:?}.", input_path);
+                            assert!(!attribute_values || json::parse(right).is_err(), "Block attributes are not decoded for {:?}.", input_path);
+                        },
+
+                        (Some(wire::Attributes::Value(left)), Some(right)) => {
+                            assert!(attribute_values, "Block attributes are unexpectedly decoded for {:?}.", input_path);
+                            assert_eq!(Ok(left), json::parse(right).as_ref(), "Block attribute values differ for {:?}.", input_path);
+                        },
+
+                        _ => panic!("Block attributes differ for {:?}.", input_path)
+                    }
+
+                    assert_equivalent(left_children, right_children, attribute_values, input_path);
+                },
+
+                _ => panic!("A node kind differs for {:?}.", input_path)
+            }
+        }
+    }
+
+    let fixtures_directory = Path::new("./tests/fixtures");
+    let html = OsStr::new("html");
+
+    for entry in fs::read_dir(fixtures_directory).unwrap() {
+        let entry = entry.unwrap();
+        let input_path = entry.path();
+
+        if let Some(extension) = input_path.extension() {
+            if extension == html {
+                let html_content = fs::read_to_string(&input_path).unwrap();
+                let input = html_content.as_bytes();
+                let (remaining, nodes) = root(input).unwrap();
+                let parse_error = error::diagnose(input, remaining);
+
+                for &attribute_values in &[false, true] {
+                    let mut encoding = vec![];
+
+                    wire::encode_with_options(input, &nodes, parse_error, &wire::EncodeOptions { attribute_values }, &mut encoding);
+
+                    let (decoded_nodes, decoded_error) = wire::decode(input, &encoding).unwrap();
+
+                    assert_equivalent(&decoded_nodes, &nodes, attribute_values, &input_path);
+                    assert_eq!(decoded_error, parse_error, "The error differs for {:?}.", input_path);
+                }
+
+                // The recovered nodes of a malformed input can overlap.
+                let recovered_nodes = output::parse(input);
+                let mut encoding = vec![];
+
+                wire::encode(input, &recovered_nodes, None, &mut encoding);
+
+                let (decoded_nodes, _) = wire::decode(input, &encoding).unwrap();
+
+                assert_equivalent(&decoded_nodes, &recovered_nodes, false, &input_path);
+            }
+        }
+    }
+}
+
+#[test]
+fn compare_canonical_output_to_json_fixtures() {
+    // Numbers are compared by value, since the fixtures are serialized
+    // by JavaScript, e.g. `1.0` becomes `1`.
+    fn assert_same_json(left: &json::Value, right: &json::Value, input_path: &Path) {
+        match (left, right) {
+            (json::Value::Number(left), json::Value::Number(right)) => {
+                let left: f64 = std::str::from_utf8(left).unwrap().parse().unwrap();
+                let right: f64 = std::str::from_utf8(right).unwrap().parse().unwrap();
+
+                assert_eq!(left, right, "A number differs for {:?}.", input_path);
+            },
+
+            (json::Value::Array(left), json::Value::Array(right)) => {
+                assert_eq!(left.len(), right.len(), "The length of an array differs for {:?}.", input_path);
+
+                for (left, right) in left.iter().zip(right) {
+                    assert_same_json(left, right, input_path);
+                }
+            },
+
+            (json::Value::Object(left), json::Value::Object(right)) => {
+                assert_eq!(left.len(), right.len(), "The size of an object differs for {:?}.", input_path);
+
+                for ((left_key, left), (right_key, right)) in left.iter().zip(right) {
+                    assert_eq!(left_key, right_key, "A key differs for {:?}.", input_path);
+                    assert_same_json(left, right, input_path);
+                }
+            },
+
+            (left, right) => assert_eq!(left, right, "A value differs for {:?}.", input_path)
+        }
+    }
+
+    let fixtures_directory = Path::new("./tests/fixtures");
+    let html = OsStr::new("html");
+
+    for entry in fs::read_dir(fixtures_directory).unwrap() {
+        let entry = entry.unwrap();
+        let input_path = entry.path();
+
+        if let Some(extension) = input_path.extension() {
+            if extension == html {
+                let html_content = fs::read_to_string(&input_path).unwrap();
+                let nodes = output::parse(html_content.as_bytes());
+                let output = output::serialize(&output::blocks(&nodes));
+
+                let expected_path = input_path.as_path().with_extension("json");
+                let expected_content = fs::read_to_string(&expected_path).unwrap();
+
+                assert_same_json(
+                    &json::parse(output.as_bytes()).unwrap(),
+                    &json::parse(expected_content.as_bytes()).unwrap(),
+                    &input_path
+                );
+            }
+        }
+    }
+}